@@ -0,0 +1,150 @@
+/*
+Benchmarks for table generation, search and applying turns, run with `cargo bench`.
+
+Criterion reports the median and spread of each benchmark, and the throughput of the ones that apply turns. To
+check for regressions, save a baseline before a change with `cargo bench -- --save-baseline before` and compare
+against it afterwards with `cargo bench -- --baseline before`. Loading the tables in each file format is compared
+when the serde-tables feature is on, with `cargo bench --features serde-tables`.
+*/
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::hint::black_box;
+
+use fto_scramble::coordinates::CoordinateType;
+use fto_scramble::movedefs::{Face, Turn};
+use fto_scramble::movetables::{MoveTable, MoveTables};
+use fto_scramble::pruningtables::SimplePruningTable;
+use fto_scramble::search::search_phase_1;
+use fto_scramble::state::{CoordState, RawState, SOLVED_COORD_STATE};
+
+
+const NUM_SEARCH_STATES: usize = 100;
+const SEARCH_DEPTH: u8 = 7;
+const SEED: u64 = 2023;
+
+
+fn bench_move_table_populate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("MoveTable::populate");
+    group.sample_size(10);
+    for coord_type in CoordinateType::iter() {
+        group.throughput(Throughput::Elements(coord_type.get_size() as u64));
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", coord_type)), |b| {
+            b.iter(|| MoveTable::new(black_box(coord_type)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_pruning_table_populate(c: &mut Criterion) {
+    let move_tables = MoveTables::generate(&mut |_| {});
+    let mut group = c.benchmark_group("SimplePruningTable::populate");
+    group.sample_size(10);
+    for coord_type in CoordinateType::iter() {
+        group.throughput(Throughput::Elements(coord_type.get_size() as u64));
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", coord_type)), |b| {
+            b.iter(|| {
+                let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+                pruning_tables.populate_coordinate_type(&move_tables, black_box(coord_type));
+                pruning_tables
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_search_phase_1(c: &mut Criterion) {
+    let move_tables = MoveTables::generate(&mut |_| {});
+    let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+    pruning_tables.populate(&move_tables);
+
+    // Scrambling with as many moves as the search depth means every state has a solution within reach
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let states: Vec<CoordState> = (0..NUM_SEARCH_STATES)
+        .map(|_| SOLVED_COORD_STATE.perturb(&move_tables, SEARCH_DEPTH, &mut rng))
+        .collect();
+
+    let mut group = c.benchmark_group("search_phase_1");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(NUM_SEARCH_STATES as u64));
+    group.bench_function(BenchmarkId::new("depth", SEARCH_DEPTH), |b| {
+        b.iter(|| {
+            for state in states.iter() {
+                black_box(search_phase_1(state, &move_tables, &pruning_tables, SEARCH_DEPTH, None));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_apply(c: &mut Criterion) {
+    let move_tables = MoveTables::generate(&mut |_| {});
+    let turns = Turn::get_all_turns();
+
+    let mut group = c.benchmark_group("apply");
+    group.throughput(Throughput::Elements(turns.len() as u64));
+    group.bench_function("CoordState", |b| {
+        let mut state = SOLVED_COORD_STATE;
+        b.iter(|| {
+            for turn in turns.iter() {
+                state.apply(&move_tables, black_box(turn));
+            }
+        })
+    });
+    let lookup_table = move_tables.build_lookup_table();
+    group.bench_function("FlatLookupTable", |b| {
+        let mut state = SOLVED_COORD_STATE;
+        b.iter(|| {
+            for turn in turns.iter() {
+                lookup_table.apply(&mut state, black_box(turn));
+            }
+        })
+    });
+    group.bench_function("RawState", |b| {
+        let mut state = RawState::solved();
+        b.iter(|| {
+            for turn in turns.iter() {
+                state.apply(black_box(turn));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// Loads the tables saved in the original format and in the serde format, and prints the size of each.
+#[cfg(feature = "serde-tables")]
+fn bench_table_formats(c: &mut Criterion) {
+    let move_tables = MoveTables::generate(&mut |_| {});
+    let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+    pruning_tables.populate(&move_tables);
+
+    let formats = [
+        ("MoveTables", "original", move_tables.to_bytes()),
+        ("MoveTables", "serde", move_tables.to_serde_bytes()),
+        ("SimplePruningTable", "original", pruning_tables.to_bytes()),
+        ("SimplePruningTable", "serde", pruning_tables.to_serde_bytes()),
+    ];
+    for (tables, format, bytes) in formats.iter() {
+        println!("{} in the {} format: {} bytes", tables, format, bytes.len());
+    }
+
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    for (tables, format, bytes) in formats.iter() {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(BenchmarkId::new(*tables, format), |b| {
+            b.iter(|| match *tables {
+                "MoveTables" => black_box(MoveTables::from_bytes(black_box(bytes)).is_ok()),
+                _ => black_box(SimplePruningTable::from_bytes(black_box(bytes)).is_ok()),
+            })
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "serde-tables"))]
+criterion_group!(benches, bench_move_table_populate, bench_pruning_table_populate, bench_search_phase_1, bench_apply);
+#[cfg(feature = "serde-tables")]
+criterion_group!(benches, bench_move_table_populate, bench_pruning_table_populate, bench_search_phase_1, bench_apply, bench_table_formats);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+/*
+Benchmarks for converting between piece positions and coordinates, run with `cargo bench --bench coord_encoding`.
+
+These conversions run for every coordinate and every turn while the move tables are generated, so they need to
+take only a few nanoseconds each. Each coordinate type is timed in both directions on a fixed input, through
+`CoordinateType::state_to_coord` and `CoordinateType::coord_to_state`. EdgeInFace and the centres go through
+`face_position_to_coord` and `invert_coord_to_face_positions`, and EdgeAcrossFaces through `perm_across_face_coord`
+and `invert_coord_to_perm_across_face`. `coord_to_state` returns a `Vec`, so its times include the allocation.
+
+Save a baseline before trying an optimisation with `cargo bench --bench coord_encoding -- --save-baseline before`
+and compare against it afterwards with `--baseline before`.
+*/
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+use fto_scramble::coordinates::CoordinateType;
+
+
+/// Picks a coordinate away from solved, where the positions aren't in order, so the conversions have real work to do.
+fn get_fixed_coord(coord_type: CoordinateType) -> u32 {
+    (coord_type.get_size() * 2 / 3) as u32 + 1
+}
+
+fn bench_state_to_coord(c: &mut Criterion) {
+    let mut group = c.benchmark_group("state_to_coord");
+    group.throughput(Throughput::Elements(1));
+    for coord_type in CoordinateType::iter() {
+        let state = coord_type.coord_to_state(get_fixed_coord(coord_type));
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", coord_type)), |b| {
+            b.iter(|| coord_type.state_to_coord(black_box(&state)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_coord_to_state(c: &mut Criterion) {
+    let mut group = c.benchmark_group("coord_to_state");
+    group.throughput(Throughput::Elements(1));
+    for coord_type in CoordinateType::iter() {
+        let coord = get_fixed_coord(coord_type);
+        group.bench_function(BenchmarkId::from_parameter(format!("{:?}", coord_type)), |b| {
+            b.iter(|| coord_type.coord_to_state(black_box(coord)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_state_to_coord, bench_coord_to_state);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+/*
+Solves scrambles in a web page. Browsers have no filesystem and generating the tables takes minutes, so the page
+fetches the files written by `fto-scramble gen-tables` and hands their contents to `load_tables` before solving.
+Build it with
+
+    cargo build --release --example wasm_solver --target wasm32-unknown-unknown
+    wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/examples/wasm_solver.wasm
+
+and use it from JavaScript with
+
+    const tables = await Promise.all(["movetables.dat", "pruningtables.dat"].map(async (name) =>
+        new Uint8Array(await (await fetch(name)).arrayBuffer())));
+    load_tables(...tables);
+    console.log(solve("U R' BL"));
+
+Only phase 1 of the solver exists so far, so the solution only solves phase 1.
+*/
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use fto_scramble::movedefs::Turn;
+use fto_scramble::movetables::MoveTables;
+use fto_scramble::pruningtables::SimplePruningTable;
+use fto_scramble::search::solve_phase_1;
+use fto_scramble::state::CoordState;
+
+
+const MAX_DEPTH: u8 = 9;
+
+thread_local! {
+    // A web page runs on a single thread, so the tables are kept for it to use on every call
+    static TABLES: RefCell<Option<(MoveTables, SimplePruningTable)>> = const { RefCell::new(None) };
+}
+
+/// Reads the contents of `movetables.dat` and `pruningtables.dat`, which must be done before solving anything.
+#[wasm_bindgen]
+pub fn load_tables(move_tables: &[u8], pruning_tables: &[u8]) -> Result<(), JsError> {
+    let move_tables = MoveTables::from_bytes(move_tables)?;
+    if !move_tables.all_tables_generated() {
+        return Err(JsError::new(&format!("the move tables are missing {:?}", move_tables.missing_tables())));
+    }
+    let pruning_tables = SimplePruningTable::from_bytes(pruning_tables)?;
+    TABLES.with(|tables| *tables.borrow_mut() = Some((move_tables, pruning_tables)));
+    Ok(())
+}
+
+/// Finds a phase 1 solution for a scramble such as "U R' BL", with the turns separated by spaces.
+#[wasm_bindgen]
+pub fn solve(scramble: &str) -> Result<String, JsError> {
+    let scramble = Turn::parse_sequence(scramble)?;
+    TABLES.with(|tables| {
+        let tables = tables.borrow();
+        let (move_tables, pruning_tables) = tables.as_ref()
+            .ok_or_else(|| JsError::new("load_tables has to be called before solving"))?;
+
+        let mut state = CoordState::solved();
+        for turn in scramble.iter() {
+            state.apply(move_tables, turn);
+        }
+        let solution = solve_phase_1(&state, move_tables, pruning_tables, MAX_DEPTH, false)
+            .ok_or_else(|| JsError::new(&format!("there is no solution within {} turns", MAX_DEPTH)))?;
+        Ok(Turn::format_sequence(&solution))
+    })
+}
@@ -0,0 +1,21 @@
+/*
+Feeds arbitrary bytes to CoordState::from_bytes, which is what reads states from untrusted input. Any state it
+accepts has to write back to the same bytes, and has to convert to a raw state without panicking, even when the
+coordinates combine into a state that can't be reached.
+
+    cargo fuzz run fuzz_coord_state
+*/
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use fto_scramble::state::CoordState;
+
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(state) = CoordState::from_bytes(data) {
+        assert_eq!(state.to_bytes().as_slice(), data);
+        let _ = state.to_raw();
+        let _ = state.try_to_raw();
+    }
+});
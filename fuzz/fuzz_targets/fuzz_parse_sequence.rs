@@ -0,0 +1,21 @@
+/*
+Feeds arbitrary text to Turn::parse_sequence, which reads scrambles and solutions typed by users. Whatever it
+accepts has to read back the same once written out again, and any sequence of turns has to survive being written
+and read back.
+
+    cargo fuzz run fuzz_parse_sequence
+*/
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use fto_scramble::movedefs::Turn;
+
+
+fuzz_target!(|input: (&str, Vec<Turn>)| {
+    let (text, turns) = input;
+    if let Ok(parsed) = Turn::parse_sequence(text) {
+        assert_eq!(Turn::parse_sequence(&Turn::format_sequence(&parsed)), Ok(parsed));
+    }
+    assert_eq!(Turn::parse_sequence(&Turn::format_sequence(&turns)), Ok(turns));
+});
@@ -0,0 +1,26 @@
+/*
+Feeds arbitrary raw states to RawState::validate and the coordinate conversions, and arbitrary coordinates to
+CoordState::try_to_raw. A state that passes the checks has to convert to the other representation and back without
+changing, and nothing is allowed to panic whether it passes or not.
+
+    cargo fuzz run fuzz_raw_state
+*/
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use fto_scramble::state::{CoordState, RawState};
+
+
+fuzz_target!(|input: (RawState, CoordState)| {
+    let (raw, coords) = input;
+    if raw.validate().is_ok() {
+        let raw_coords = raw.try_to_coords().expect("a valid state converts to coordinates");
+        assert_eq!(raw_coords.to_raw(), raw);
+    } else {
+        assert!(raw.try_to_coords().is_err());
+    }
+    if let Ok(coords_raw) = coords.try_to_raw() {
+        assert_eq!(coords_raw.to_coords(), coords);
+    }
+});
@@ -0,0 +1,82 @@
+#ifndef FTO_SCRAMBLE_H
+#define FTO_SCRAMBLE_H
+
+/* Generated by cbindgen from src/ffi.rs. Regenerate it rather than editing it by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define FTO_ERR_NULL_POINTER -1
+
+#define FTO_ERR_INVALID_UTF8 -2
+
+#define FTO_ERR_TABLES -3
+
+#define FTO_ERR_NOTATION -4
+
+#define FTO_ERR_NO_SOLUTION -5
+
+#define FTO_ERR_BUFFER_TOO_SMALL -6
+
+#define FTO_ERR_PANIC -7
+
+/*
+ The tables and random number generator used by the other functions. C code only ever sees a pointer to it.
+ */
+typedef struct FtoContext FtoContext;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Loads the tables from `tables_dir`, as filled by `fto-scramble gen-tables`, or generates them if `tables_dir`
+ is `NULL`, which takes several minutes. Returns `NULL` if the tables couldn't be loaded.
+
+ # Safety
+
+ `tables_dir` must be `NULL` or point to a null terminated string.
+ */
+FtoContext *fto_init(const char *tables_dir);
+
+/*
+ Writes a phase 1 solution for `scramble`, such as "U R' BL", to `out_buf` as turns separated by spaces, and
+ returns its length in bytes, not counting the terminator.
+
+ # Safety
+
+ `ctx` must come from `fto_init` and not have been freed, `scramble` must point to a null terminated string,
+ and `out_buf` must point to at least `out_len` writable bytes.
+ */
+int32_t fto_solve(FtoContext *ctx, const char *scramble, char *out_buf, size_t out_len);
+
+/*
+ Writes a random move scramble to `out_buf`, and returns its length in bytes, not counting the terminator.
+
+ # Safety
+
+ `ctx` must come from `fto_init` and not have been freed, and `out_buf` must point to at least `out_len`
+ writable bytes.
+ */
+int32_t fto_scramble(FtoContext *ctx, char *out_buf, size_t out_len);
+
+/*
+ Releases a context. Passing `NULL` does nothing.
+
+ # Safety
+
+ `ctx` must be `NULL` or come from `fto_init`, and must not be used again afterwards.
+ */
+void fto_free(FtoContext *ctx);
+
+/*
+ Describes the last failure on the calling thread, or is empty if nothing has failed. The string belongs to the
+ library, and stays valid until the next call that fails on the same thread.
+ */
+const char *fto_last_error_message(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FTO_SCRAMBLE_H */
@@ -0,0 +1,182 @@
+/*
+Times phase 1 solves of a fixed set of states, so that the effect of a change on performance can be measured by
+running the same benchmark before and after it. The states come from a seeded random number generator, which makes
+the numbers comparable between commits as long as the seed, number of cases and depth stay the same.
+*/
+
+use std::time::{Duration, Instant};
+
+use log::debug;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::movetables::MoveTables;
+use crate::pruningtables::SimplePruningTable;
+use crate::search::solve_phase_1_with_stats;
+use crate::state::{CoordState, SOLVED_COORD_STATE};
+
+
+pub const DEFAULT_SEED: u64 = 2023;
+pub const DEFAULT_CASES: usize = 100;
+pub const DEFAULT_DEPTH: u8 = 7;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    pub seed: u64,
+    /// How many states to solve.
+    pub cases: usize,
+    /// How many random turns scramble each state, which is also the search depth, so every state can be solved.
+    pub depth: u8,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self { seed: DEFAULT_SEED, cases: DEFAULT_CASES, depth: DEFAULT_DEPTH }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    pub config: BenchConfig,
+    pub table_load_time: Duration,
+    /// The time taken by each solve, in the order the states were generated.
+    pub solve_times: Vec<Duration>,
+    /// The number of nodes explored by each solve.
+    pub nodes: Vec<u64>,
+    /// How many of the states had a solution within the depth.
+    pub solved: usize,
+}
+
+impl BenchReport {
+    pub fn get_total_solve_time(&self) -> Duration {
+        self.solve_times.iter().sum()
+    }
+
+    pub fn get_average_solve_time(&self) -> Duration {
+        match self.solve_times.len() {
+            0 => Duration::ZERO,
+            count => self.get_total_solve_time() / count as u32,
+        }
+    }
+
+    /// The middle solve time, or the mean of the two middle times when there is an even number of them.
+    pub fn get_median_solve_time(&self) -> Duration {
+        let mut times = self.solve_times.clone();
+        times.sort();
+        match times.len() {
+            0 => Duration::ZERO,
+            count if count % 2 == 1 => times[count / 2],
+            count => (times[count / 2 - 1] + times[count / 2]) / 2,
+        }
+    }
+
+    pub fn get_total_nodes(&self) -> u64 {
+        self.nodes.iter().sum()
+    }
+
+    pub fn get_average_nodes(&self) -> f64 {
+        match self.nodes.len() {
+            0 => 0.0,
+            count => self.get_total_nodes() as f64 / count as f64,
+        }
+    }
+
+    pub fn get_nodes_per_second(&self) -> f64 {
+        match self.get_total_solve_time().as_secs_f64() {
+            seconds if seconds > 0.0 => self.get_total_nodes() as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Generates the states for a benchmark. Each one is the solved state scrambled with `depth` random turns.
+pub fn get_bench_states(config: &BenchConfig, move_tables: &MoveTables) -> Vec<CoordState> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    (0..config.cases)
+        .map(|_| SOLVED_COORD_STATE.perturb(move_tables, config.depth, &mut rng))
+        .collect()
+}
+
+/// Runs a benchmark, timing `load_tables` as well as the solves. Loading is left to the caller, so that it can
+/// come from wherever the tables are normally kept, and any error from it is passed back.
+pub fn run<'a, E>(
+    config: &BenchConfig,
+    load_tables: impl FnOnce() -> Result<(&'a MoveTables, &'a SimplePruningTable), E>,
+) -> Result<BenchReport, E> {
+    let now = Instant::now();
+    let (move_tables, pruning_tables) = load_tables()?;
+    let table_load_time = now.elapsed();
+
+    let states = get_bench_states(config, move_tables);
+    let mut solve_times = Vec::with_capacity(states.len());
+    let mut nodes = Vec::with_capacity(states.len());
+    let mut solved = 0;
+    for state in states.iter() {
+        let now = Instant::now();
+        let result = solve_phase_1_with_stats(state, move_tables, pruning_tables, config.depth, false);
+        solve_times.push(now.elapsed());
+        nodes.push(result.stats.nodes_explored);
+        if result.solution.is_some() {
+            solved += 1;
+        }
+    }
+    debug!("Solved {} of {} benchmark states", solved, states.len());
+
+    Ok(BenchReport { config: *config, table_load_time, solve_times, nodes, solved })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use test_case::test_case;
+    use crate::movedefs::Face;
+
+    fn get_report(solve_times_ms: &[u64], nodes: &[u64]) -> BenchReport {
+        BenchReport {
+            config: BenchConfig::default(),
+            table_load_time: Duration::ZERO,
+            solve_times: solve_times_ms.iter().map(|&ms| Duration::from_millis(ms)).collect(),
+            nodes: nodes.to_vec(),
+            solved: nodes.len(),
+        }
+    }
+
+    #[test_case(&[], 0, 0; "no cases")]
+    #[test_case(&[5], 5, 5; "one case")]
+    #[test_case(&[9, 1, 5], 5, 5; "odd number of cases")]
+    #[test_case(&[8, 2, 4, 2], 4, 3; "even number of cases")]
+    fn test_solve_times(solve_times_ms: &[u64], average_ms: u64, median_ms: u64) {
+        let report = get_report(solve_times_ms, &vec![0; solve_times_ms.len()]);
+        assert_eq!(report.get_average_solve_time(), Duration::from_millis(average_ms));
+        assert_eq!(report.get_median_solve_time(), Duration::from_millis(median_ms));
+    }
+
+    #[test]
+    fn test_nodes() {
+        let report = get_report(&[100, 400], &[1000, 3000]);
+        assert_eq!(report.get_average_nodes(), 2000.0);
+        assert_eq!(report.get_nodes_per_second(), 8000.0);
+        assert_eq!(get_report(&[], &[]).get_nodes_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_run() {
+        let move_tables = MoveTables::generate(&mut |_| {});
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate(&move_tables);
+
+        let config = BenchConfig { seed: 1, cases: 3, depth: 3 };
+        let report = run(&config, || Ok::<_, Infallible>((&move_tables, &pruning_tables))).unwrap();
+        assert_eq!(report.config, config);
+        assert_eq!(report.solve_times.len(), 3);
+        assert_eq!(report.nodes.len(), 3);
+        assert_eq!(report.solved, 3);
+
+        // The same seed gives the same states, so the same amount of searching
+        let repeat = run(&config, || Ok::<_, Infallible>((&move_tables, &pruning_tables))).unwrap();
+        assert_eq!(repeat.nodes, report.nodes);
+    }
+}
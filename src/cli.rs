@@ -0,0 +1,1811 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use log::{debug, info, warn, LevelFilter};
+use rand::{thread_rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde_json::json;
+
+use fto_scramble::{bench, selftest};
+#[cfg(feature = "svg")]
+use fto_scramble::{drawascii, drawstate, patterns};
+
+use crate::config::{Config, ConfigError, OutputFormat, get_default_config_path};
+use crate::serve::{self, Address, Listener, ServeOptions};
+use fto_scramble::bench::{BenchConfig, BenchReport};
+#[cfg(feature = "svg")]
+use fto_scramble::drawquery::{QueryError, RenderRequest};
+#[cfg(feature = "svg")]
+use fto_scramble::drawstate::{ColourScheme, DrawError, Layout, RenderOptions};
+use fto_scramble::coordinates::CoordinateType;
+use fto_scramble::movedefs::{Face, Turn, ParseTurnError, NUM_FACES, get_random_turns};
+use fto_scramble::pruningtables::{SimplePruningTable, PRUNING_TABLE_FILE_NAME};
+use fto_scramble::ergonomics::Scoring;
+use fto_scramble::search::{search_phase_1, solve_phase_1_with_stats, solve_phase_1_batch, solve_multiple_with_stats, Ranking, SearchStats};
+use fto_scramble::state::{warm_up_solved_centres_cache, CoordState, RawState, SOLVED_COORD_STATE};
+use fto_scramble::movetables::{MoveTable, MoveTables, MOVE_TABLE_FILE_NAME};
+use fto_scramble::timings::{Phase, Timings};
+use fto_scramble::verify::{verify_solution_for_goal, VerifyFailure, VerifyGoal};
+
+
+pub(crate) const DEFAULT_DEPTH: u8 = 9;
+const SCRAMBLE_LENGTH: usize = 30;
+/// The most scrambles that one scramble command, or one serve request, can ask for.
+pub(crate) const MAX_SCRAMBLE_COUNT: usize = 10_000;
+/// How many solutions `solve --rank ergonomic` chooses from.
+const RANKED_SOLUTIONS: usize = 20;
+
+
+#[derive(Parser)]
+#[command(name = "fto-scramble", about = "Scrambles and solves the Face-Turning Octahedron")]
+pub struct Cli {
+    /// Also print the demo scramble as coloured text
+    #[arg(long)]
+    ascii: bool,
+
+    /// Print the result as a single JSON object, with any progress messages going to stderr
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Load the tables from a directory filled by gen-tables, instead of the platform data directory or
+    /// movetables.dat in the working directory
+    #[arg(long, global = true)]
+    tables_dir: Option<PathBuf>,
+
+    /// Read default settings from this TOML file, instead of config.toml in the platform config directory
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Show more about what the program is doing. Use -vv to also follow the table generation step by step
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only print the result, leaving out progress messages
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// End with a line giving the time spent loading tables, searching and rendering, in milliseconds
+    #[arg(long, global = true)]
+    timings: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    /// The most detailed log messages that should be shown, which `RUST_LOG` can still override.
+    pub fn get_log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Error,
+            (false, 0) => LevelFilter::Info,
+            (false, 1) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Find a phase 1 solution for a scramble
+    Solve {
+        /// The scramble as turns separated by spaces, such as "U R' BL"
+        #[arg(long, allow_hyphen_values = true, required_unless_present = "input", conflicts_with = "input")]
+        scramble: Option<String>,
+        /// A file with one scramble per line to solve instead, or - to read them from stdin
+        #[arg(long)]
+        input: Option<String>,
+        /// The most turns a solution may have [default: 9]
+        #[arg(long)]
+        depth: Option<u8>,
+        /// Search one depth at a time, so the solution found is as short as possible
+        #[arg(long)]
+        optimal: bool,
+        /// How to choose the solution: the first one found, or the easiest to turn of the shortest few
+        #[arg(long, value_enum, default_value_t = RankArg::Length, conflicts_with = "input")]
+        rank: RankArg,
+    },
+    /// Print random move scrambles
+    Scramble {
+        /// How many scrambles to print
+        #[arg(long, default_value_t = 1,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(0..=MAX_SCRAMBLE_COUNT as u64))]
+        count: usize,
+        /// Seed for the random number generator, to get the same scrambles every time
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Draw the state a scramble leads to as an SVG image
+    Draw {
+        /// The scramble to apply to the solved state
+        #[arg(long, allow_hyphen_values = true, required_unless_present_any = ["state_json", "pattern", "query"],
+            conflicts_with_all = ["state_json", "pattern", "query"])]
+        scramble: Option<String>,
+        /// A JSON file with the coordinates of the state to draw, as written by `scramble --json`
+        #[arg(long, conflicts_with = "pattern")]
+        state_json: Option<String>,
+        /// A named pattern to draw instead of a scramble
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(fto_scramble::patterns::get_names()))]
+        pattern: Option<String>,
+        /// An image description in the form of a URL query string, such as "fd=…&layout=net", giving the sticker
+        /// colours along with the colours, layout and highlights to draw them with
+        #[arg(long, conflicts_with_all = ["state_json", "pattern", "colours", "layout"])]
+        query: Option<String>,
+        /// Where to save the image
+        #[arg(long)]
+        out: String,
+        /// A preset colour scheme, or eight colours separated by commas in the order U, F, BL, BR, D, B, L, R [default: default]
+        #[arg(long)]
+        colours: Option<String>,
+        /// Which arrangement of the faces to draw
+        #[arg(long, value_enum, default_value_t = LayoutArg::Default)]
+        layout: LayoutArg,
+        /// Width of the image, in pixels
+        #[arg(long)]
+        width: Option<u32>,
+        /// Height of the image, in pixels
+        #[arg(long)]
+        height: Option<u32>,
+        /// Space around the puzzle, where a sticker is 20 units wide
+        #[arg(long, default_value_t = 0)]
+        margin: u32,
+        /// Colour to fill the background with, instead of leaving it transparent
+        #[arg(long)]
+        background: Option<String>,
+    },
+    /// Apply a sequence of turns to a state and print or save the state it leads to
+    Apply {
+        /// The turns to apply
+        #[arg(long, allow_hyphen_values = true)]
+        scramble: String,
+        /// A JSON file with the coordinates of the state to start from, in the same form as `--out-json` writes,
+        /// instead of starting from solved
+        #[arg(long)]
+        state_json: Option<String>,
+        /// Save the coordinates of the resulting state as JSON
+        #[arg(long)]
+        out_json: Option<String>,
+        /// Print the resulting state as text, one letter per sticker
+        #[arg(long)]
+        ascii: bool,
+        /// Save the resulting state as an SVG image
+        #[arg(long)]
+        svg: Option<String>,
+    },
+    /// Check that a solution solves a scramble, exiting with an error if it doesn't
+    Verify {
+        #[arg(long, allow_hyphen_values = true)]
+        scramble: String,
+        #[arg(long, allow_hyphen_values = true)]
+        solution: String,
+        /// Accept the puzzle being turned as a whole, as long as every face is one colour
+        #[arg(long, conflicts_with = "phase1")]
+        up_to_rotation: bool,
+        /// Only check that the solution reaches the end of phase 1, as the solve command's solutions do
+        #[arg(long)]
+        phase1: bool,
+    },
+    /// Generate and save every table, so that later commands only have to load them
+    GenTables {
+        /// Where to save the tables, by default the platform data directory
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// How many move tables to generate at once, by default one per core
+        #[arg(long)]
+        threads: Option<NonZeroUsize>,
+        /// Generate the tables even if the saved ones are already complete
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check the move tables and coordinate conversions, to find stale or corrupted table files
+    Selftest {
+        /// How many random entries, coordinates and states each check tries
+        #[arg(long, default_value_t = 1000)]
+        samples: usize,
+        /// Seed for the random samples, to repeat a run exactly
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Load the tables once, then answer solve, scramble and draw requests sent as lines of JSON over a socket
+    Serve {
+        /// The path of a Unix domain socket to listen on
+        #[arg(long, required_unless_present = "port", conflicts_with = "port")]
+        socket: Option<PathBuf>,
+        /// Listen on this TCP port on 127.0.0.1 instead, such as on Windows. Use 0 to pick a free port
+        #[arg(long)]
+        port: Option<u16>,
+        /// How many clients to serve at once, by default one per core
+        #[arg(long)]
+        threads: Option<NonZeroUsize>,
+        /// Stop once the first client has disconnected, rather than serving until the process is killed
+        #[arg(long)]
+        once: bool,
+    },
+    /// Time solving a fixed set of random states, to compare performance between versions
+    Bench {
+        /// Seed for the random states. Runs with the same seed, cases and depth solve the same states
+        #[arg(long, default_value_t = bench::DEFAULT_SEED)]
+        seed: u64,
+        /// How many states to solve
+        #[arg(long, default_value_t = bench::DEFAULT_CASES)]
+        cases: usize,
+        /// How many random turns scramble each state, which is also the search depth
+        #[arg(long, default_value_t = bench::DEFAULT_DEPTH)]
+        depth: u8,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum LayoutArg {
+    Default,
+    Net,
+    TopView,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum RankArg {
+    Length,
+    Ergonomic,
+}
+
+#[cfg(feature = "svg")]
+impl From<LayoutArg> for Layout {
+    fn from(layout: LayoutArg) -> Self {
+        match layout {
+            LayoutArg::Default => Self::Default,
+            LayoutArg::Net => Self::Net,
+            LayoutArg::TopView => Self::TopView,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(clap::Error),
+    Notation(ParseTurnError),
+    IncompleteTables(Vec<CoordinateType>),
+    NoSolution(u8),
+    NoDataDir,
+    BadTables { path: PathBuf, reason: String },
+    Verify(VerifyFailure),
+    InvalidState(String),
+    #[cfg(feature = "svg")]
+    Draw(DrawError),
+    #[cfg(feature = "svg")]
+    Query(QueryError),
+    /// The command needs a cargo feature that this binary was built without.
+    #[cfg(not(feature = "svg"))]
+    FeatureDisabled { command: &'static str, feature: &'static str },
+    Read { path: String, source: io::Error },
+    Config(ConfigError),
+    /// How many of the self test checks failed.
+    SelfTest(usize),
+    Write { path: PathBuf, source: io::Error },
+    Io(io::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(error) => write!(f, "{}", error),
+            Self::Notation(error) => write!(f, "could not read the scramble: {}", error),
+            Self::IncompleteTables(missing) => write!(f,
+                "the saved move tables are incomplete, missing {:?}. Delete movetables.dat to regenerate them", missing),
+            Self::NoSolution(depth) => write!(f, "no solution found within {} moves", depth),
+            Self::NoDataDir => write!(f, "there is no data directory on this platform, so the tables need a --dir"),
+            Self::BadTables { path, reason } => write!(f,
+                "the tables in '{}' can't be used: {}. Run gen-tables --force to regenerate them", path.display(), reason),
+            Self::Verify(failure) => write!(f, "{}", failure),
+            Self::InvalidState(reason) => write!(f, "could not read the state: {}", reason),
+            #[cfg(feature = "svg")]
+            Self::Draw(error) => write!(f, "{}", error),
+            #[cfg(feature = "svg")]
+            Self::Query(error) => write!(f, "could not read the query: {}", error),
+            #[cfg(not(feature = "svg"))]
+            Self::FeatureDisabled { command, feature } => write!(f,
+                "the {} command was compiled out. Build with the {} feature to use it", command, feature),
+            Self::Read { path, source } => write!(f, "could not read '{}': {}", path, source),
+            Self::Config(error) => write!(f, "{}", error),
+            Self::SelfTest(failed) => write!(f, "{} of the self test checks failed", failed),
+            Self::Write { path, source } => write!(f, "could not write '{}': {}", path.display(), source),
+            Self::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// The command line, a scramble or a state couldn't be read. A successful run exits with 0.
+pub const EXIT_USAGE: i32 = 2;
+/// No solution was found within the depth limit, or the solution given to verify doesn't solve the scramble.
+pub const EXIT_NO_SOLUTION: i32 = 3;
+/// The tables are missing, incomplete or corrupt.
+pub const EXIT_DATA: i32 = 4;
+/// A file couldn't be read or written.
+pub const EXIT_IO: i32 = 5;
+
+impl CliError {
+    /// The code to exit the process with, so that scripts can tell what went wrong without reading the message.
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            // Clap exits with 0 for --help and --version, and with EXIT_USAGE otherwise
+            Self::Usage(error) => error.exit_code(),
+            Self::Notation(_) | Self::InvalidState(_) => EXIT_USAGE,
+            #[cfg(not(feature = "svg"))]
+            Self::FeatureDisabled { .. } => EXIT_USAGE,
+            #[cfg(feature = "svg")]
+            Self::Draw(DrawError::UnknownPreset(_)) | Self::Query(_) => EXIT_USAGE,
+            Self::Verify(VerifyFailure::Scramble(_) | VerifyFailure::Solution(_)) => EXIT_USAGE,
+            Self::Config(ConfigError::Parse { .. }) => EXIT_USAGE,
+            Self::NoSolution(_) | Self::Verify(_) => EXIT_NO_SOLUTION,
+            Self::IncompleteTables(_) | Self::NoDataDir | Self::BadTables { .. } | Self::SelfTest(_) => EXIT_DATA,
+            Self::Read { .. } | Self::Config(ConfigError::Read { .. }) | Self::Write { .. } | Self::Io(_) => EXIT_IO,
+            #[cfg(feature = "svg")]
+            Self::Draw(DrawError::Io { .. }) => EXIT_IO,
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// The move and pruning tables, loaded the first time a command needs them. They are kept behind `Arc` so that
+/// the serve command can share them between threads.
+pub struct Tables {
+    dir: Option<PathBuf>,
+    move_tables: Option<Arc<MoveTables>>,
+    pruning_tables: Option<Arc<SimplePruningTable>>,
+}
+
+impl Tables {
+    pub fn new() -> Self {
+        Self {
+            dir: None,
+            move_tables: None,
+            pruning_tables: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn from_move_tables(move_tables: MoveTables) -> Self {
+        Self {
+            dir: None,
+            move_tables: Some(Arc::new(move_tables)),
+            pruning_tables: None,
+        }
+    }
+
+    /// Loads the tables from files saved by gen-tables, rather than the move table file in the working directory.
+    fn use_dir(&mut self, dir: PathBuf) {
+        self.dir = Some(dir);
+    }
+
+    /// Loads or generates the move tables if they aren't ready yet, logging what it is doing and adding the time it
+    /// took to `timings`.
+    fn get_move_tables(&mut self, timings: &mut Timings) -> Result<&MoveTables, CliError> {
+        if self.move_tables.is_none() {
+            let now = Instant::now();
+            let move_tables = timings.time(Phase::TableLoad, || match &self.dir {
+                Some(dir) => {
+                    let move_tables = load_move_tables(dir)?;
+                    info!("Loaded move tables from '{}' in {} seconds", dir.display(), get_seconds(now));
+                    Ok(move_tables)
+                }
+                None => {
+                    let move_tables = load_or_generate_move_tables()?;
+                    info!("Total time taken: {} seconds", get_seconds(now));
+                    Ok::<_, CliError>(move_tables)
+                }
+            })?;
+            self.move_tables = Some(Arc::new(move_tables));
+        }
+        Ok(self.move_tables.as_deref().expect("move tables were loaded above"))
+    }
+
+    /// Loads or generates any tables that aren't ready yet, logging what it is doing.
+    fn get(&mut self, timings: &mut Timings) -> Result<(&MoveTables, &SimplePruningTable), CliError> {
+        self.load(timings)?;
+        Ok((self.move_tables.as_deref().expect("move tables were loaded above"),
+            self.pruning_tables.as_deref().expect("pruning tables were loaded above")))
+    }
+
+    /// Loads the tables like `get`, returning them in a form that can be sent to other threads.
+    pub(crate) fn get_shared(&mut self, timings: &mut Timings) -> Result<(Arc<MoveTables>, Arc<SimplePruningTable>), CliError> {
+        self.load(timings)?;
+        Ok((self.move_tables.clone().expect("move tables were loaded above"),
+            self.pruning_tables.clone().expect("pruning tables were loaded above")))
+    }
+
+    fn load(&mut self, timings: &mut Timings) -> Result<(), CliError> {
+        if self.pruning_tables.is_none() {
+            let now = Instant::now();
+            warm_up_solved_centres_cache();
+            debug!("Built the matched centres lookup in {} seconds", get_seconds(now));
+        }
+
+        self.get_move_tables(timings)?;
+        let move_tables = self.move_tables.as_ref().expect("move tables were loaded above");
+
+        if self.pruning_tables.is_none() {
+            let now = Instant::now();
+            let pruning_tables = timings.time(Phase::Pruning, || match &self.dir {
+                Some(dir) => {
+                    let pruning_tables = load_pruning_tables(dir)?;
+                    info!("Loaded pruning tables from '{}' in {} seconds", dir.display(), get_seconds(now));
+                    Ok(pruning_tables)
+                }
+                None => {
+                    info!("Loading pruning tables");
+                    let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+                    for stats in pruning_tables.populate(move_tables) {
+                        debug!("{:?} pruning table max depth = {}, with {} coords unreachable", stats.coord_type,
+                            stats.max_depth, stats.coord_type.get_size() - stats.entry_count);
+                    }
+                    info!("Total time taken: {} seconds", get_seconds(now));
+                    Ok::<_, CliError>(pruning_tables)
+                }
+            })?;
+            self.pruning_tables = Some(Arc::new(pruning_tables));
+        }
+        Ok(())
+    }
+}
+
+/// Where the config file and the tables are looked for when neither the command line nor the config file says.
+/// The binary uses the platform's directories, while tests leave both out so that they don't depend on the files
+/// of whoever runs them.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultPaths {
+    pub config: Option<PathBuf>,
+    pub tables_dir: Option<PathBuf>,
+}
+
+impl DefaultPaths {
+    pub fn for_platform() -> Self {
+        Self {
+            config: get_default_config_path(),
+            tables_dir: get_default_tables_dir(),
+        }
+    }
+}
+
+/// Reads the command line arguments, which start with the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, CliError> {
+    Cli::try_parse_from(args).map_err(CliError::Usage)
+}
+
+/// Runs the command, writing its output to `out`. Progress messages go to the logger.
+pub fn run(cli: Cli, defaults: &DefaultPaths, tables: &mut Tables, out: &mut impl Write) -> Result<(), CliError> {
+    let start = Instant::now();
+    let mut timings = Timings::default();
+    // Options given on the command line take precedence over the config file
+    let mut config = Config::load(cli.config.as_deref(), defaults.config.as_deref()).map_err(CliError::Config)?;
+    let json = cli.json || config.format == Some(OutputFormat::Json);
+    let tables_dir = cli.tables_dir.or(config.tables_dir.take());
+
+    match tables_dir.clone() {
+        Some(dir) => tables.use_dir(dir),
+        // Tables that gen-tables saved to the default place are used when they are there
+        None => if let Some(dir) = defaults.tables_dir.clone().filter(|dir| dir.join(MOVE_TABLE_FILE_NAME).exists()) {
+            tables.use_dir(dir);
+        },
+    }
+    // From here on it is where gen-tables saves the tables, which is the default place unless one was given
+    config.tables_dir = tables_dir.or_else(|| defaults.tables_dir.clone());
+
+    let result = run_command(cli.command, tables, out, &mut timings, config, json, cli.ascii);
+    if cli.timings {
+        write_timings(out, &timings, start.elapsed(), json)?;
+    }
+    result
+}
+
+fn run_command(
+    command: Option<Command>,
+    tables: &mut Tables,
+    out: &mut impl Write,
+    timings: &mut Timings,
+    config: Config,
+    json: bool,
+    ascii: bool,
+) -> Result<(), CliError> {
+    match command {
+        None if json => solve(tables, timings, &get_demo_scramble(), DEFAULT_DEPTH, false, RankArg::Length)?.write(out, true),
+        None => run_demo(tables, out, timings, ascii),
+        Some(Command::Solve { input: Some(input), depth, optimal, .. }) => {
+            let depth = depth.or(config.depth).unwrap_or(DEFAULT_DEPTH);
+            let lines = read_input_lines(&input)?;
+            solve_batch(tables, out, timings, &lines, depth, optimal, json)
+        }
+        Some(Command::Solve { scramble, depth, optimal, rank, .. }) => {
+            let depth = depth.or(config.depth).unwrap_or(DEFAULT_DEPTH);
+            let scramble = scramble.expect("clap requires a scramble when there is no input file");
+            // Read the scramble before loading anything, so that typos are reported straight away
+            let scramble = Turn::parse_sequence(&scramble).map_err(CliError::Notation)?;
+            let result = solve(tables, timings, &scramble, depth, optimal, rank)?;
+            result.write(out, json)
+        }
+        Some(Command::Scramble { count, seed }) => scramble(out, count, seed, json),
+        #[cfg(feature = "svg")]
+        Some(Command::Draw { scramble, state_json, pattern, query, out: filename, colours, layout, width, height,
+                margin, background }) => {
+            let mut request = match (scramble, state_json, pattern, query) {
+                (_, _, _, Some(query)) => RenderRequest::parse(&query).map_err(CliError::Query)?,
+                (scramble, state_json, pattern, None) => {
+                    let state = match (scramble, state_json, pattern) {
+                        (Some(scramble), _, _) => {
+                            let mut state = RawState::solved();
+                            for turn in Turn::parse_sequence(&scramble).map_err(CliError::Notation)?.iter() {
+                                state.apply(turn);
+                            }
+                            state
+                        }
+                        (None, Some(path), _) => read_state_json(&path)?,
+                        (None, None, Some(name)) => patterns::get(&name).expect("clap only accepts known patterns").get_state(),
+                        (None, None, None) => unreachable!("clap requires a scramble, a state file, a pattern or a query"),
+                    };
+                    RenderRequest::from_state(&state, RenderOptions {
+                        colour_scheme: get_colour_scheme(colours.or(config.colours).as_deref().unwrap_or("default"))?,
+                        layout: layout.into(),
+                        ..Default::default()
+                    })
+                }
+            };
+            request.options = RenderOptions { width, height, margin, background, ..request.options };
+            timings.time(Phase::Render, || drawstate::write_svg(&filename, &request.render())).map_err(CliError::Draw)?;
+            if !json {
+                writeln!(out, "Saved {}", filename)?;
+            } else {
+                writeln!(out, "{}", json!({ "out": filename }))?;
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "svg"))]
+        Some(Command::Draw { .. }) => Err(CliError::FeatureDisabled { command: "draw", feature: "svg" }),
+        Some(Command::Apply { scramble, state_json, out_json, ascii, svg }) =>
+            apply(out, timings, &scramble, state_json.as_deref(), out_json.as_deref(), ascii, svg.as_deref(), json),
+        Some(Command::Verify { scramble, solution, up_to_rotation, phase1 }) => {
+            let goal = match (up_to_rotation, phase1) {
+                (true, _) => VerifyGoal::SolvedUpToRotation,
+                (_, true) => VerifyGoal::Phase1,
+                _ => VerifyGoal::Solved,
+            };
+            let result = verify_solution_for_goal(&scramble, &solution, goal);
+            match (&result, json) {
+                (Ok(()), true) => writeln!(out, "{}", json!({ "valid": true }))?,
+                (Ok(()), false) => writeln!(out, "The solution is correct")?,
+                (Err(failure), true) => writeln!(out, "{}", json!({ "valid": false, "reason": failure.to_string() }))?,
+                (Err(_), false) => (),
+            }
+            result.map_err(CliError::Verify)
+        }
+        Some(Command::GenTables { dir, threads, force }) => {
+            let dir = dir.or(config.tables_dir).ok_or(CliError::NoDataDir)?;
+            let threads = threads.or(config.threads).or_else(|| thread::available_parallelism().ok()).map_or(1, NonZeroUsize::get);
+            gen_tables(out, &dir, threads, force, json)
+        }
+        Some(Command::Selftest { samples, seed }) => selftest(tables, out, timings, samples, seed, json),
+        Some(Command::Serve { socket, port, threads, once }) => {
+            let address = match (socket, port) {
+                (Some(path), _) => Address::Socket(path),
+                (None, Some(port)) => Address::Port(port),
+                (None, None) => unreachable!("clap requires a socket or a port"),
+            };
+            let options = ServeOptions {
+                depth: config.depth.unwrap_or(DEFAULT_DEPTH),
+                threads: threads.or(config.threads).or_else(|| thread::available_parallelism().ok()).map_or(1, NonZeroUsize::get),
+                once,
+            };
+            // Load the tables before listening, so the first client doesn't have to wait for them
+            let (move_tables, pruning_tables) = tables.get_shared(timings)?;
+            let listener = Listener::bind(&address)?;
+            match json {
+                true => writeln!(out, "{}", json!({ "listening": listener.get_address() }))?,
+                false => writeln!(out, "Listening on {}", listener.get_address())?,
+            }
+            out.flush()?;
+            serve::serve(listener, move_tables, pruning_tables, &options)
+        }
+        Some(Command::Bench { seed, cases, depth }) => {
+            let report = bench::run(&BenchConfig { seed, cases, depth }, || tables.get(timings))?;
+            write_bench_report(out, &report, json)
+        }
+    }
+}
+
+/// A solution found by the solve command, ready to be printed.
+pub(crate) struct SolveReport {
+    scramble: Vec<Turn>,
+    solution: Vec<Turn>,
+    stats: SearchStats,
+    seconds: f64,
+}
+
+impl SolveReport {
+    pub(crate) fn write(&self, out: &mut impl Write, json: bool) -> Result<(), CliError> {
+        if json {
+            writeln!(out, "{}", json!({
+                "scramble": Turn::format_sequence(&self.scramble),
+                "solution": Turn::format_sequence(&self.solution),
+                "length": self.solution.len(),
+                "nodes": self.stats.nodes_explored,
+                "time_ms": self.seconds * 1000.0,
+            }))?;
+        } else {
+            writeln!(out, "{}", Turn::format_sequence(&self.solution))?;
+            info!("Phase 1 solved in {} moves, taking {} seconds", self.solution.len(), self.seconds);
+        }
+        Ok(())
+    }
+}
+
+fn solve(tables: &mut Tables, timings: &mut Timings, scramble: &[Turn], depth: u8, optimal: bool, rank: RankArg) -> Result<SolveReport, CliError> {
+    let (move_tables, pruning_tables) = tables.get(timings)?;
+    timings.time(Phase::Search, || match rank {
+        RankArg::Length => solve_with_tables(move_tables, pruning_tables, scramble, depth, optimal),
+        RankArg::Ergonomic => solve_ergonomic(move_tables, pruning_tables, scramble, depth),
+    })
+}
+
+pub(crate) fn solve_with_tables(
+    move_tables: &MoveTables,
+    pruning_tables: &SimplePruningTable,
+    scramble: &[Turn],
+    depth: u8,
+    optimal: bool,
+) -> Result<SolveReport, CliError> {
+    let mut state = SOLVED_COORD_STATE;
+    for turn in scramble.iter() {
+        state.apply(move_tables, turn);
+    }
+
+    let now = Instant::now();
+    let result = solve_phase_1_with_stats(&state, move_tables, pruning_tables, depth, optimal);
+    let solution = result.solution.ok_or(CliError::NoSolution(depth))?;
+
+    Ok(SolveReport {
+        scramble: scramble.to_vec(),
+        solution,
+        stats: result.stats,
+        seconds: get_seconds(now),
+    })
+}
+
+/// Finds the shortest `RANKED_SOLUTIONS` solutions and picks the one that `score_sequence` says is easiest to turn.
+/// The depths are searched in turn until there are that many, up to `depth`, so a solution longer than the shortest
+/// can win if it is easier to turn. Every depth is searched, so `--optimal` makes no difference.
+fn solve_ergonomic(move_tables: &MoveTables, pruning_tables: &SimplePruningTable, scramble: &[Turn], depth: u8) -> Result<SolveReport, CliError> {
+    let mut state = SOLVED_COORD_STATE;
+    for turn in scramble.iter() {
+        state.apply(move_tables, turn);
+    }
+
+    let now = Instant::now();
+    let mut stats = SearchStats::default();
+    let ranking = Ranking::Ergonomic(Scoring::default());
+    let solutions = solve_multiple_with_stats(&state, move_tables, pruning_tables, depth, RANKED_SOLUTIONS, ranking, &mut stats);
+    let solution = solutions.into_iter().next().ok_or(CliError::NoSolution(depth))?;
+
+    Ok(SolveReport {
+        scramble: scramble.to_vec(),
+        solution,
+        stats,
+        seconds: get_seconds(now),
+    })
+}
+
+/// Solves every non-empty line of the input, writing one line of output for each. Lines that can't be read or
+/// solved get an error entry rather than stopping the rest of the batch.
+fn solve_batch(tables: &mut Tables, out: &mut impl Write, timings: &mut Timings, lines: &[String], depth: u8, optimal: bool,
+        json: bool) -> Result<(), CliError> {
+    let scrambles: Vec<Result<Vec<Turn>, ParseTurnError>> = lines.iter()
+        .map(|line| Turn::parse_sequence(line))
+        .collect();
+    let (move_tables, pruning_tables) = tables.get(timings)?;
+
+    let states: Vec<CoordState> = scrambles.iter()
+        .filter_map(|scramble| scramble.as_ref().ok())
+        .map(|scramble| {
+            let mut state = SOLVED_COORD_STATE;
+            for turn in scramble.iter() {
+                state.apply(move_tables, turn);
+            }
+            state
+        })
+        .collect();
+
+    let now = Instant::now();
+    let mut results = timings.time(Phase::Search, || solve_phase_1_batch(&states, move_tables, pruning_tables, depth, optimal))
+        .into_iter();
+    let seconds = get_seconds(now);
+
+    for (line, scramble) in lines.iter().zip(scrambles.iter()) {
+        let result = match scramble {
+            Ok(scramble) => {
+                let result = results.next().expect("there is a result for every scramble that could be read");
+                result.solution
+                    .map(|solution| (scramble, solution, result.stats))
+                    .ok_or(CliError::NoSolution(depth))
+            }
+            Err(error) => Err(CliError::Notation(error.clone())),
+        };
+        match (result, json) {
+            // Solves in a batch overlap, so there is no time for each one on its own
+            (Ok((scramble, solution, stats)), true) => writeln!(out, "{}", json!({
+                "scramble": Turn::format_sequence(scramble),
+                "solution": Turn::format_sequence(&solution),
+                "length": solution.len(),
+                "nodes": stats.nodes_explored,
+                "time_ms": null,
+            }))?,
+            (Ok((_, solution, _)), false) => writeln!(out, "{}", Turn::format_sequence(&solution))?,
+            (Err(error), true) => writeln!(out, "{}", json!({ "scramble": line, "error": error.to_string() }))?,
+            (Err(error), false) => writeln!(out, "Error: {}", error)?,
+        }
+    }
+    info!("Solved {} scrambles in {} seconds", states.len(), seconds);
+    Ok(())
+}
+
+/// Reads the non-empty lines of a file, or of stdin if the file name is `-`.
+fn read_input_lines(input: &str) -> Result<Vec<String>, CliError> {
+    let text = if input == "-" {
+        io::read_to_string(io::stdin())?
+    } else {
+        read_file(input)?
+    };
+    Ok(text.lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn read_file(path: &str) -> Result<String, CliError> {
+    fs::read_to_string(path).map_err(|source| CliError::Read { path: path.to_string(), source })
+}
+
+/// Reads a state from the coordinates in a JSON file. The file can hold just the coordinates object, or an entry
+/// from the output of `scramble --json` that has one.
+fn read_state_json(path: &str) -> Result<RawState, CliError> {
+    let json: serde_json::Value = serde_json::from_str(&read_file(path)?)
+        .map_err(|error| CliError::InvalidState(error.to_string()))?;
+    let coordinates = json.get("coordinates").unwrap_or(&json);
+    let get_coord = |name: &str| coordinates.get(name)
+        .and_then(|value| value.as_u64())
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or_else(|| CliError::InvalidState(format!("missing or invalid \"{}\" coordinate", name)));
+
+    let state = CoordState {
+        corners: get_coord("corners")?,
+        edges_within_faces: get_coord("edges_within_faces")?,
+        edges_across_faces: get_coord("edges_across_faces")?,
+        up_centres: get_coord("up_centres")?,
+        down_centres: get_coord("down_centres")?,
+    };
+    state.try_to_raw().map_err(|error| CliError::InvalidState(error.to_string()))
+}
+
+/// Applies the scramble to the state in `state_json`, or to solved, then writes the result in each of the formats
+/// asked for. Without `out_json`, `ascii` or `svg`, or with `json`, the scramble and the coordinates of the result
+/// are printed as a JSON object, which `--state-json` can read back.
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(feature = "svg"), allow(unused_variables))]
+fn apply(out: &mut impl Write, timings: &mut Timings, scramble: &str, state_json: Option<&str>, out_json: Option<&str>, ascii: bool,
+        svg: Option<&str>, json: bool) -> Result<(), CliError> {
+    #[cfg(not(feature = "svg"))]
+    if ascii || svg.is_some() {
+        let command = if ascii { "apply --ascii" } else { "apply --svg" };
+        return Err(CliError::FeatureDisabled { command, feature: "svg" });
+    }
+    let turns = Turn::parse_sequence(scramble).map_err(CliError::Notation)?;
+    let mut state = match state_json {
+        Some(path) => read_state_json(path)?,
+        None => RawState::solved(),
+    };
+    for turn in turns.iter() {
+        state.apply(turn);
+    }
+    let entry = json!({ "scramble": Turn::format_sequence(&turns), "coordinates": state.to_coords() });
+
+    #[cfg(feature = "svg")]
+    if ascii {
+        write!(out, "{}", drawascii::render_ascii(&state))?;
+    }
+    if let Some(path) = out_json {
+        fs::write(path, format!("{}\n", entry)).map_err(|source| CliError::Write { path: PathBuf::from(path), source })?;
+        if !json {
+            writeln!(out, "Saved {}", path)?;
+        }
+    }
+    #[cfg(feature = "svg")]
+    if let Some(path) = svg {
+        timings.time(Phase::Render, || drawstate::write_state_svg(path, &state, &RenderOptions::default()))
+            .map_err(CliError::Draw)?;
+        if !json {
+            writeln!(out, "Saved {}", path)?;
+        }
+    }
+    if json || (out_json.is_none() && !ascii && svg.is_none()) {
+        writeln!(out, "{}", entry)?;
+    }
+    Ok(())
+}
+
+/// Either the name of a preset, or a list of eight colours separated by commas.
+#[cfg(feature = "svg")]
+pub(crate) fn get_colour_scheme(colours: &str) -> Result<ColourScheme, CliError> {
+    let list: Vec<&str> = colours.split(',').map(str::trim).collect();
+    match <[&str; 8]>::try_from(list.as_slice()) {
+        Ok(list) => Ok(ColourScheme::new(list)),
+        Err(_) if list.len() == 1 => ColourScheme::preset(colours).map_err(CliError::Draw),
+        Err(_) => Err(CliError::Usage(clap::Error::raw(clap::error::ErrorKind::InvalidValue,
+            format!("expected a preset or 8 colours for --colours, but got {}\n", list.len())))),
+    }
+}
+
+/// Random move scrambles, until there is a full solver to generate random state scrambles with. The JSON output
+/// gives the coordinates of the state each scramble leads to as well.
+pub(crate) fn scramble(out: &mut impl Write, count: usize, seed: Option<u64>, json: bool) -> Result<(), CliError> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(thread_rng()).expect("the thread rng should be able to seed another rng"),
+    };
+    let scrambles: Vec<Vec<Turn>> = (0..count).map(|_| get_random_turns(SCRAMBLE_LENGTH, &mut rng)).collect();
+
+    if json {
+        let entries: Vec<serde_json::Value> = scrambles.iter()
+            .map(|scramble| {
+                let mut state = RawState::solved();
+                for turn in scramble.iter() {
+                    state.apply(turn);
+                }
+                json!({ "scramble": Turn::format_sequence(scramble), "coordinates": state.to_coords() })
+            })
+            .collect();
+        writeln!(out, "{}", serde_json::Value::from(entries))?;
+    } else {
+        for scramble in scrambles.iter() {
+            writeln!(out, "{}", Turn::format_sequence(scramble))?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs each self test check on the loaded move tables, printing whether it passed, and fails if any didn't.
+fn selftest(tables: &mut Tables, out: &mut impl Write, timings: &mut Timings, samples: usize, seed: Option<u64>, json: bool) -> Result<(), CliError> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(thread_rng()).expect("the thread rng should be able to seed another rng"),
+    };
+    let move_tables = tables.get_move_tables(timings)?;
+
+    let checks = [
+        ("move tables", selftest::check_move_tables(move_tables, samples, &mut rng)),
+        ("coordinate round trips", selftest::check_coordinate_round_trips(samples, &mut rng)),
+        ("move tables match raw states", selftest::check_move_tables_match_raw_state(move_tables, samples, &mut rng)),
+    ];
+    let failed = checks.iter().filter(|(_, result)| result.is_err()).count();
+
+    if json {
+        let entries: Vec<serde_json::Value> = checks.iter()
+            .map(|(name, result)| match result {
+                Ok(()) => json!({ "name": name, "passed": true }),
+                Err(failure) => json!({ "name": name, "passed": false, "reason": failure.to_string() }),
+            })
+            .collect();
+        writeln!(out, "{}", json!({ "passed": failed == 0, "checks": entries }))?;
+    } else {
+        for (name, result) in checks.iter() {
+            match result {
+                Ok(()) => writeln!(out, "PASS {}", name)?,
+                Err(failure) => writeln!(out, "FAIL {}: {}", name, failure)?,
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        failed => Err(CliError::SelfTest(failed)),
+    }
+}
+
+/// Writes the time recorded for each phase, with phases that didn't run given as 0, and the total time of the run.
+fn write_timings(out: &mut impl Write, timings: &Timings, total: Duration, json: bool) -> Result<(), CliError> {
+    let entries: Vec<(&str, f64)> = Phase::iter()
+        .map(|phase| (phase.get_name(), timings.get(phase).as_secs_f64() * 1000.0))
+        .chain(std::iter::once(("total_ms", total.as_secs_f64() * 1000.0)))
+        .collect();
+    if json {
+        let fields: serde_json::Map<String, serde_json::Value> = entries.iter()
+            .map(|(name, ms)| (name.to_string(), json!(ms)))
+            .collect();
+        writeln!(out, "{}", json!({ "timings": fields }))?;
+    } else {
+        let fields: Vec<String> = entries.iter().map(|(name, ms)| format!("{}={:.3}", name, ms)).collect();
+        writeln!(out, "timings: {}", fields.join(" "))?;
+    }
+    Ok(())
+}
+
+fn write_bench_report(out: &mut impl Write, report: &BenchReport, json: bool) -> Result<(), CliError> {
+    let config = &report.config;
+    if json {
+        writeln!(out, "{}", json!({
+            "seed": config.seed,
+            "cases": config.cases,
+            "depth": config.depth,
+            "solved": report.solved,
+            "table_load_ms": report.table_load_time.as_secs_f64() * 1000.0,
+            "average_solve_ms": report.get_average_solve_time().as_secs_f64() * 1000.0,
+            "median_solve_ms": report.get_median_solve_time().as_secs_f64() * 1000.0,
+            "average_nodes": report.get_average_nodes(),
+            "nodes_per_second": report.get_nodes_per_second(),
+        }))?;
+    } else {
+        writeln!(out, "Seed {}, {} cases, depth {}", config.seed, config.cases, config.depth)?;
+        writeln!(out, "{:<20}{:>14.3} ms", "Table load time", report.table_load_time.as_secs_f64() * 1000.0)?;
+        writeln!(out, "{:<20}{:>14.3} ms", "Average solve time", report.get_average_solve_time().as_secs_f64() * 1000.0)?;
+        writeln!(out, "{:<20}{:>14.3} ms", "Median solve time", report.get_median_solve_time().as_secs_f64() * 1000.0)?;
+        writeln!(out, "{:<20}{:>14.1}", "Average nodes", report.get_average_nodes())?;
+        writeln!(out, "{:<20}{:>14.0}", "Nodes per second", report.get_nodes_per_second())?;
+        if report.solved < config.cases {
+            writeln!(out, "{} of the states had no solution within the depth", config.cases - report.solved)?;
+        }
+    }
+    Ok(())
+}
+
+/// Solves a fixed scramble, saving a picture of it, as the program did before it had commands.
+fn run_demo(tables: &mut Tables, out: &mut impl Write, timings: &mut Timings, print_ascii: bool) -> Result<(), CliError> {
+    let (move_tables, pruning_tables) = tables.get(timings)?;
+
+    // let random = CoordState::get_random();
+    let mut random = SOLVED_COORD_STATE;
+    for turn in get_demo_scramble().iter() {
+        random.apply(move_tables, turn);
+    }
+
+    draw_demo_state(out, timings, &random, print_ascii)?;
+
+    let now = Instant::now();
+
+    let limit = DEFAULT_DEPTH;
+    writeln!(out, "Searching to depth {}.", limit)?;
+
+    let solution = timings.time(Phase::Search, || search_phase_1(&random, move_tables, pruning_tables, limit, None));
+    writeln!(out, "{:?}", solution)?;
+
+    writeln!(out, "Total time taken: {} seconds", get_seconds(now))?;
+    Ok(())
+}
+
+/// Prints the demo state as coloured text if asked to, and saves it as an image.
+#[cfg(feature = "svg")]
+fn draw_demo_state(out: &mut impl Write, timings: &mut Timings, state: &CoordState, print_ascii: bool) -> Result<(), CliError> {
+    if print_ascii {
+        let raw_state = state.try_to_raw().expect("Scrambled state should be valid");
+        write!(out, "{}", drawascii::render_ascii_coloured(&raw_state, &drawstate::ColourScheme::default()))?;
+    }
+
+    let result = timings.time(Phase::Render, || {
+        let svg_data = drawstate::get_svg_for_coord_state(state).expect("Scrambled state should be valid");
+        drawstate::write_svg("random_start.svg", &svg_data)
+    });
+    if let Err(error) = result {
+        warn!("Could not save the scrambled state image: {}", error);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "svg"))]
+fn draw_demo_state(_out: &mut impl Write, _timings: &mut Timings, _state: &CoordState, print_ascii: bool) -> Result<(), CliError> {
+    if print_ascii {
+        warn!("Drawing was compiled out, so the demo state can't be shown. Build with the svg feature to see it");
+    }
+    Ok(())
+}
+
+/// Loads the move tables from the working directory, or generates them and saves them there if they aren't
+/// there yet.
+fn load_or_generate_move_tables() -> Result<MoveTables, CliError> {
+    let path = Path::new(MOVE_TABLE_FILE_NAME);
+    let move_tables = if path.exists() {
+        info!("Loading move tables");
+        MoveTables::load_from(path)
+            .map_err(|error| CliError::BadTables { path: path.to_path_buf(), reason: error.to_string() })?
+    } else {
+        info!("Generating move tables");
+        let move_tables = MoveTables::generate(&mut |progress| {
+            info!("  {:?}: {:.0}% ({} seconds remaining)", progress.coord_type,
+                progress.get_fraction_complete() * 100.0, progress.estimated_remaining.as_secs());
+        });
+        // The tables can still be used without saving them, it just means generating them again next time
+        if let Err(error) = move_tables.save_to(path) {
+            warn!("Could not save the move tables to '{}': {}", path.display(), error);
+        }
+        move_tables
+    };
+    if !move_tables.all_tables_generated() {
+        return Err(CliError::IncompleteTables(move_tables.missing_tables()));
+    }
+    Ok(move_tables)
+}
+
+/// Where gen-tables saves the tables when it isn't given a directory.
+fn get_default_tables_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("fto-scramble"))
+}
+
+fn load_move_tables(dir: &Path) -> Result<MoveTables, CliError> {
+    let path = dir.join(MOVE_TABLE_FILE_NAME);
+    let move_tables = MoveTables::load_from(&path)
+        .map_err(|error| CliError::BadTables { path: path.clone(), reason: error.to_string() })?;
+    if !move_tables.all_tables_generated() {
+        let reason = format!("missing {:?}", move_tables.missing_tables());
+        return Err(CliError::BadTables { path, reason });
+    }
+    Ok(move_tables)
+}
+
+fn load_pruning_tables(dir: &Path) -> Result<SimplePruningTable, CliError> {
+    let path = dir.join(PRUNING_TABLE_FILE_NAME);
+    let pruning_tables = SimplePruningTable::load_from(&path)
+        .map_err(|error| CliError::BadTables { path: path.clone(), reason: error.to_string() })?;
+    let missing: Vec<CoordinateType> = CoordinateType::iter()
+        .filter(|coord_type| !pruning_tables.populated_coordinate_types().contains(coord_type))
+        .collect();
+    if !missing.is_empty() {
+        return Err(CliError::BadTables { path, reason: format!("missing {:?}", missing) });
+    }
+    Ok(pruning_tables)
+}
+
+/// How long one table took to generate, and how much space it takes up in its file.
+struct TableReport {
+    kind: &'static str,
+    coord_type: CoordinateType,
+    duration: Duration,
+    bytes: usize,
+}
+
+/// Generates every move and pruning table and saves them to `dir`, then loads them back to check that the
+/// files are complete. The corner and down centre lookup for matched triples is quick to build, so it is made
+/// whenever the program starts rather than being saved.
+fn gen_tables(out: &mut impl Write, dir: &Path, threads: usize, force: bool, json: bool) -> Result<(), CliError> {
+    let move_table_path = dir.join(MOVE_TABLE_FILE_NAME);
+    let pruning_table_path = dir.join(PRUNING_TABLE_FILE_NAME);
+
+    let already_generated = !force && load_move_tables(dir).is_ok() && load_pruning_tables(dir).is_ok();
+    let mut reports = vec![];
+    if already_generated {
+        info!("The tables in '{}' are already complete. Use --force to generate them again", dir.display());
+    } else {
+        fs::create_dir_all(dir).map_err(|source| CliError::Write { path: dir.to_path_buf(), source })?;
+
+        info!("Generating move tables (threads: {})", threads);
+        let (move_tables, move_table_reports) = generate_move_tables(threads);
+        move_tables.save_to(&move_table_path)
+            .map_err(|source| CliError::Write { path: move_table_path.clone(), source })?;
+        reports.extend(move_table_reports);
+
+        info!("Generating pruning tables");
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        for coord_type in CoordinateType::iter() {
+            let stats = pruning_tables.populate_coordinate_type(&move_tables, coord_type);
+            reports.push(TableReport { kind: "pruning", coord_type, duration: stats.fill_duration, bytes: coord_type.get_size() });
+        }
+        pruning_tables.save_to(&pruning_table_path)
+            .map_err(|source| CliError::Write { path: pruning_table_path.clone(), source })?;
+
+        info!("Verifying the saved tables");
+        if load_move_tables(dir)? != move_tables {
+            return Err(CliError::BadTables { path: move_table_path, reason: String::from("the saved tables differ") });
+        }
+        if load_pruning_tables(dir)? != pruning_tables {
+            return Err(CliError::BadTables { path: pruning_table_path, reason: String::from("the saved tables differ") });
+        }
+    }
+
+    let file_sizes: Vec<(&PathBuf, u64)> = [&move_table_path, &pruning_table_path].into_iter()
+        .map(|path| fs::metadata(path).map(|metadata| (path, metadata.len()))
+            .map_err(|source| CliError::Read { path: path.display().to_string(), source }))
+        .collect::<Result<_, _>>()?;
+
+    if json {
+        let tables: Vec<serde_json::Value> = reports.iter()
+            .map(|report| json!({
+                "kind": report.kind,
+                "coordinate": format!("{:?}", report.coord_type),
+                "time_ms": report.duration.as_secs_f64() * 1000.0,
+                "bytes": report.bytes,
+            }))
+            .collect();
+        let files: Vec<serde_json::Value> = file_sizes.iter()
+            .map(|(path, size)| json!({ "path": path.display().to_string(), "bytes": size }))
+            .collect();
+        writeln!(out, "{}", json!({
+            "dir": dir.display().to_string(),
+            "generated": !already_generated,
+            "tables": tables,
+            "files": files,
+        }))?;
+    } else {
+        for report in reports.iter() {
+            writeln!(out, "{:?} {} table: {} seconds, {} bytes", report.coord_type, report.kind,
+                report.duration.as_secs_f64(), report.bytes)?;
+        }
+        for (path, size) in file_sizes.iter() {
+            writeln!(out, "{}: {} bytes", path.display(), size)?;
+        }
+    }
+    Ok(())
+}
+
+/// Generates the move table for each coordinate type, sharing them out between up to `threads` threads.
+fn generate_move_tables(threads: usize) -> (MoveTables, Vec<TableReport>) {
+    let coord_types: Vec<CoordinateType> = CoordinateType::iter().collect();
+    let chunk_size = coord_types.len().div_ceil(threads.min(coord_types.len()));
+
+    let mut tables = vec![];
+    thread::scope(|scope| {
+        let workers: Vec<_> = coord_types.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                chunk.iter()
+                    .map(|&coord_type| {
+                        let now = Instant::now();
+                        let move_table = MoveTable::new(coord_type);
+                        (coord_type, move_table, now.elapsed())
+                    })
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+        for worker in workers {
+            tables.extend(worker.join().expect("a move table thread panicked"));
+        }
+    });
+
+    let reports = tables.iter()
+        .map(|(coord_type, _, duration)| TableReport {
+            kind: "move",
+            coord_type: *coord_type,
+            duration: *duration,
+            bytes: coord_type.get_size() * NUM_FACES * 4,
+        })
+        .collect();
+    let move_tables = MoveTables {
+        tables: tables.into_iter().map(|(coord_type, move_table, _)| (coord_type, move_table)).collect(),
+    };
+    (move_tables, reports)
+}
+
+fn get_demo_scramble() -> Vec<Turn> {
+    vec![
+        Turn::new(Face::R, false),
+        Turn::new(Face::U, false),
+        Turn::new(Face::R, false),
+        Turn::new(Face::U, false),
+    ]
+}
+
+fn get_seconds(start: Instant) -> f64 {
+    start.elapsed().as_micros() as f64 / 1_000_000.0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::{Mutex, Once};
+    use std::sync::OnceLock;
+    use log::{Log, Metadata, Record};
+    use test_case::test_case;
+    use fto_scramble::ergonomics::score_sequence;
+    use fto_scramble::search::is_phase_1_solved;
+
+    // Generating move tables is slow, so the tests share one set, along with the pruning tables built from it
+    fn get_test_tables() -> &'static Mutex<Tables> {
+        static TABLES: OnceLock<Mutex<Tables>> = OnceLock::new();
+        TABLES.get_or_init(|| Mutex::new(Tables::from_move_tables(MoveTables::generate(&mut |_| {}))))
+    }
+
+    thread_local! {
+        static CAPTURED_LOG: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    /// Keeps the messages logged by each test's thread, so that tests running side by side don't see each other's.
+    struct CapturingLogger;
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= log::max_level()
+        }
+
+        fn log(&self, record: &Record) {
+            CAPTURED_LOG.with(|captured| captured.borrow_mut().push_str(&format!("{}\n", record.args())));
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn run_to_string(args: &[&str]) -> Result<String, CliError> {
+        run_to_strings(args).map(|(out, _)| out)
+    }
+
+    fn run_to_strings(args: &[&str]) -> Result<(String, String), CliError> {
+        let mut tables = get_test_tables().lock().unwrap();
+        run_with_tables(args, &mut tables)
+    }
+
+    /// Runs with the given tables, returning both the output and the log.
+    fn run_with_tables(args: &[&str], tables: &mut Tables) -> Result<(String, String), CliError> {
+        static INIT_LOGGER: Once = Once::new();
+        INIT_LOGGER.call_once(|| {
+            log::set_logger(&CapturingLogger).unwrap();
+            log::set_max_level(LevelFilter::Info);
+        });
+        CAPTURED_LOG.with(|captured| captured.borrow_mut().clear());
+
+        let args = std::iter::once("fto-scramble").chain(args.iter().copied()).map(String::from);
+        let mut out = Vec::new();
+        run(parse(args)?, &DefaultPaths::default(), tables, &mut out)?;
+        Ok((String::from_utf8(out).unwrap(), CAPTURED_LOG.with(|captured| captured.take())))
+    }
+
+    #[test_case(&[], LevelFilter::Info; "default")]
+    #[test_case(&["-q"], LevelFilter::Error; "quiet")]
+    #[test_case(&["-v"], LevelFilter::Debug; "verbose")]
+    #[test_case(&["solve", "-vv", "--scramble", "U"], LevelFilter::Trace; "very verbose after the command")]
+    fn test_get_log_level(args: &[&str], expected: LevelFilter) {
+        let args = std::iter::once("fto-scramble").chain(args.iter().copied()).map(String::from);
+        assert_eq!(parse(args).unwrap().get_log_level(), expected);
+    }
+
+    #[test]
+    fn test_quiet_conflicts_with_verbose() {
+        let args = ["fto-scramble", "-q", "-v"].map(String::from);
+        assert!(matches!(parse(args), Err(CliError::Usage(_))));
+    }
+
+    #[test]
+    fn test_demo() {
+        let output = run_to_string(&[]).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], format!("Searching to depth {}.", DEFAULT_DEPTH));
+        assert!(lines.last().unwrap().starts_with("Total time taken: "), "{}", output);
+    }
+
+    #[test]
+    fn test_solve() {
+        let (output, log) = run_to_strings(&["solve", "--scramble", "U R' BL", "--depth", "5"]).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let solution = Turn::parse_sequence(output.lines().next().unwrap()).unwrap();
+        assert!(!solution.is_empty() && solution.len() <= 5);
+        assert!(log.contains(&format!("Phase 1 solved in {} moves", solution.len())));
+
+        let tables = get_test_tables().lock().unwrap();
+        let move_tables = tables.move_tables.as_ref().unwrap();
+        let mut state = SOLVED_COORD_STATE;
+        for turn in Turn::parse_sequence("U R' BL").unwrap().iter().chain(solution.iter()) {
+            state.apply(move_tables, turn);
+        }
+        assert!(is_phase_1_solved(&state));
+    }
+
+    #[test]
+    fn test_solve_optimal() {
+        let output = run_to_string(&["solve", "--scramble", "R U", "--optimal"]).unwrap();
+        assert_eq!(output.lines().next(), Some("U' R'"));
+    }
+
+    #[test]
+    fn test_solve_ergonomic() {
+        let output = run_to_string(&["solve", "--scramble", "U R' BL", "--depth", "5", "--rank", "ergonomic"]).unwrap();
+        let ranked = Turn::parse_sequence(output.lines().next().unwrap()).unwrap();
+        let output = run_to_string(&["solve", "--scramble", "U R' BL", "--depth", "5", "--optimal"]).unwrap();
+        let shortest = Turn::parse_sequence(output.lines().next().unwrap()).unwrap();
+        // The first solution found is one of those the ranking chooses from
+        let scoring = Scoring::default();
+        assert!(score_sequence(&ranked, &scoring) >= score_sequence(&shortest, &scoring), "{:?} {:?}", ranked, shortest);
+
+        let tables = get_test_tables().lock().unwrap();
+        let mut state = SOLVED_COORD_STATE;
+        for turn in Turn::parse_sequence("U R' BL").unwrap().iter().chain(ranked.iter()) {
+            state.apply(tables.move_tables.as_ref().unwrap(), turn);
+        }
+        assert!(is_phase_1_solved(&state));
+        drop(tables);
+
+        let error = run_to_string(&["solve", "--input", "-", "--rank", "ergonomic"]).unwrap_err();
+        assert!(matches!(error, CliError::Usage(_)));
+    }
+
+    #[test]
+    fn test_solve_solved_state() {
+        let (output, log) = run_to_strings(&["solve", "--scramble", ""]).unwrap();
+        assert_eq!(output.lines().next(), Some(""));
+        assert!(log.contains("solved in 0 moves"));
+    }
+
+    #[test]
+    fn test_solve_reports_malformed_scramble() {
+        let error = run_to_string(&["solve", "--scramble", "U R2 BL"]).unwrap_err();
+        assert!(matches!(&error, CliError::Notation(ParseTurnError { token }) if token == "R2"));
+        assert!(error.to_string().contains("'R2'"));
+    }
+
+    #[test]
+    fn test_solve_requires_scramble() {
+        assert!(matches!(run_to_string(&["solve"]), Err(CliError::Usage(_))));
+        assert!(matches!(run_to_string(&["solve", "--scramble", "U", "--input", "-"]), Err(CliError::Usage(_))));
+    }
+
+    fn write_input_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("fto-scramble-{}-{}.txt", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_solve_batch() {
+        let input = write_input_file("batch", "R U\n\nU R2 BL\n  BL' D  \n");
+        let output = run_to_string(&["solve", "--input", &input, "--optimal"]).unwrap();
+        fs::remove_file(&input).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "U' R'");
+        assert_eq!(lines[1], "Error: could not read the scramble: unrecognised turn 'R2'");
+        assert_eq!(lines[2], "D'");
+    }
+
+    #[test]
+    fn test_solve_batch_json() {
+        let input = write_input_file("batch-json", "R U\nU R2 BL\nBL' D\n");
+        let output = run_to_string(&["--json", "solve", "--input", &input, "--optimal"]).unwrap();
+        fs::remove_file(&input).unwrap();
+
+        let entries: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["scramble"], "R U");
+        assert_eq!(entries[0]["solution"], "U' R'");
+        assert_eq!(entries[0]["length"], 2);
+        assert_eq!(entries[1]["scramble"], "U R2 BL");
+        assert!(entries[1]["error"].as_str().unwrap().contains("'R2'"));
+        assert_eq!(entries[2]["solution"], "D'");
+    }
+
+    #[test]
+    fn test_solve_batch_missing_file() {
+        let error = run_to_string(&["solve", "--input", "no-such-file.txt"]).unwrap_err();
+        assert!(matches!(&error, CliError::Read { path, .. } if path == "no-such-file.txt"));
+    }
+
+    #[test]
+    fn test_solve_json() {
+        let output = run_to_string(&["solve", "--scramble", "R  U", "--optimal", "--json"]).unwrap();
+        assert_eq!(output.lines().count(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["scramble"], "R U");
+        assert_eq!(json["solution"], "U' R'");
+        assert_eq!(json["length"], 2);
+        assert!(json["nodes"].as_u64().unwrap() > 0);
+        assert!(json["time_ms"].as_f64().unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn test_scramble_json() {
+        let output = run_to_string(&["--json", "scramble", "--count", "2", "--seed", "7"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let scrambles = json.as_array().unwrap();
+        assert_eq!(scrambles.len(), 2);
+
+        let text_output = run_to_string(&["scramble", "--count", "2", "--seed", "7"]).unwrap();
+        for (entry, line) in scrambles.iter().zip(text_output.lines()) {
+            assert_eq!(entry["scramble"], line);
+
+            let mut state = RawState::solved();
+            for turn in Turn::parse_sequence(line).unwrap().iter() {
+                state.apply(turn);
+            }
+            let coords = state.to_coords();
+            assert_eq!(entry["coordinates"]["corners"], coords.corners);
+            assert_eq!(entry["coordinates"]["edges_within_faces"], coords.edges_within_faces);
+            assert_eq!(entry["coordinates"]["edges_across_faces"], coords.edges_across_faces);
+            assert_eq!(entry["coordinates"]["up_centres"], coords.up_centres);
+            assert_eq!(entry["coordinates"]["down_centres"], coords.down_centres);
+        }
+    }
+
+    #[test]
+    fn test_scramble() {
+        let output = run_to_string(&["scramble", "--count", "3", "--seed", "7"]).unwrap();
+        assert_eq!(output.lines().count(), 3);
+        for line in output.lines() {
+            assert_eq!(Turn::parse_sequence(line).unwrap().len(), SCRAMBLE_LENGTH);
+        }
+        assert_eq!(run_to_string(&["scramble", "--count", "3", "--seed", "7"]).unwrap(), output);
+    }
+
+    fn make_temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("fto-scramble-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(feature = "svg")]
+    fn get_style_rules(svg: &str) -> Vec<&str> {
+        svg.split('}').filter(|rule| rule.contains("fill:")).collect()
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_draw_scramble() {
+        let dir = make_temp_dir("draw");
+        let out = dir.join("state.svg").to_string_lossy().into_owned();
+        let output = run_to_string(&["draw", "--scramble", "R U BL'", "--out", &out, "--layout", "net", "--width", "300"]).unwrap();
+        assert_eq!(output, format!("Saved {}\n", out));
+
+        let svg = fs::read_to_string(&out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(svg.contains("width=\"300\""));
+
+        let solved = drawstate::get_svg_for_state_with_options(&RawState::solved(),
+            &RenderOptions { layout: Layout::Net, ..Default::default() });
+        let solved_rules = get_style_rules(&solved);
+        assert!(get_style_rules(&svg).iter().any(|rule| !solved_rules.contains(rule)));
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_draw_query() {
+        let dir = make_temp_dir("draw-query");
+        let out = dir.join("state.svg").to_string_lossy().into_owned();
+        let mut state = RawState::solved();
+        state.apply(&Turn::new(Face::R, false));
+        let options = RenderOptions { layout: Layout::TopView, ..Default::default() };
+        let query = RenderRequest::from_state(&state, options.clone()).to_query_string();
+        run_to_string(&["draw", "--query", &query, "--out", &out, "--width", "300"]).unwrap();
+
+        let svg = fs::read_to_string(&out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(svg, drawstate::get_svg_for_state_with_options(&state, &RenderOptions { width: Some(300), ..options }));
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_draw_pattern() {
+        let dir = make_temp_dir("draw-pattern");
+        let out = dir.join("checker.svg").to_string_lossy().into_owned();
+        run_to_string(&["draw", "--pattern", "checker", "--out", &out]).unwrap();
+        let svg = fs::read_to_string(&out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let pattern = patterns::get("checker").unwrap();
+        assert_eq!(svg, drawstate::get_svg_for_state_with_options(&pattern.get_state(), &RenderOptions::default()));
+
+        let error = run_to_string(&["draw", "--pattern", "nonsense", "--out", "unused.svg"]).unwrap_err();
+        assert_eq!(error.get_exit_code(), EXIT_USAGE);
+        assert!(run_to_string(&["draw", "--pattern", "checker", "--scramble", "R", "--out", "unused.svg"]).is_err());
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_draw_state_json() {
+        let dir = make_temp_dir("draw-json");
+        let scrambles = run_to_string(&["--json", "scramble", "--seed", "2"]).unwrap();
+        let entry = serde_json::from_str::<serde_json::Value>(&scrambles).unwrap()[0].to_string();
+        let state_file = dir.join("state.json");
+        fs::write(&state_file, &entry).unwrap();
+
+        let out = dir.join("state.svg").to_string_lossy().into_owned();
+        run_to_string(&["draw", "--state-json", &state_file.to_string_lossy(), "--out", &out,
+            "--colours", "#fff,#0f0,#f0f,#888,#ff0,#00f,#f80,#f00"]).unwrap();
+        let svg = fs::read_to_string(&out).unwrap();
+
+        let mut state = RawState::solved();
+        let scramble = serde_json::from_str::<serde_json::Value>(&entry).unwrap()["scramble"].as_str().unwrap().to_string();
+        for turn in Turn::parse_sequence(&scramble).unwrap().iter() {
+            state.apply(turn);
+        }
+        let options = RenderOptions {
+            colour_scheme: ColourScheme::new(["#fff", "#0f0", "#f0f", "#888", "#ff0", "#00f", "#f80", "#f00"]),
+            ..Default::default()
+        };
+        assert_eq!(svg, drawstate::get_svg_for_state_with_options(&state, &options));
+
+        fs::write(&state_file, "{\"corners\": 1}").unwrap();
+        let error = run_to_string(&["draw", "--state-json", &state_file.to_string_lossy(), "--out", &out]).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(error.to_string(), "could not read the state: missing or invalid \"edges_within_faces\" coordinate");
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_draw_errors() {
+        let error = run_to_string(&["draw", "--scramble", "R X", "--out", "unused.svg"]).unwrap_err();
+        assert!(matches!(error, CliError::Notation(_)));
+
+        let error = run_to_string(&["draw", "--scramble", "R", "--out", "no-such-dir/state.svg"]).unwrap_err();
+        assert!(matches!(error, CliError::Draw(DrawError::Io { .. })));
+        assert!(error.to_string().starts_with("could not write 'no-such-dir/state.svg'"));
+        assert_eq!(error.to_string().lines().count(), 1);
+
+        let error = run_to_string(&["draw", "--scramble", "R", "--out", "unused.svg", "--colours", "pastel"]).unwrap_err();
+        assert!(matches!(error, CliError::Draw(DrawError::UnknownPreset(_))));
+        assert!(matches!(run_to_string(&["draw", "--scramble", "R", "--out", "unused.svg", "--colours", "#fff,#000"]),
+            Err(CliError::Usage(_))));
+
+        let error = run_to_string(&["draw", "--query", "fd=UUU", "--out", "unused.svg"]).unwrap_err();
+        assert_eq!(error.to_string(), "could not read the query: expected 72 facelets but got 3");
+        assert_eq!(error.get_exit_code(), EXIT_USAGE);
+        assert!(matches!(run_to_string(&["draw", "--query", "layout=net", "--layout", "net", "--out", "unused.svg"]),
+            Err(CliError::Usage(_))));
+    }
+
+    #[cfg(not(feature = "svg"))]
+    #[test]
+    fn test_draw_compiled_out() {
+        let error = run_to_string(&["draw", "--scramble", "R", "--out", "unused.svg"]).unwrap_err();
+        assert_eq!(error.to_string(), "the draw command was compiled out. Build with the svg feature to use it");
+        assert_eq!(error.get_exit_code(), EXIT_USAGE);
+
+        let error = run_to_string(&["apply", "--scramble", "R", "--ascii"]).unwrap_err();
+        assert_eq!(error.to_string(), "the apply --ascii command was compiled out. Build with the svg feature to use it");
+        assert!(run_to_string(&["apply", "--scramble", "R"]).is_ok());
+    }
+
+    #[test]
+    fn test_gen_tables_then_solve_loads_them() {
+        let dir = make_temp_dir("gen-tables");
+        let dir_arg = dir.to_string_lossy().into_owned();
+
+        let (output, _) = run_with_tables(&["gen-tables", "--dir", &dir_arg, "--threads", "2"], &mut Tables::new()).unwrap();
+        for coord_type in CoordinateType::iter() {
+            assert!(output.contains(&format!("{:?} move table: ", coord_type)));
+            assert!(output.contains(&format!("{:?} pruning table: ", coord_type)));
+        }
+        assert!(output.contains(&format!("{}: ", dir.join(MOVE_TABLE_FILE_NAME).display())));
+
+        let (output, log) = run_with_tables(&["--json", "gen-tables", "--dir", &dir_arg], &mut Tables::new()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["generated"], false);
+        assert_eq!(json["files"].as_array().unwrap().len(), 2);
+        assert!(log.contains("already complete"));
+
+        let (output, log) = run_with_tables(&["solve", "--scramble", "R U", "--optimal", "--tables-dir", &dir_arg],
+            &mut Tables::new()).unwrap();
+        assert_eq!(output.lines().next(), Some("U' R'"));
+        assert!(log.contains("Loaded move tables from"));
+        assert!(log.contains("Loaded pruning tables from"));
+        assert!(!log.contains("Loading"));
+
+        // A damaged file is reported rather than silently regenerated
+        let pruning_path = dir.join(PRUNING_TABLE_FILE_NAME);
+        let data = fs::read(&pruning_path).unwrap();
+        fs::write(&pruning_path, &data[..data.len() / 2]).unwrap();
+        let error = run_with_tables(&["solve", "--scramble", "R U", "--tables-dir", &dir_arg], &mut Tables::new()).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(&error, CliError::BadTables { path, .. } if *path == pruning_path));
+    }
+
+    #[test]
+    fn test_verify() {
+        let output = run_to_string(&["verify", "--scramble", "R U BL'", "--solution", "BL U' R'"]).unwrap();
+        assert_eq!(output, "The solution is correct\n");
+
+        let error = run_to_string(&["verify", "--scramble", "R U BL'", "--solution", "BL U'"]).unwrap_err();
+        assert!(matches!(error, CliError::Verify(VerifyFailure::WrongCoordinate { moves: 2, .. })));
+
+        let output = run_to_string(&["--json", "verify", "--scramble", "R U", "--solution", "U' R'", "--phase1"]).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&output).unwrap()["valid"], true);
+
+        let error = run_to_string(&["verify", "--scramble", "R U", "--solution", "U2"]).unwrap_err();
+        assert_eq!(error.to_string(), "could not read the solution: unrecognised turn 'U2'");
+    }
+
+    /// Sends each request on one connection, returning the response to each.
+    fn send_requests(connection: impl io::Read + Write, requests: &[&str]) -> Vec<serde_json::Value> {
+        let mut reader = io::BufReader::new(connection);
+        requests.iter()
+            .map(|request| {
+                writeln!(reader.get_mut(), "{}", request).unwrap();
+                let mut line = String::new();
+                io::BufRead::read_line(&mut reader, &mut line).unwrap();
+                serde_json::from_str(&line).unwrap()
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_socket() {
+        let dir = make_temp_dir("serve");
+        let socket = dir.join("fto.sock");
+        let socket_arg = socket.to_string_lossy().into_owned();
+
+        let mut guard = get_test_tables().lock().unwrap();
+        let tables: &mut Tables = &mut guard;
+        let (result, responses) = thread::scope(|scope| {
+            let server = scope.spawn(|| run_with_tables(&["--json", "serve", "--socket", &socket_arg, "--once"], tables));
+            while !socket.exists() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let connection = std::os::unix::net::UnixStream::connect(&socket).unwrap();
+            let responses = send_requests(connection, &[
+                r#"{"cmd":"solve","scramble":"R U","optimal":true}"#,
+                r#"{"cmd":"scramble","count":2,"seed":1}"#,
+            ]);
+            (server.join().unwrap(), responses)
+        });
+
+        let (output, _) = result.unwrap();
+        assert_eq!(output, format!("{}\n", json!({ "listening": socket_arg })));
+        assert_eq!(responses[0]["solution"], "U' R'");
+        assert_eq!(responses[1].as_array().unwrap().len(), 2);
+        // The socket is removed once the server stops
+        assert!(!socket.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_serve_tcp() {
+        let (move_tables, pruning_tables) = get_test_tables().lock().unwrap().get_shared(&mut Timings::default()).unwrap();
+        let listener = Listener::bind(&Address::Port(0)).unwrap();
+        let address = listener.get_address();
+        let options = ServeOptions { depth: 3, threads: 2, once: true };
+
+        let server = thread::spawn(move || serve::serve(listener, move_tables, pruning_tables, &options));
+        let connection = std::net::TcpStream::connect(&address).unwrap();
+        let responses = send_requests(connection, &[
+            r#"{"cmd":"draw","scramble":"R","layout":"net"}"#,
+            r#"{"cmd":"solve","scramble":"R X"}"#,
+            r#"{"cmd":"solve","scramble":"R U BL D F"}"#,
+            "not json",
+            r#"{"cmd":"draw","query":"layout=net&hl=c0"}"#,
+            r#"{"cmd":"draw","query":"hl=c9"}"#,
+        ]);
+        server.join().unwrap().unwrap();
+
+        #[cfg(feature = "svg")]
+        assert!(responses[0]["svg"].as_str().unwrap().starts_with("<svg"));
+        #[cfg(not(feature = "svg"))]
+        assert_eq!(responses[0]["error"], "the draw command was compiled out. Build with the svg feature to use it");
+        assert_eq!(responses[1]["error"], "could not read the scramble: unrecognised turn 'X'");
+        // The depth given to the server applies when the request doesn't give one
+        assert_eq!(responses[2]["error"], "no solution found within 3 moves");
+        assert!(responses[3]["error"].as_str().unwrap().starts_with("could not read the request"));
+        #[cfg(feature = "svg")]
+        assert!(responses[4]["svg"].as_str().unwrap().starts_with("<svg"));
+        #[cfg(feature = "svg")]
+        assert_eq!(responses[5]["error"], "could not read the query: could not read 'c9' for hl");
+    }
+
+    #[test]
+    fn test_bench() {
+        let output = run_to_string(&["bench", "--cases", "3", "--depth", "3", "--seed", "5"]).unwrap();
+        assert_eq!(output.lines().next(), Some("Seed 5, 3 cases, depth 3"));
+        assert!(output.contains("Median solve time"));
+        assert!(output.contains("Nodes per second"));
+        assert!(!output.contains("no solution"));
+
+        let output = run_to_string(&["--json", "bench", "--cases", "3", "--depth", "3"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["seed"], bench::DEFAULT_SEED);
+        assert_eq!(json["solved"], 3);
+        assert!(json["average_nodes"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test_case(&["solve", "--scramble", "R X"], EXIT_USAGE; "bad notation")]
+    #[test_case(&["solve", "--depth", "0", "--scramble", "R U"], EXIT_NO_SOLUTION; "no solution")]
+    #[test_case(&["verify", "--scramble", "R", "--solution", "U"], EXIT_NO_SOLUTION; "wrong solution")]
+    #[test_case(&["solve", "--input", "does-not-exist.txt"], EXIT_IO; "missing input")]
+    #[test_case(&["verify", "--scramble", "R", "--solution", "U2"], EXIT_USAGE; "bad solution")]
+    #[cfg_attr(feature = "svg", test_case(&["draw", "--scramble", "R", "--colours", "nonsense", "--out", "unused.svg"], EXIT_USAGE; "unknown colours"))]
+    fn test_get_exit_code(args: &[&str], expected: i32) {
+        assert_eq!(run_to_string(args).unwrap_err().get_exit_code(), expected);
+    }
+
+    #[test]
+    fn test_config_precedence() {
+        let config = write_input_file("config-precedence.toml", "depth = 0\nformat = \"json\"\ncolours = \"nonsense\"\n");
+
+        // The config file's settings are used in place of the built in defaults
+        let error = run_to_string(&["solve", "--scramble", "R U", "--config", &config]).unwrap_err();
+        assert!(matches!(error, CliError::NoSolution(0)));
+        #[cfg(feature = "svg")]
+        assert!(matches!(run_to_string(&["draw", "--scramble", "R", "--out", "unused.svg", "--config", &config]),
+            Err(CliError::Draw(DrawError::UnknownPreset(_)))));
+
+        // But options on the command line win over them
+        let output = run_to_string(&["solve", "--scramble", "R U", "--depth", "5", "--config", &config]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert!(json["length"].as_u64().unwrap() <= 5);
+        std::fs::remove_file(config).unwrap();
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let config = write_input_file("config-errors.toml", "depth = 9\ncolour = \"default\"\n");
+        let error = run_to_string(&["scramble", "--config", &config]).unwrap_err();
+        std::fs::remove_file(&config).unwrap();
+        assert_eq!(error.get_exit_code(), EXIT_USAGE);
+        assert!(error.to_string().contains("line 2: `colour = \"default\"`: unknown field `colour`"), "{}", error);
+
+        let error = run_to_string(&["scramble", "--config", "/no/such/dir/config.toml"]).unwrap_err();
+        assert_eq!(error.get_exit_code(), EXIT_IO);
+    }
+
+    #[test]
+    fn test_selftest() {
+        let output = run_to_string(&["selftest", "--samples", "10", "--seed", "1"]).unwrap();
+        assert_eq!(output, "PASS move tables\nPASS coordinate round trips\nPASS move tables match raw states\n");
+
+        let output = run_to_string(&["--json", "selftest", "--samples", "10"]).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json["passed"], true);
+        assert_eq!(json["checks"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_selftest_finds_corrupted_table() {
+        let dir = make_temp_dir("selftest");
+        let path = dir.join(MOVE_TABLE_FILE_NAME);
+        get_test_tables().lock().unwrap().move_tables.as_ref().unwrap().save_to(&path).unwrap();
+
+        // The file starts with the type of the first table, then the U face and its entry for coordinate 0
+        let mut data = fs::read(&path).unwrap();
+        let coord_type = CoordinateType::from_byte(data[3]);
+        let value = (u32::from_be_bytes(data[8..12].try_into().unwrap()) + 1) % coord_type.get_size() as u32;
+        data[8..12].copy_from_slice(&value.to_be_bytes());
+        fs::write(&path, data).unwrap();
+
+        let args = ["fto-scramble", "selftest", "--samples", "10", "--tables-dir", dir.to_str().unwrap()].map(String::from);
+        let mut out = Vec::new();
+        let result = run(parse(args).unwrap(), &DefaultPaths::default(), &mut Tables::new(), &mut out);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(CliError::SelfTest(1))));
+        let output = String::from_utf8(out).unwrap();
+        assert!(output.contains(&format!("FAIL move tables: the {:?} move table turns 0 to {} for face U", coord_type, value)),
+            "{}", output);
+    }
+
+    const TIMING_NAMES: [&str; 5] = ["table_load_ms", "pruning_ms", "search_ms", "render_ms", "total_ms"];
+
+    #[test]
+    fn test_timings() {
+        let output = run_to_string(&["solve", "--scramble", "U R'", "--depth", "3", "--timings"]).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let timings = lines[1].strip_prefix("timings: ").unwrap();
+        let names: Vec<&str> = timings.split(' ').map(|field| {
+            let (name, ms) = field.split_once('=').unwrap();
+            assert!(ms.parse::<f64>().unwrap() >= 0.0, "{}", field);
+            name
+        }).collect();
+        assert_eq!(names, TIMING_NAMES);
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_timings_json() {
+        let dir = make_temp_dir("timings");
+        let path = dir.join("state.svg").to_string_lossy().into_owned();
+        let output = run_to_string(&["draw", "--scramble", "R", "--out", &path, "--json", "--timings"]).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let json: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        for name in TIMING_NAMES {
+            assert!(json["timings"][name].as_f64().unwrap() >= 0.0, "{}", lines[1]);
+        }
+    }
+}
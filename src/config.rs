@@ -0,0 +1,158 @@
+/*
+Settings read from a TOML config file, so that options that rarely change don't need to be given every time. Each
+key matches the command line option it sets a default for, and options given on the command line win over the file.
+
+    tables-dir = "/var/lib/fto-scramble"
+    depth = 10
+    threads = 4
+    colours = "deuteranopia"
+    format = "json"
+*/
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Where to load tables from, and where gen-tables saves them.
+    pub tables_dir: Option<PathBuf>,
+    /// The most turns a solution may have.
+    pub depth: Option<u8>,
+    /// How many move tables gen-tables generates at once.
+    pub threads: Option<NonZeroUsize>,
+    /// A preset colour scheme, or eight colours separated by commas, for draw.
+    pub colours: Option<String>,
+    pub format: Option<OutputFormat>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: io::Error },
+    /// The file isn't valid TOML, or has a key that isn't known or a value of the wrong type. The line is counted
+    /// from 1, and is missing if the problem isn't with any one line, such as a key that has to be a table.
+    Parse { path: PathBuf, line: Option<usize>, text: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read { path, source } => write!(f, "could not read the config file '{}': {}", path.display(), source),
+            Self::Parse { path, line: Some(line), text, message } =>
+                write!(f, "{} line {}: `{}`: {}", path.display(), line, text, message),
+            Self::Parse { path, line: None, message, .. } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads the config file given with --config, or else the one at `default_path` if there is one there. Only a
+    /// file that was asked for by name has to exist.
+    pub fn load(path: Option<&Path>, default_path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Self::load_from(path),
+            None => match default_path {
+                Some(path) if path.exists() => Self::load_from(path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        Self::parse(&contents, path)
+    }
+
+    /// Reads a config from the contents of `path`, which is only used in errors.
+    pub fn parse(contents: &str, path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|error| {
+            let line = error.span().map(|span| contents[..span.start].matches('\n').count());
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line: line.map(|line| line + 1),
+                text: line.and_then(|line| contents.lines().nth(line)).unwrap_or_default().trim().to_string(),
+                message: error.message().to_string(),
+            }
+        })
+    }
+}
+
+/// Where the config file is read from when --config isn't given.
+pub fn get_default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("fto-scramble").join(CONFIG_FILE_NAME))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn parse(contents: &str) -> Result<Config, ConfigError> {
+        Config::parse(contents, Path::new("config.toml"))
+    }
+
+    #[test]
+    fn test_parse() {
+        let config = parse("tables-dir = \"/tmp/tables\"\ndepth = 10\nthreads = 4\ncolours = \"deuteranopia\"\nformat = \"json\"\n");
+        assert_eq!(config.unwrap(), Config {
+            tables_dir: Some(PathBuf::from("/tmp/tables")),
+            depth: Some(10),
+            threads: NonZeroUsize::new(4),
+            colours: Some(String::from("deuteranopia")),
+            format: Some(OutputFormat::Json),
+        });
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(parse("# Nothing set yet\n").unwrap(), Config::default());
+    }
+
+    #[test_case("depth = 9\ndepth = \"deep\"\n", 2, "depth = \"deep\""; "wrong type")]
+    #[test_case("depth = 9\n\ncolour = \"classic\"\n", 3, "colour = \"classic\""; "unknown key")]
+    #[test_case("format = \"yaml\"\n", 1, "format = \"yaml\""; "unknown format")]
+    #[test_case("threads = 0\n", 1, "threads = 0"; "no threads")]
+    #[test_case("depth = \n", 1, "depth ="; "not toml")]
+    fn test_parse_error(contents: &str, expected_line: usize, expected_text: &str) {
+        match parse(contents).unwrap_err() {
+            ConfigError::Parse { line, text, .. } => {
+                assert_eq!(line, Some(expected_line));
+                assert_eq!(text, expected_text);
+            }
+            error => panic!("expected a parse error, got {}", error),
+        }
+    }
+
+    #[test]
+    fn test_parse_error_message() {
+        let error = parse("depth = 9\ncolour = \"classic\"\n").unwrap_err();
+        assert!(error.to_string().starts_with("config.toml line 2: `colour = \"classic\"`: unknown field `colour`"),
+            "{}", error);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let error = Config::load(Some(Path::new("/no/such/dir/config.toml")), None).unwrap_err();
+        assert!(matches!(error, ConfigError::Read { .. }));
+    }
+}
@@ -1,3 +1,48 @@
+//! Coordinates compress each part of the puzzle state into a single `u32`, small enough to index move and
+//! pruning tables. The pieces, their ordering and what counts as a flipped corner are defined in the comment at
+//! the top of `state.rs`; this module only deals with turning those arrays into numbers and back.
+//!
+//! Coordinate types:
+//! - `CornerState`: corner permutation and orientation together, 0..11_520.
+//! - `EdgeInFace`: which up face each edge's group belongs to, treating the three edges of a group as identical, 0..369_600.
+//! - `EdgeAcrossFaces`: the remaining information needed to tell the edges within each group apart, 0..34_650.
+//! - `UpCentre`: the up centres, four colours of three identical pieces, 0..369_600.
+//! - `DownCentre`: the down centres, encoded in the same way as the up centres.
+//! - `TripleCentre`: the down centres numbered relative to the corners instead of fixed positions (see below). It
+//!   isn't in `CoordinateType::iter`, for the reason given there.
+//!
+//! Parity:
+//! Every turn cycles three corners, and a 3-cycle is an even permutation, so only even corner
+//! permutations can be reached. That halves the 6! = 720 arrangements to 360. The permutation encoding uses
+//! this by only recording how far out of place each piece is from the third piece onwards; the order of the first
+//! two pieces is whichever makes the permutation even. Corner orientation works the same way: the number of
+//! flipped corners is always even, so only five of the six flips are stored and the first is worked out from the others, giving
+//! 2^5 = 32 orientations. Edges are also always an even permutation.
+//!
+//! Sub-permutation encoding:
+//! Centres come in groups of identical pieces, so a full permutation coordinate would count the same state many
+//! times over. Instead, the positions of one group at a time are encoded as a combination, counted with binomial
+//! coefficients. The first group picks 3 of the 12 positions, the next picks 3 of the 9 that are left, and the
+//! next 3 of the remaining 6, after which the last group has no choice. The three sub-coordinates have ranges of
+//! binomial(12,3) = 220, binomial(9,3) = 84 and binomial(6,3) = 20 and are combined as a mixed radix number,
+//! which gives 220 * 84 * 20 = 369_600 values.
+//!
+//! Why two edge coordinates:
+//! There are 12!/2 = 239_500_800 edge permutations, far too many for a move table with an entry per face. Edges
+//! are instead split two ways. Grouping them by up face (indices 0..3, 3..6, 6..9 and 9..12) gives four groups of
+//! three, encoded with the sub-permutation encoding exactly like the centres. Grouping them by index modulo 3, so
+//! that each group has one edge from each up face, gives three groups of four, which have 495 * 70 = 34_650
+//! arrangements. Neither coordinate is enough on its own, but together they identify every edge, and each one
+//! is small enough to have its own move table.
+//!
+//! Down centres and triple centres:
+//! `DownCentre` and `TripleCentre` both use the face position coordinate, so both have `NUM_FACE_PIECE_PERMS`
+//! possible values, but they encode different things. The down centre coordinate is position-absolute: it
+//! records which colour of down centre sits in each of the twelve fixed down centre positions. The triple
+//! centre coordinate instead follows the corners. Each corner and the two down centres touching it form a
+//! triple, and triple centre positions are numbered relative to the corner they are attached to, so a turn
+//! only changes the coordinate if it breaks up triples. Up face turns move whole triples and leave the triple
+//! centre coordinate unchanged, while the down centre coordinate changes with every turn that moves down centres.
 use core::panic;
 use lazy_static::lazy_static;
 
@@ -11,19 +56,31 @@ pub const NUM_CORNER_STATES: usize = 11_520;
 pub const NUM_EDGE_PERMS: usize = 239_500_800;
 pub const NUM_FACE_PIECE_PERMS: usize = 369_600;
 pub const NUM_ACROSS_FACE_PERMS: usize = 34_650;
+/// The number of coordinate types in `CoordinateType::iter`, which are the ones a `CoordState` is made of.
+pub const NUM_COORDINATES: usize = 5;
+
+// The number of pieces at the start of a permutation that are left out of the coordinate, because once every
+// other piece is placed, parity decides which way round they go.
+const NUM_PARITY_PIECES: usize = 2;
 
 // Triple centres depend on the definitions for down centres and corner state.
 const CORNER_MAIN_TRIPLE_CENTRE: [usize; NUM_CORNERS] = [6, 0, 3, 11, 10, 9];
 const CORNER_FLIPPED_TRIPLE_CENTRE: [usize; NUM_CORNERS] = [1, 4, 7, 2, 5, 8];
 
+// The binomial table is indexed by a count of centres from 0 to NUM_CENTRES inclusive, in both dimensions, so it
+// has to be at least (NUM_CENTRES + 1) × (NUM_CENTRES + 1). Indexing past the end would panic rather than corrupt
+// anything, but only for the states that reach it, so check the size when compiling instead.
+const BINOMIAL_TABLE_SIZE: usize = 13;
+const _: () = assert!(BINOMIAL_TABLE_SIZE > NUM_CENTRES);
+
 lazy_static! {
-    static ref BINOMIAL_TABLE: [[u32; 13]; 13] = precompute_binomial_table();
+    static ref BINOMIAL_TABLE: [[u32; BINOMIAL_TABLE_SIZE]; BINOMIAL_TABLE_SIZE] = precompute_binomial_table();
 }
 
 
-fn precompute_binomial_table() -> [[u32; 13]; 13] {
-    let mut binomial_table = [[0; 13]; 13];
-    for n in 0..=12 {
+fn precompute_binomial_table() -> [[u32; BINOMIAL_TABLE_SIZE]; BINOMIAL_TABLE_SIZE] {
+    let mut binomial_table = [[0; BINOMIAL_TABLE_SIZE]; BINOMIAL_TABLE_SIZE];
+    for n in 0..BINOMIAL_TABLE_SIZE {
         binomial_table[n][0] = 1;
         for k in 1..=n {
             binomial_table[n][k] = binomial_table[n-1][k-1] + binomial_table[n-1][k];
@@ -43,6 +100,13 @@ pub enum CoordinateType {
 }
 
 impl CoordinateType {
+    /// The coordinate types that make up a `CoordState`, which are the ones with move and pruning tables.
+    ///
+    /// `TripleCentre` is left out on purpose. Up face turns never change it, even when the triples have already
+    /// been broken up and the turn carries corners away from the centres they were next to, so it can't tell
+    /// whether the triples match the corners. After B' BL B the triple centre coordinate is back to 0, but the
+    /// corners have moved and the down centres haven't followed them. The phase 1 check uses
+    /// `do_triple_centres_match_corners` instead, which compares the down centres against the corners directly.
     pub fn iter() -> impl Iterator<Item = Self> {
         [
             Self::CornerState,
@@ -129,17 +193,18 @@ impl CoordinateType {
 ///
 /// Example:
 /// ```
+/// # use fto_scramble::coordinates::permutation_to_coord;
 ///   let coord = permutation_to_coord(&[0,1,2,3,4,5]);
 /// ```
-fn permutation_to_coord(positions: &[u8]) -> u32 {
+pub fn permutation_to_coord(positions: &[u8]) -> u32 {
     let mut coord: u32 = 0;  // Note: 32 bits can only handle up to 12 pieces.
-    for i in (2..positions.len()).rev() {
+    for i in (NUM_PARITY_PIECES..positions.len()).rev() {
         for j in 0..i {
             if positions[i] < positions[j] {
                 coord += 1;
             }
         }
-        if i > 2 {
+        if i > NUM_PARITY_PIECES {
             coord *= i as u32;
         }
     }
@@ -150,9 +215,10 @@ fn permutation_to_coord(positions: &[u8]) -> u32 {
 ///
 /// Example
 /// ```
+/// # use fto_scramble::coordinates::invert_coord_to_permutation;
 ///   let perm = invert_coord_to_permutation::<6>(0);
 /// ```
-fn invert_coord_to_permutation<const N: usize>(coord: u32) -> [u8; N] {
+pub fn invert_coord_to_permutation<const N: usize>(coord: u32) -> [u8; N] {
     let mut perm: [u8; N] = invert_coord_to_permutation_ignore_parity::<N>(coord);
     if !is_even_parity(&perm) {
         perm.swap(0, 1);
@@ -181,6 +247,14 @@ fn corner_state_to_coord(state: &[u8]) -> u32 {
     perm_coord + (orientation * NUM_CORNER_PERMS as u32)
 }
 
+/// Gives the same coordinate as `corner_state_to_coord`, but takes the corner permutation and the orientation
+/// bitmask from `RawState` as they are, rather than combined into one array. The flip of the first corner is
+/// fixed by the other five, so only the lowest five bits of the orientation are used.
+pub(crate) fn corner_state_to_coord_separate(perm: &[u8], orientation: u8) -> u32 {
+    let orientation_coord = (orientation & ((1 << (NUM_CORNERS - 1)) - 1)) as u32;
+    permutation_to_coord(perm) + (orientation_coord * NUM_CORNER_PERMS as u32)
+}
+
 fn invert_coord_to_corner_state(coord: u32) -> [u8; NUM_CORNERS] {
     let perm_coord = coord % NUM_CORNER_PERMS as u32;
     let mut state = invert_coord_to_permutation::<NUM_CORNERS>(perm_coord);
@@ -214,9 +288,10 @@ fn invert_coord_to_corner_state(coord: u32) -> [u8; NUM_CORNERS] {
 ///
 /// Example:
 /// ```
+/// # use fto_scramble::coordinates::face_position_to_coord;
 ///   let coord = face_position_to_coord(&[0,1,2,3,4,5,6,7,8,9,10,11]);
 /// ```
-fn face_position_to_coord(positions: &[u8]) -> u32 {
+pub fn face_position_to_coord(positions: &[u8]) -> u32 {
     sub_permutation_coord(positions, 4, 3)
 }
 
@@ -226,9 +301,10 @@ fn face_position_to_coord(positions: &[u8]) -> u32 {
 ///
 /// Example
 /// ```
+/// # use fto_scramble::coordinates::invert_coord_to_face_positions;
 ///   let perm = invert_coord_to_face_positions(0);
 /// ```
-fn invert_coord_to_face_positions(coord: u32) -> [u8; NUM_CENTRES] {
+pub fn invert_coord_to_face_positions(coord: u32) -> [u8; NUM_CENTRES] {
     invert_coord_to_sub_permutation::<3>(coord)
 }
 
@@ -256,6 +332,32 @@ pub fn get_down_centre_coord_for_matched_triples(corner_coord: u32) -> u32 {
 /// way as the face position coordinate, although there are 3 groups of 4 instead of 4 groups of 3. There are
 /// therefore 2 sub coordinates, since the final group is determined by the other two. The sub coordinates have
 /// ranges 0..494 and 0..69, giving a combined coordinate range of 0..34649.
+///
+/// Using the edge order from the table at the top of `movedefs.rs`, edge `i` is in group `i % 3`:
+///
+/// | Group | Edges              |
+/// | ----- | ------------------ |
+/// | 0     | UB, BLB, BRR, FL   |
+/// | 1     | UR, BLL, BRB, FR   |
+/// | 2     | UL, BLD, BRD, FD   |
+///
+/// Only the group of the edge in each position matters, not which edge of the group it is, and a position is also
+/// counted as belonging to the group it would hold when solved. `positions` starts as a label for each edge whose
+/// value divided by 4 is its group, and after the edges are permuted, `ordering` lists the positions group by
+/// group, so that the solved state reads `[0,0,0,0, 1,1,1,1, 2,2,2,2]` when divided by 4.
+///
+/// For example, take the 3-cycle where FL moves to UB, UB moves to UR and UR moves to FL, which is the state
+/// `[9,0,2,3,4,5,6,7,8,1,10,11]`. Listing the group of the edge in each position, group by group, gives:
+///
+/// ```text
+///   positions:  UB BLB BRR FL | UR BLL BRB FR | UL BLD BRD FD
+///   groups:      0  0   0   1 |  0  1   1   1 |  2  2   2   2
+/// ```
+///
+/// The group 2 edges are all in group 2 positions, so the first sub coordinate is 0. Leaving out group 2, the
+/// group 1 edges are the 4th, 6th, 7th and 8th of the remaining 8 positions, which is the first arrangement after
+/// the solved one, so the second sub coordinate is 1. The coordinate is `0 * 70 + 1 = 1`. Swapping the edges in the
+/// UB and BLB positions as well wouldn't change this, since both are group 0 edges in group 0 positions.
 fn perm_across_face_coord(edges: &[u8]) -> u32 {
     let mut positions: [u8; NUM_EDGES] = [0,4,8,1,5,9,2,6,10,3,7,11];
     let ordering: [u8; NUM_EDGES] = [0,3,6,9,1,4,7,10,2,5,8,11];
@@ -286,11 +388,11 @@ fn invert_coord_to_sub_permutation<const N: usize>(coord: u32) -> [u8; NUM_CENTR
             (NUM_CENTRES - (N*i)) as u8,
             (NUM_CENTRES - (N * (i+1))) as u8);
 
-        for j in 0..NUM_CENTRES {
-            if state[j] == 0 {
+        for item in state.iter_mut() {
+            if *item == 0 {
                 let piece = pieces.pop().unwrap();
                 if piece != u8::MAX {
-                    state[j] = piece;
+                    *item = piece;
                 }
             }
         }
@@ -298,6 +400,50 @@ fn invert_coord_to_sub_permutation<const N: usize>(coord: u32) -> [u8; NUM_CENTR
     state
 }
 
+/// Combines the positions of `num_groups` groups of `num_per_group` interchangeable pieces into one coordinate.
+/// Each entry of `positions` is a piece label whose group is `label / num_per_group`, and the groups are labelled by
+/// their first label, so for 4 groups of 3 they are 0, 3, 6 and 9.
+///
+/// The groups are encoded from the highest down. For each group, only the positions still holding that group or a
+/// lower one are considered, since the positions taken by higher groups are already known. The sub coordinate is
+/// the rank of where the group sits among those `n` positions, counted with the combinatorial number system: for
+/// every position holding a lower group, where it is the `n`th position considered and `k` pieces of the group have
+/// been seen before it, `binomial(n-1, k-1)` is added. A group that comes after all the lower pieces adds nothing,
+/// so the solved arrangement ranks 0, and one that comes before all of them ranks highest. The lowest group fills
+/// whatever is left, so it isn't encoded.
+///
+/// The sub coordinates are combined as a mixed radix number, highest group first. After each group, the coordinate
+/// so far is multiplied by `binomial(face, num_per_group)`, where `face` is that group's label, which is the number
+/// of positions left for the groups below it and so the range of the next sub coordinate. The first pass of the loop
+/// is for a label one past the highest group, which matches nothing and only multiplies 0, and the last multiplies
+/// by `binomial(num_per_group, num_per_group)`, which is 1.
+///
+/// For `face_position_to_coord(&[0,0,0,3,3,3,6,6,6,9,9,9])`, which is the solved state:
+///
+/// ```text
+///   label 12:  no pieces, sub coordinate 0, coord = 0 * binomial(12,3) = 0
+///   label 9:   the 9s come after all 9 lower pieces, so k is 0 each time and nothing is added.
+///              coord = (0 + 0) * binomial(9,3) = 0
+///   label 6:   considering only the first 9 positions, the 6s come after all 6 lower pieces.
+///              coord = (0 + 0) * binomial(6,3) = 0
+///   label 3:   considering only the first 6 positions, the 3s come after all 3 lower pieces.
+///              coord = (0 + 0) * binomial(3,3) = 0
+/// ```
+///
+/// For `[0,0,3,0,3,3,6,6,6,9,9,9]`, the 9s and 6s are still after everything lower, so the coordinate is 0 until
+/// the last group. For label 3, the first 6 positions are scanned:
+///
+/// ```text
+///   position:  0  1  2  3  4  5
+///   label:     0  0  3  0  3  3
+///   n:         1  2  3  4  5  6
+///   k:         0  0  1  1  2  3
+///   added:     -  -     1
+/// ```
+///
+/// The 0s in positions 0 and 1 come before any 3, so they add nothing. The 0 in position 3 is the 4th position
+/// considered and comes after one 3, so it adds `binomial(3, 0) = 1`. The coordinate is `(0 + 1) * binomial(3,3) =
+/// 1`, since swapping one 3 back past a single 0 is the first arrangement after the solved one.
 #[allow(clippy::comparison_chain)]
 fn sub_permutation_coord(positions: &[u8], num_groups: u32, num_per_group: u32) -> u32 {
     let mut coord: u32 = 0;
@@ -330,15 +476,23 @@ fn sub_permutation_coord(positions: &[u8], num_groups: u32, num_per_group: u32)
     coord
 }
 
+/// Returns the place values used to decode a permutation coordinate, where `factors[i] = i!/2` for `i >= 2` and
+/// `1` otherwise. The division by 2 is because the first two pieces are never encoded (their order is fixed by
+/// parity), which also means the digits for positions 0 and 1 are always zero and their factors are never used.
 fn get_factors<const N: usize>() -> [usize; N] {
     let mut factors = [1 ; N];
-    for i in 3..N {
-        factors[i] = factors[i-1] * i;
+    for (i, factor) in factors.iter_mut().enumerate().skip(NUM_PARITY_PIECES) {
+        *factor = get_partial_factorial(i, NUM_PARITY_PIECES);
     }
     factors
 }
 
-fn is_even_parity(perm: &[u8]) -> bool {
+/// Computes `n!/start!`, the product of the integers from `start + 1` up to `n`. Returns 1 if `n <= start`.
+fn get_partial_factorial(n: usize, start: usize) -> usize {
+    ((start + 1)..=n).product()
+}
+
+pub fn is_even_parity(perm: &[u8]) -> bool {
     // TODO. This is O(n^2). It would be nice to use a O(n log n) method.
     let n = perm.len();
     let mut result = true;
@@ -375,7 +529,7 @@ fn invert_single_face_centre_coord(mut coord: u32, num_pieces_to_place: u8, num_
 
     for j in 0..num_positions_to_fill {
         let n = num_positions_to_fill - j - 1;
-        let n_choose_k = BINOMIAL_TABLE[n as usize][num_left-1] as u32;
+        let n_choose_k = BINOMIAL_TABLE[n as usize][num_left-1];
         if coord >= n_choose_k {
             coord -= n_choose_k;
         }
@@ -394,6 +548,7 @@ fn invert_single_face_centre_coord(mut coord: u32, num_pieces_to_place: u8, num_
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::movedefs::Face;
     use test_case::test_case;
 
     #[test_case(&[0,2,4,6,8,10], 0)]
@@ -410,6 +565,63 @@ mod tests {
         assert_eq!(coord.state_to_coord(state), value);
     }
 
+    #[test_case(&[0,1,2,3,4,5], 0b000000, 0)]
+    #[test_case(&[0,1,2,3,4,5], 0b100001, 360)]
+    #[test_case(&[2,0,1,3,4,5], 0b000000, 1)]
+    #[test_case(&[0,1,5,3,2,4], 0b001001, 3327)]
+    #[test_case(&[4,5,3,2,1,0], 0b111111, 11_519)]
+    fn test_corner_state_to_coord_separate(perm: &[u8], orientation: u8, value: u32) {
+        assert_eq!(corner_state_to_coord_separate(perm, orientation), value);
+    }
+
+    #[test]
+    fn test_corner_state_to_coord_separate_matches_combined() {
+        for coord in 0..NUM_CORNER_STATES as u32 {
+            let state = invert_coord_to_corner_state(coord);
+            let perm: Vec<u8> = state.iter().map(|piece| piece / 2).collect();
+            let orientation = state.iter().fold(0, |orientation, piece| orientation * 2 + piece % 2);
+            assert_eq!(corner_state_to_coord_separate(&perm, orientation), corner_state_to_coord(&state));
+        }
+    }
+
+    #[test]
+    fn test_corner_state_encoding_is_bijection() {
+        for coord in 0..NUM_CORNER_STATES as u32 {
+            assert_eq!(corner_state_to_coord(&invert_coord_to_corner_state(coord)), coord);
+        }
+
+        // Every even permutation of the corners, found by checking each way of filling the six positions
+        let perms: Vec<[u8; NUM_CORNERS]> = (0..NUM_CORNERS.pow(NUM_CORNERS as u32))
+            .map(|mut index| {
+                let mut perm = [0; NUM_CORNERS];
+                for piece in perm.iter_mut() {
+                    *piece = (index % NUM_CORNERS) as u8;
+                    index /= NUM_CORNERS;
+                }
+                perm
+            })
+            .filter(|perm| (0..NUM_CORNERS as u8).all(|piece| perm.contains(&piece)) && is_even_parity(perm))
+            .collect();
+        assert_eq!(perms.len(), NUM_CORNER_PERMS);
+
+        let mut seen = vec![false; NUM_CORNER_STATES];
+        for perm in perms.iter() {
+            for flips in 0..(1u8 << NUM_CORNERS) {
+                if flips.count_ones() % 2 == 1 {
+                    continue;
+                }
+                let state: Vec<u8> = perm.iter().enumerate()
+                    .map(|(i, piece)| piece * 2 + ((flips >> i) & 1))
+                    .collect();
+                let coord = corner_state_to_coord(&state) as usize;
+                assert!(coord < NUM_CORNER_STATES, "{:?} gave coordinate {}", state, coord);
+                assert!(!seen[coord], "{:?} gave the repeated coordinate {}", state, coord);
+                seen[coord] = true;
+            }
+        }
+        assert!(seen.iter().all(|&seen| seen));
+    }
+
     #[test_case(&[0,1,2,3,4,5,6,7,8,9,10,11], 0)]
     #[test_case(&[1,0,3,2,4,5,6,7,8,9,10,11], 1)]
     #[test_case(&[11,10,9,8,7,6,5,4,3,2,1,0], 369_599)]
@@ -623,6 +835,21 @@ mod tests {
         }
     }
 
+    #[test_case(0, 0, 1)]
+    #[test_case(3, 5, 1)]
+    #[test_case(5, 0, 120)]
+    #[test_case(6, 2, 360)]
+    #[test_case(12, 9, 1320)]
+    fn test_get_partial_factorial(n: usize, start: usize, expected: usize) {
+        assert_eq!(get_partial_factorial(n, start), expected);
+    }
+
+    #[test]
+    fn test_get_factors() {
+        assert_eq!(get_factors::<7>(), [1, 1, 1, 3, 12, 60, 360]);
+        assert_eq!(get_factors::<12>()[11], 19_958_400);
+    }
+
     #[test]
     fn test_precompute_binomial_table() {
         let binomial_table = precompute_binomial_table();
@@ -643,4 +870,50 @@ mod tests {
         assert_eq!(binomial_table[4][4], 1);
         assert_eq!(binomial_table[11][7], 330);
     }
+
+    #[test]
+    fn test_triple_centre_coordinate_misses_broken_triples() {
+        use crate::movedefs::Turn;
+        use crate::state::{do_triple_centres_match_corners, RawState};
+
+        let sequence = Turn::parse_sequence("B' BL B").unwrap();
+        let mut triple_centres = CoordinateType::TripleCentre.coord_to_state(0);
+        let mut state = RawState::solved();
+        for turn in sequence.iter() {
+            for _ in 0..if turn.is_inverse() { 2 } else { 1 } {
+                apply_raw_permutation(&mut triple_centres, &turn.get_face().get_raw_turn().get_effect(TurnEffectType::TripleCentre));
+            }
+            state.apply(turn);
+        }
+
+        // The coordinate says the triples are matched, but BL has moved corners away from their centres
+        assert_eq!(CoordinateType::TripleCentre.state_to_coord(&triple_centres), 0);
+        let coords = state.to_coords();
+        assert!(!do_triple_centres_match_corners(coords.corners, coords.down_centres));
+    }
+
+    #[test]
+    fn test_down_centre_and_triple_centre_differ_under_moves() {
+        let solved = CoordinateType::DownCentre.coord_to_state(0);
+
+        for face in Face::get_up_faces() {
+            let turn = face.get_raw_turn();
+            let mut down_centres = solved.clone();
+            apply_raw_permutation(&mut down_centres, &turn.get_effect(TurnEffectType::DownCentre));
+            let mut triple_centres = solved.clone();
+            apply_raw_permutation(&mut triple_centres, &turn.get_effect(TurnEffectType::TripleCentre));
+
+            assert_ne!(CoordinateType::DownCentre.state_to_coord(&down_centres), 0);
+            assert_eq!(CoordinateType::TripleCentre.state_to_coord(&triple_centres), 0);
+        }
+
+        // Down face turns break up triples, so they move triple centres too
+        for face in Face::get_down_faces() {
+            let turn = face.get_raw_turn();
+            let mut triple_centres = solved.clone();
+            apply_raw_permutation(&mut triple_centres, &turn.get_effect(TurnEffectType::TripleCentre));
+
+            assert_ne!(CoordinateType::TripleCentre.state_to_coord(&triple_centres), 0);
+        }
+    }
 }
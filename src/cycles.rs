@@ -0,0 +1,237 @@
+/*
+Finds short algorithms for pure 3-cycles: sequences that move three pieces of one type around and leave everything
+else where it was, which are what FTO methods are built from.
+
+The search meets in the middle. Every coordinate state within half the length limit of solved is stored with the
+last turn of a shortest way to reach it, and the same is done starting from the state the cycle leads to. A state
+found from both sides joins a way there from solved with the way back from the goal, reversed and inverted. The
+coordinates compare centres by colour, so a cycle that only swaps centres of the same colour is the same as solved.
+*/
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::movedefs::{PieceType, Turn, NUM_CORNERS};
+use crate::movetables::MoveTables;
+use crate::state::{CoordState, RawState, SOLVED_COORD_STATE};
+
+
+/// Every state within `depth` turns of `start`, with how many turns it takes and the last of them. The start state
+/// has no last turn.
+fn get_states_within(move_tables: &MoveTables, start: CoordState, depth: u8) -> HashMap<CoordState, (u8, Option<Turn>)> {
+    let mut states = HashMap::from([(start, (0, None))]);
+    let mut frontier = vec![start];
+    for distance in 1..=depth {
+        let mut next_frontier = Vec::new();
+        for state in frontier {
+            for (turn, successor) in state.all_successors(move_tables) {
+                if let Entry::Vacant(entry) = states.entry(successor) {
+                    entry.insert((distance, Some(turn)));
+                    next_frontier.push(successor);
+                }
+            }
+        }
+        frontier = next_frontier;
+    }
+    states
+}
+
+/// The turns that lead from the start of `states` to `state`, following the stored last turns back.
+fn get_path(move_tables: &MoveTables, states: &HashMap<CoordState, (u8, Option<Turn>)>, mut state: CoordState) -> Vec<Turn> {
+    let mut path = Vec::new();
+    while let Some(turn) = states[&state].1 {
+        path.push(turn);
+        state.apply(move_tables, &turn.inverse());
+    }
+    path.reverse();
+    path
+}
+
+/// Searches for the shortest sequences that take solved to a given state, keeping the states near solved so that
+/// they can be reused for every goal, such as when listing every cycle of a piece type.
+pub struct CycleFinder<'a> {
+    move_tables: &'a MoveTables,
+    max_len: u8,
+    from_solved: HashMap<CoordState, (u8, Option<Turn>)>,
+}
+
+impl<'a> CycleFinder<'a> {
+    pub fn new(move_tables: &'a MoveTables, max_len: u8) -> Self {
+        let from_solved = get_states_within(move_tables, SOLVED_COORD_STATE, max_len.div_ceil(2));
+        Self { move_tables, max_len, from_solved }
+    }
+
+    /// The shortest sequence of at most `max_len` turns that takes the solved state to `goal`, or `None` if there
+    /// isn't one or the goal can't be reached at all.
+    pub fn find(&self, goal: &RawState) -> Option<Vec<Turn>> {
+        if goal.validate().is_err() {
+            return None;
+        }
+        let from_goal = get_states_within(self.move_tables, goal.to_coords(), self.max_len / 2);
+        let (meeting_state, _) = from_goal.iter()
+            .filter_map(|(state, (goal_distance, _))| self.from_solved.get(state)
+                .map(|(solved_distance, _)| (*state, solved_distance + goal_distance)))
+            .min_by_key(|(_, distance)| *distance)?;
+
+        let mut sequence = get_path(self.move_tables, &self.from_solved, meeting_state);
+        sequence.extend(get_path(self.move_tables, &from_goal, meeting_state).iter().rev().map(Turn::inverse));
+        Some(sequence)
+    }
+}
+
+/// The state where the piece in position `pieces[0]` has moved to `pieces[1]`, that one to `pieces[2]` and that
+/// one back to `pieces[0]`, with everything else solved. For corners, `flips` says which of the three end up
+/// flipped, by the position they end up in. Other pieces have no orientation, so `flips` has to be all false for
+/// them. Returns `None` if the positions aren't three different ones of that type.
+pub fn get_cycle_state(piece_type: PieceType, pieces: [usize; 3], flips: [bool; 3]) -> Option<RawState> {
+    let num_pieces = piece_type.get_num_pieces();
+    if pieces.iter().any(|piece| *piece >= num_pieces) || pieces[0] == pieces[1] || pieces[1] == pieces[2]
+        || pieces[0] == pieces[2] || (piece_type != PieceType::Corner && flips.contains(&true)) {
+        return None;
+    }
+
+    let mut state = RawState::solved();
+    let positions = match piece_type {
+        PieceType::Corner => &mut state.corners,
+        PieceType::Edge => &mut state.edges,
+        PieceType::UpCentre => &mut state.up_centres,
+        PieceType::DownCentre => &mut state.down_centres,
+    };
+    let moved = pieces.map(|piece| positions[piece]);
+    for i in 0..3 {
+        positions[pieces[(i + 1) % 3]] = moved[i];
+    }
+    for (piece, flipped) in pieces.iter().zip(flips) {
+        if flipped {
+            state.corner_orientation |= 1 << (NUM_CORNERS - 1 - piece);
+        }
+    }
+    Some(state)
+}
+
+/// The shortest sequence of at most `max_len` turns that makes exactly the 3-cycle described for
+/// `get_cycle_state`, or `None` if there isn't one.
+pub fn find_pure_cycle(move_tables: &MoveTables, piece_type: PieceType, pieces: [usize; 3], flips: [bool; 3], max_len: u8) -> Option<Vec<Turn>> {
+    let goal = get_cycle_state(piece_type, pieces, flips)?;
+    CycleFinder::new(move_tables, max_len).find(&goal)
+}
+
+/// Every pure corner 3-cycle that can be done in at most `max_len` turns, with the shortest sequence for each, for
+/// building algorithm sheets. Each cycle is listed once, starting from its smallest position, with each even
+/// choice of flips.
+pub fn find_pure_corner_cycles(move_tables: &MoveTables, max_len: u8) -> Vec<([usize; 3], [bool; 3], Vec<Turn>)> {
+    let finder = CycleFinder::new(move_tables, max_len);
+    let mut cycles = Vec::new();
+    for first in 0..NUM_CORNERS {
+        for second in first + 1..NUM_CORNERS {
+            for third in first + 1..NUM_CORNERS {
+                if third == second {
+                    continue;
+                }
+                let pieces = [first, second, third];
+                for flips in [[false, false, false], [true, true, false], [true, false, true], [false, true, true]] {
+                    let goal = get_cycle_state(PieceType::Corner, pieces, flips).expect("the positions are different");
+                    if let Some(sequence) = finder.find(&goal) {
+                        cycles.push((pieces, flips, sequence));
+                    }
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// The cycles the pieces of one type move around in, each as the positions a piece goes through, starting from
+/// its smallest position. Pieces that stay where they are aren't listed, and corner flips are left out.
+pub fn get_piece_cycles(state: &RawState, piece_type: PieceType) -> Vec<Vec<usize>> {
+    let positions = match piece_type {
+        PieceType::Corner => &state.corners,
+        PieceType::Edge => &state.edges,
+        PieceType::UpCentre => &state.up_centres,
+        PieceType::DownCentre => &state.down_centres,
+    };
+    let mut visited = vec![false; positions.len()];
+    let mut cycles = Vec::new();
+    for start in 0..positions.len() {
+        let mut cycle = Vec::new();
+        let mut position = start;
+        while !visited[position] {
+            visited[position] = true;
+            cycle.push(position);
+            // The piece in this position moves to wherever its number now is
+            position = positions.iter().position(|piece| *piece as usize == position).unwrap();
+        }
+        if cycle.len() > 1 {
+            cycles.push(cycle);
+        }
+    }
+    cycles
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movetables::get_test_move_tables;
+
+    fn get_state_after(sequence: &[Turn]) -> RawState {
+        let mut state = RawState::solved();
+        for turn in sequence {
+            state.apply(turn);
+        }
+        state
+    }
+
+    #[test]
+    fn test_get_piece_cycles() {
+        let state = get_state_after(&Turn::parse_sequence("F R F' D F R F' D F R F' D").unwrap());
+        assert_eq!(get_piece_cycles(&state, PieceType::Corner), [vec![1, 3, 2, 5, 4]]);
+        assert!(get_piece_cycles(&state, PieceType::Edge).is_empty());
+        assert!(get_piece_cycles(&RawState::solved(), PieceType::Corner).is_empty());
+    }
+
+    #[test]
+    fn test_get_cycle_state() {
+        let state = get_cycle_state(PieceType::Corner, [0, 2, 4], [true, false, true]).unwrap();
+        assert_eq!(get_piece_cycles(&state, PieceType::Corner), [vec![0, 2, 4]]);
+        assert_eq!(state.corner_orientation, 0b100010);
+        assert!(state.validate().is_ok());
+
+        assert!(get_cycle_state(PieceType::Corner, [0, 0, 4], [false; 3]).is_none());
+        assert!(get_cycle_state(PieceType::Edge, [0, 1, 12], [false; 3]).is_none());
+        assert!(get_cycle_state(PieceType::Edge, [0, 1, 2], [true, true, false]).is_none());
+    }
+
+    #[test]
+    fn test_find_known_corner_cycle() {
+        // Cycles three corners without flipping them and leaves every other piece where it was
+        let known = Turn::parse_sequence("L' BL L' R' L BL' L' R L'").unwrap();
+        let known_state = get_state_after(&known);
+        assert_eq!(get_piece_cycles(&known_state, PieceType::Corner), [vec![0, 1, 2]]);
+        assert_eq!(get_cycle_state(PieceType::Corner, [0, 1, 2], [false; 3]), Some(known_state.clone()));
+
+        let move_tables = get_test_move_tables();
+        let found = find_pure_cycle(move_tables, PieceType::Corner, [0, 1, 2], [false; 3], known.len() as u8).unwrap();
+        assert!(found.len() <= known.len());
+        assert_eq!(get_state_after(&found), known_state);
+        assert_eq!(find_pure_cycle(move_tables, PieceType::Corner, [0, 1, 2], [false; 3], 8), None);
+    }
+
+    #[test]
+    fn test_find_pure_cycle_out_of_reach() {
+        // Flipping one corner can't be done at all, and no 3-cycle takes just two turns
+        let move_tables = get_test_move_tables();
+        assert!(find_pure_cycle(move_tables, PieceType::Corner, [0, 1, 2], [true, false, false], 8).is_none());
+        assert!(find_pure_cycle(move_tables, PieceType::Edge, [0, 1, 2], [false; 3], 2).is_none());
+    }
+
+    #[test]
+    fn test_find_pure_corner_cycles() {
+        let cycles = find_pure_corner_cycles(get_test_move_tables(), 9);
+        assert!(!cycles.is_empty());
+        for (pieces, flips, sequence) in cycles {
+            assert!(sequence.len() <= 9);
+            assert_eq!(Some(get_state_after(&sequence)), get_cycle_state(PieceType::Corner, pieces, flips));
+        }
+    }
+}
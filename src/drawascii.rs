@@ -0,0 +1,135 @@
+/*
+Draws the puzzle as text, for when an image can't be opened, such as over ssh.
+
+The layout follows the net SVG template. Its stickers are equilateral triangles in horizontal rows, pointing
+alternately up and down, and neighbouring triangles in a row are half a side length apart. Each row of triangles
+becomes a line of text with one character per half side length, so every sticker gets its own character.
+*/
+
+use crate::drawstate::{ColourScheme, Layout, get_svg_template, get_view_box_size, get_sticker_centroids,
+    get_label_colour, parse_hex_colour};
+use crate::movedefs::get_state_sticker_faces;
+use crate::state::RawState;
+
+
+// The side length of a sticker in the net template
+const STICKER_SIZE: f32 = 10.0;
+
+// One character per face, in the same order as the face constants in drawstate (U, F, BL, BR, D, B, L, R).
+// BL and BR use the same letters as Face::to_byte.
+pub(crate) const FACE_CHARS: [char; 8] = ['U', 'F', 'P', 'S', 'D', 'B', 'L', 'R'];
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+
+/// Draws the state as plain letters, one per sticker.
+pub fn render_ascii(state: &RawState) -> String {
+    render(state, None)
+}
+
+/// Draws the state with each sticker's letter on a background of its colour, using 24-bit ANSI escape codes.
+pub fn render_ascii_coloured(state: &RawState, scheme: &ColourScheme) -> String {
+    render(state, Some(scheme))
+}
+
+fn render(state: &RawState, scheme: Option<&ColourScheme>) -> String {
+    let grid = get_sticker_grid(state);
+
+    let mut output = String::from("");
+    for row in grid.iter() {
+        let row_length = row.iter().rposition(|face| face.is_some()).map_or(0, |last| last + 1);
+        for face in row.iter().take(row_length) {
+            output += &match (face, scheme) {
+                (Some(face), Some(scheme)) => get_coloured_char(*face, scheme),
+                (Some(face), None) => FACE_CHARS[*face as usize].to_string(),
+                (None, _) => String::from(" "),
+            };
+        }
+        output += "\n";
+    }
+    output
+}
+
+/// Places the face shown by every sticker into rows and columns of characters.
+fn get_sticker_grid(state: &RawState) -> Vec<Vec<Option<u8>>> {
+    let template = get_svg_template(Layout::Net);
+    let (width, height) = get_view_box_size(template).expect("the net template should have a view box");
+    let centroids = get_sticker_centroids(template);
+
+    let column_width = STICKER_SIZE / 2.0;
+    let row_height = STICKER_SIZE * 3f32.sqrt() / 2.0;
+    let columns = (width / column_width).round() as usize;
+    let rows = (height / row_height).round() as usize;
+    let mut grid = vec![vec![None; columns]; rows];
+
+    for (name, face) in get_state_sticker_faces(state) {
+        let (x, y) = centroids[name];
+        // Triangle centroids sit on the half side length boundaries, so shift them into the middle of a column
+        let column = ((x - column_width / 2.0) / column_width).round() as usize;
+        let row = (y / row_height) as usize;
+        grid[row][column] = Some(face);
+    }
+
+    let first_column = (0..columns)
+        .find(|column| grid.iter().any(|row| row[*column].is_some()))
+        .unwrap_or(0);
+    grid.into_iter()
+        .map(|row| row[first_column..].to_vec())
+        .collect()
+}
+
+fn get_coloured_char(face: u8, scheme: &ColourScheme) -> String {
+    let fill = scheme.get_colour(face);
+    let [red, green, blue] = parse_hex_colour(fill).unwrap_or([0, 0, 0]);
+    let [text_red, text_green, text_blue] = parse_hex_colour(get_label_colour(fill)).unwrap_or([0, 0, 0]);
+    format!("\x1b[48;2;{};{};{}m\x1b[38;2;{};{};{}m{}{}",
+        red, green, blue, text_red, text_green, text_blue, FACE_CHARS[face as usize], ANSI_RESET)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movedefs::{Face, Turn};
+
+    const SOLVED_SNAPSHOT: &str = include_str!("snapshots/ascii_solved.txt");
+
+    #[test]
+    fn test_render_ascii_solved_matches_snapshot() {
+        assert_eq!(render_ascii(&RawState::solved()), SOLVED_SNAPSHOT);
+    }
+
+    #[test]
+    fn test_render_ascii_u_turn_changes_expected_positions() {
+        let solved = render_ascii(&RawState::solved());
+        let mut state = RawState::solved();
+        state.apply(&Turn::new(Face::U, false));
+        let turned = render_ascii(&state);
+
+        let changed: Vec<(usize, usize)> = solved.lines().zip(turned.lines()).enumerate()
+            .flat_map(|(row, (before, after))| {
+                before.chars().zip(after.chars()).enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(move |(column, _)| (row, column))
+            })
+            .collect();
+
+        // The row of five stickers next to U on each of L, B and R, plus the tip of each U corner on BL, BR and F
+        let expected = vec![
+            (2, 19),
+            (3, 3), (3, 4), (3, 18), (3, 19),
+            (4, 4), (4, 5), (4, 17), (4, 18),
+            (5, 5), (5, 6), (5, 16), (5, 17),
+            (6, 0), (6, 1), (6, 2), (6, 3), (6, 4),
+        ];
+        assert_eq!(changed, expected);
+    }
+
+    #[test]
+    fn test_render_ascii_coloured() {
+        let output = render_ascii_coloured(&RawState::solved(), &ColourScheme::default());
+        assert!(output.contains("\x1b[48;2;255;255;255m\x1b[38;2;0;0;0mU\x1b[0m"));
+        assert!(output.contains("\x1b[48;2;0;0;255m\x1b[38;2;255;255;255mB\x1b[0m"));
+        assert_eq!(output.lines().count(), SOLVED_SNAPSHOT.lines().count());
+    }
+}
@@ -0,0 +1,152 @@
+/*
+Renders the SVG images as PNGs, by drawing the same SVG with resvg.
+
+No fonts are loaded, so text such as the letter overlay and captions is left out and only the stickers and their
+outlines are drawn.
+*/
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::io;
+use resvg::{tiny_skia, usvg};
+use crate::drawstate::{RenderOptions, get_svg_for_state_with_options};
+use crate::state::RawState;
+
+
+const MAX_SIZE_PX: u32 = 8192;
+
+
+#[derive(Debug)]
+pub enum RenderError {
+    InvalidSize(u32),
+    InvalidSvg(String),
+    Encoding(String),
+    Io { path: String, source: io::Error },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize(size) => write!(f, "image size must be between 1 and {} pixels, got {}", MAX_SIZE_PX, size),
+            Self::InvalidSvg(message) => write!(f, "could not read the SVG image: {}", message),
+            Self::Encoding(message) => write!(f, "could not encode the PNG image: {}", message),
+            Self::Io { path, source } => write!(f, "could not write '{}': {}", path, source),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+
+pub fn render_png(state: &RawState, size_px: u32) -> Result<Vec<u8>, RenderError> {
+    render_png_with_options(state, size_px, &RenderOptions::default())
+}
+
+/// Renders the state as a PNG that is `size_px` pixels wide, with the height following the shape of the
+/// template. Anywhere not covered by a sticker is left transparent.
+pub fn render_png_with_options(state: &RawState, size_px: u32, options: &RenderOptions) -> Result<Vec<u8>, RenderError> {
+    if size_px == 0 || size_px > MAX_SIZE_PX {
+        return Err(RenderError::InvalidSize(size_px));
+    }
+
+    let svg = get_svg_for_state_with_options(state, options);
+    let tree = usvg::Tree::from_str(&svg, &usvg::Options::default())
+        .map_err(|error| RenderError::InvalidSvg(error.to_string()))?;
+
+    let scale = size_px as f32 / tree.size().width();
+    let height = ((tree.size().height() * scale).round() as u32).max(1);
+    let mut pixmap = tiny_skia::Pixmap::new(size_px, height).ok_or(RenderError::InvalidSize(size_px))?;
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+    pixmap.encode_png().map_err(|error| RenderError::Encoding(error.to_string()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_png(filename: &str, state: &RawState, size_px: u32) -> Result<(), RenderError> {
+    let png_data = render_png(state, size_px)?;
+    fs::write(filename, png_data).map_err(|source| RenderError::Io {
+        path: filename.to_string(),
+        source,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+    use crate::drawstate::{ColourScheme, Layout, get_svg_template, get_sticker_polygons, get_view_box_size};
+
+    struct DecodedPng {
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+    }
+
+    impl DecodedPng {
+        fn get_pixel(&self, x: usize, y: usize) -> [u8; 4] {
+            let start = (y * self.width + x) * 4;
+            [self.pixels[start], self.pixels[start + 1], self.pixels[start + 2], self.pixels[start + 3]]
+        }
+    }
+
+    fn decode(png_data: &[u8]) -> DecodedPng {
+        let mut reader = png::Decoder::new(png_data).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        assert_eq!(info.bit_depth, png::BitDepth::Eight);
+        pixels.truncate(info.buffer_size());
+        DecodedPng { width: info.width as usize, height: info.height as usize, pixels }
+    }
+
+    fn get_sticker_pixel(image: &DecodedPng, name: &str) -> [u8; 4] {
+        let template = get_svg_template(Layout::Default);
+        let (view_width, _) = get_view_box_size(template).unwrap();
+        let points = &get_sticker_polygons(template)[name];
+        let x = points.iter().map(|p| p.0).sum::<f32>() / points.len() as f32;
+        let y = points.iter().map(|p| p.1).sum::<f32>() / points.len() as f32;
+        let scale = image.width as f32 / view_width;
+        image.get_pixel((x * scale) as usize, (y * scale) as usize)
+    }
+
+    #[test_case(240, 240, 120)]
+    #[test_case(101, 101, 51)]
+    #[test_case(1, 1, 1)]
+    fn test_png_dimensions(size_px: u32, width: usize, height: usize) {
+        let image = decode(&render_png(&RawState::solved(), size_px).unwrap());
+        assert_eq!(image.width, width);
+        assert_eq!(image.height, height);
+        assert_eq!(image.pixels.len(), width * height * 4);
+    }
+
+    #[test]
+    fn test_png_u_face_is_white() {
+        let image = decode(&render_png(&RawState::solved(), 240).unwrap());
+        assert_eq!(get_sticker_pixel(&image, "cent-UF"), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_png_uses_colour_scheme() {
+        let options = RenderOptions {
+            colour_scheme: ColourScheme::new(["#123456", "#f00", "#f80", "#888", "#ff0", "#00f", "#808", "#080"]),
+            ..Default::default()
+        };
+        let image = decode(&render_png_with_options(&RawState::solved(), 240, &options).unwrap());
+        assert_eq!(get_sticker_pixel(&image, "cent-UF"), [0x12, 0x34, 0x56, 255]);
+    }
+
+    #[test_case(0)]
+    #[test_case(MAX_SIZE_PX + 1)]
+    fn test_png_invalid_size(size_px: u32) {
+        assert!(matches!(render_png(&RawState::solved(), size_px), Err(RenderError::InvalidSize(_))));
+    }
+}
@@ -0,0 +1,263 @@
+/*
+A compact text description of an image, so that a whole picture fits in the query string of a URL, the way
+visualcube does for cubes. A small web service can then draw a puzzle for a forum post from nothing but its link.
+
+    fd=UUUUUUPS…&scheme=deuteranopia&layout=net&hl=c0,e3,u5
+
+Every parameter can be left out:
+- `fd`: the colour of each of the 72 stickers, as the letters of `drawascii` (U, F, P for BL, S for BR, D, B, L
+  and R), in the sticker order of `get_facelets`. Left out, the puzzle is solved. The stickers are coloured as
+  given, so they don't have to make up a state that can be reached.
+- `scheme`: a preset colour scheme, or eight colours separated by commas in the order U, F, BL, BR, D, B, L, R.
+  Each colour is either a hex colour, written without the `#` which would otherwise need escaping, or a CSS colour
+  name such as `red`.
+- `layout`: `default`, `net` or `top-view`.
+- `hl`: the pieces to highlight, separated by commas, each a letter for its type (c for corners, e for edges, u
+  for up centres and d for down centres) followed by its position.
+
+The other render options, such as the image size, are left to whatever serves the image.
+*/
+
+use std::fmt;
+
+use crate::drawascii::FACE_CHARS;
+use crate::drawstate::{ColourScheme, Highlight, Layout, PieceType, RenderOptions, get_facelets, get_svg_for_facelets,
+    parse_hex_colour};
+use crate::movedefs::NUM_FACELETS;
+use crate::state::RawState;
+
+
+const LAYOUT_NAMES: [(Layout, &str); 3] = [(Layout::Default, "default"), (Layout::Net, "net"), (Layout::TopView, "top-view")];
+const PIECE_LETTERS: [(PieceType, char); 4] = [
+    (PieceType::Corner, 'c'), (PieceType::Edge, 'e'), (PieceType::UpCentre, 'u'), (PieceType::DownCentre, 'd')];
+
+/// Why a query string couldn't be read. Facelet positions count from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnknownParameter(String),
+    WrongFaceletCount(usize),
+    BadFacelet { position: usize, letter: char },
+    BadValue { parameter: String, value: String },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownParameter(name) => write!(f, "unknown parameter '{}'", name),
+            Self::WrongFaceletCount(count) => write!(f, "expected {} facelets but got {}", NUM_FACELETS, count),
+            Self::BadFacelet { position, letter } => write!(f, "unrecognised facelet '{}' at position {}", letter, position),
+            Self::BadValue { parameter, value } => write!(f, "could not read '{}' for {}", value, parameter),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// The stickers to draw and how to draw them, as read from or written to a query string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderRequest {
+    /// The face constant of the colour each sticker shows, in the order of `get_facelets`.
+    pub facelets: [u8; NUM_FACELETS],
+    pub options: RenderOptions,
+}
+
+impl RenderRequest {
+    pub fn from_state(state: &RawState, options: RenderOptions) -> Self {
+        Self { facelets: get_facelets(state), options }
+    }
+
+    /// Reads a query string, with or without the leading `?`. When a parameter is given more than once, the last
+    /// one counts.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut request = Self::from_state(&RawState::solved(), RenderOptions::default());
+        let query = query.strip_prefix('?').unwrap_or(query);
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let bad_value = || QueryError::BadValue { parameter: name.to_string(), value: value.to_string() };
+            match name {
+                "fd" => request.facelets = parse_facelets(value)?,
+                "scheme" => request.options.colour_scheme = parse_scheme(value).ok_or_else(bad_value)?,
+                "layout" => request.options.layout = LAYOUT_NAMES.iter()
+                    .find_map(|(layout, layout_name)| (*layout_name == value).then_some(*layout))
+                    .ok_or_else(bad_value)?,
+                "hl" => request.options.highlight = Some(parse_highlight(value).ok_or_else(bad_value)?),
+                _ => return Err(QueryError::UnknownParameter(name.to_string())),
+            }
+        }
+        Ok(request)
+    }
+
+    /// Writes the request as a query string, without the leading `?`, leaving out parameters that are at their
+    /// defaults apart from the facelets. Only the parts of the options described at the top of this file are kept.
+    pub fn to_query_string(&self) -> String {
+        let facelets: String = self.facelets.iter().map(|face| FACE_CHARS[*face as usize]).collect();
+        let mut query = format!("fd={}", facelets);
+        let options = &self.options;
+        if options.colour_scheme != ColourScheme::default() {
+            query += &format!("&scheme={}", get_scheme_value(&options.colour_scheme));
+        }
+        if options.layout != Layout::Default {
+            let (_, name) = LAYOUT_NAMES.iter().find(|(layout, _)| *layout == options.layout).expect("every layout has a name");
+            query += &format!("&layout={}", name);
+        }
+        if let Some(highlight) = &options.highlight {
+            query += &format!("&hl={}", get_highlight_value(highlight));
+        }
+        query
+    }
+
+    pub fn render(&self) -> String {
+        get_svg_for_facelets(&self.facelets, &self.options)
+    }
+}
+
+fn parse_facelets(value: &str) -> Result<[u8; NUM_FACELETS], QueryError> {
+    let count = value.chars().count();
+    if count != NUM_FACELETS {
+        return Err(QueryError::WrongFaceletCount(count));
+    }
+    let mut facelets = [0; NUM_FACELETS];
+    for (position, letter) in value.chars().enumerate() {
+        let face = FACE_CHARS.iter().position(|face_char| *face_char == letter)
+            .ok_or(QueryError::BadFacelet { position, letter })?;
+        facelets[position] = face as u8;
+    }
+    Ok(facelets)
+}
+
+fn parse_scheme(value: &str) -> Option<ColourScheme> {
+    let colours: Vec<String> = value.split(',').map(parse_colour).collect::<Option<_>>()?;
+    match <[&str; 8]>::try_from(colours.iter().map(String::as_str).collect::<Vec<_>>().as_slice()) {
+        Ok(colours) => Some(ColourScheme::new(colours)),
+        Err(_) if colours.len() == 1 => ColourScheme::preset(value).ok(),
+        Err(_) => None,
+    }
+}
+
+/// A hex colour without its `#`, or a CSS colour name. Anything else is rejected, as the colours end up in the
+/// style sheet of the image.
+fn parse_colour(colour: &str) -> Option<String> {
+    if colour.chars().all(|c| c.is_ascii_hexdigit()) && parse_hex_colour(colour).is_some() {
+        Some(format!("#{}", colour))
+    } else if !colour.is_empty() && colour.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(colour.to_string())
+    } else {
+        None
+    }
+}
+
+fn get_scheme_value(scheme: &ColourScheme) -> String {
+    let preset = ColourScheme::get_preset_names().into_iter()
+        .find(|name| ColourScheme::preset(name).is_ok_and(|preset| preset == *scheme));
+    match preset {
+        Some(name) => name.to_string(),
+        None => (0..8).map(|face| scheme.get_colour(face).trim_start_matches('#')).collect::<Vec<_>>().join(","),
+    }
+}
+
+fn parse_highlight(value: &str) -> Option<Highlight> {
+    let mut highlight = Highlight::default();
+    for piece in value.split(',').filter(|piece| !piece.is_empty()) {
+        let mut chars = piece.chars();
+        let letter = chars.next()?;
+        let (piece_type, _) = PIECE_LETTERS.iter().find(|(_, piece_letter)| *piece_letter == letter)?;
+        let position: usize = chars.as_str().parse().ok()?;
+        if position >= piece_type.get_num_pieces() {
+            return None;
+        }
+        highlight.add(*piece_type, position);
+    }
+    Some(highlight)
+}
+
+fn get_highlight_value(highlight: &Highlight) -> String {
+    let mut pieces: Vec<(usize, char, usize)> = highlight.get_pieces().into_iter()
+        .map(|(piece_type, position)| {
+            let index = PIECE_LETTERS.iter().position(|(letter_type, _)| *letter_type == piece_type)
+                .expect("every piece type has a letter");
+            (index, PIECE_LETTERS[index].1, position)
+        })
+        .collect();
+    pieces.sort();
+    pieces.iter().map(|(_, letter, position)| format!("{}{}", letter, position)).collect::<Vec<_>>().join(",")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawstate::get_svg_for_state_with_options;
+    use crate::movedefs::Turn;
+
+    fn get_scrambled() -> RawState {
+        let mut state = RawState::solved();
+        for turn in Turn::parse_sequence("R U BL' D").unwrap().iter() {
+            state.apply(turn);
+        }
+        state
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let options = RenderOptions {
+            colour_scheme: ColourScheme::new(["#fff", "#f00", "#f80", "#888", "#ff0", "#00f", "#808", "#00aa00"]),
+            layout: Layout::Net,
+            highlight: Some(Highlight::new(&[(PieceType::Corner, 5), (PieceType::Edge, 0), (PieceType::DownCentre, 11)])),
+            ..Default::default()
+        };
+        let request = RenderRequest::from_state(&get_scrambled(), options);
+        let query = request.to_query_string();
+        assert!(query.ends_with("&scheme=fff,f00,f80,888,ff0,00f,808,00aa00&layout=net&hl=c5,e0,d11"));
+        assert_eq!(RenderRequest::parse(&query), Ok(request.clone()));
+        assert_eq!(RenderRequest::parse(&format!("?{}", query)), Ok(request));
+
+        let preset = RenderRequest::from_state(&RawState::solved(),
+            RenderOptions { colour_scheme: ColourScheme::preset("protanopia").unwrap(), ..Default::default() });
+        assert!(preset.to_query_string().ends_with("&scheme=protanopia"));
+        assert_eq!(RenderRequest::parse(&preset.to_query_string()), Ok(preset));
+    }
+
+    #[test]
+    fn test_defaults() {
+        let solved = RenderRequest::from_state(&RawState::solved(), RenderOptions::default());
+        assert_eq!(RenderRequest::parse(""), Ok(solved.clone()));
+        assert_eq!(solved.to_query_string().len(), "fd=".len() + NUM_FACELETS);
+        assert!(solved.to_query_string().starts_with("fd=UUUPSF"));
+    }
+
+    #[test]
+    fn test_rejects_bad_facelets() {
+        let facelets: String = get_facelets(&RawState::solved()).iter().map(|face| FACE_CHARS[*face as usize]).collect();
+        let mut bad = facelets.clone();
+        bad.replace_range(40..41, "X");
+        assert_eq!(RenderRequest::parse(&format!("fd={}", bad)),
+            Err(QueryError::BadFacelet { position: 40, letter: 'X' }));
+        assert_eq!(RenderRequest::parse(&format!("fd={}", bad)).unwrap_err().to_string(),
+            "unrecognised facelet 'X' at position 40");
+        assert_eq!(RenderRequest::parse(&format!("fd={}", &facelets[1..])), Err(QueryError::WrongFaceletCount(71)));
+        assert_eq!(RenderRequest::parse(&format!("fd={}U", facelets)), Err(QueryError::WrongFaceletCount(73)));
+    }
+
+    #[test]
+    fn test_rejects_bad_options() {
+        assert_eq!(RenderRequest::parse("size=3"), Err(QueryError::UnknownParameter("size".to_string())));
+        for query in ["layout=sideways", "scheme=pastel", "scheme=fff,000", "scheme=fff,f00,f80,888,ff0,00f,808,0a", "hl=c6",
+                "hl=x1", "hl=e"] {
+            assert!(matches!(RenderRequest::parse(query), Err(QueryError::BadValue { .. })), "{}", query);
+        }
+        assert!(RenderRequest::parse("scheme=white,red,orange,grey,yellow,blue,purple,green").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_markup_in_scheme() {
+        let query = "scheme=x}</style><script>alert(1)</script><style>.a{fill:red,b,c,d,e,f,g,h";
+        assert!(matches!(RenderRequest::parse(query), Err(QueryError::BadValue { .. })));
+    }
+
+    #[test]
+    fn test_render_matches_state() {
+        let options = RenderOptions { layout: Layout::Net, letter_overlay: true, ..Default::default() };
+        let request = RenderRequest::from_state(&get_scrambled(), options.clone());
+        assert_eq!(request.render(), get_svg_for_state_with_options(&get_scrambled(), &options));
+    }
+}
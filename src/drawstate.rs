@@ -6,12 +6,34 @@ Up Centres:     UBL    UBR    UF    BLU    BLF    BLBR    BRU    BRBL    BRF
 Down Centres: BR    BL    BD    RL    RB    RD    LB    LR    LD    DL    DR    DB
 */
 
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs;
-use crate::state::{RawState, apply_raw_permutation, flip_num_to_bool_array};
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::BufWriter;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use lazy_static::lazy_static;
+use crate::movedefs::{Turn, NUM_CENTRES, NUM_FACELETS, StickerState, FACE_LETTERS,
+    get_sticker_arrays, get_sticker_name_arrays, get_sticker_faces, get_state_sticker_faces};
+pub use crate::movedefs::{PieceType, get_sticker_names, get_piece_for_sticker, get_facelets};
+use crate::state::{RawState, CoordState, StateError};
 
 
-const SVG_TEMPLATE_FILE: &str = "./assets/fto.svg";
+const DEFAULT_TEMPLATE: &str = include_str!("../assets/fto.svg");
+const NET_TEMPLATE: &str = include_str!("../assets/fto-net.svg");
+const TOP_VIEW_TEMPLATE: &str = include_str!("../assets/fto-top.svg");
 
+/// The default colour of each face, indexed by the face constants below: U=0 white, F=1 red, BL=2 orange,
+/// BR=3 grey, D=4 yellow, B=5 blue, L=6 purple and R=7 green. This is not the same order as `Face::to_index`, which
+/// puts L, R and B before D.
+///
+/// Colours are CSS hex strings, either short ("#fff") or long ("#ffffff"). The SVG renderers copy them straight
+/// into the style rules, but the PNG and coloured text renderers need to read the channels, so other CSS colour
+/// forms only work in SVGs. To draw with different colours, pass a `ColourScheme` in the render options, either
+/// made with `ColourScheme::new` from eight colours in this order, or one of the presets listed in `PRESETS`.
 const COLOURS: &[&str] = &[
     "#fff",
     "#f00",
@@ -23,247 +45,909 @@ const COLOURS: &[&str] = &[
     "#080",
 ];
 
-const U: u8 = 0;
-const F: u8 = 1;
-const BL: u8 = 2;
-const BR: u8 = 3;
-const D: u8 = 4;
-const B: u8 = 5;
-const L: u8 = 6;
-const R: u8 = 7;
-
-const CORNER_NAMES_UP_GOOD: &[&str] = &[
-    "corn-UBL-U",
-    "corn-UBR-U",
-    "corn-UF-U",
-    "corn-DB-BL",
-    "corn-DR-BR",
-    "corn-DL-F",
-];
-const CORNER_NAMES_UP_FLIPPED: &[&str] = &[
-    "corn-UBL-BL",
-    "corn-UBR-BR",
-    "corn-UF-F",
-    "corn-DB-BR",
-    "corn-DR-F",
-    "corn-DL-BL",
-];
-const CORNER_NAMES_DOWN_GOOD: &[&str] = &[
-    "corn-UBL-L",
-    "corn-UBR-B",
-    "corn-UF-R",
-    "corn-DB-D",
-    "corn-DR-D",
-    "corn-DL-D",
-];
-const CORNER_NAMES_DOWN_FLIPPED: &[&str] = &[
-    "corn-UBL-B",
-    "corn-UBR-R",
-    "corn-UF-L",
-    "corn-DB-B",
-    "corn-DR-R",
-    "corn-DL-L",
-];
-const EDGE_UP_NAMES: &[&str] = &[
-    "edge-UB-U",
-    "edge-UR-U",
-    "edge-UL-U",
-    "edge-BLB-BL",
-    "edge-BLL-BL",
-    "edge-BLD-BL",
-    "edge-BRR-BR",
-    "edge-BRB-BR",
-    "edge-BRD-BR",
-    "edge-FL-F",
-    "edge-FR-F",
-    "edge-FD-F",
+// Presets based on the Okabe-Ito palette. White, yellow, blue and grey are kept where they are, and the
+// red/orange/purple/green faces are replaced by colours that stay distinct under the named deficiency.
+const DEUTERANOPIA_COLOURS: &[&str] = &[
+    "#fff",
+    "#d55e00",
+    "#56b4e9",
+    "#888",
+    "#f0e442",
+    "#0072b2",
+    "#000",
+    "#cc79a7",
 ];
-const EDGE_DOWN_NAMES: &[&str] = &[
-    "edge-UB-B",
-    "edge-UR-R",
-    "edge-UL-L",
-    "edge-BLB-B",
-    "edge-BLL-L",
-    "edge-BLD-D",
-    "edge-BRR-R",
-    "edge-BRB-B",
-    "edge-BRD-D",
-    "edge-FL-L",
-    "edge-FR-R",
-    "edge-FD-D",
+
+const PROTANOPIA_COLOURS: &[&str] = &[
+    "#fff",
+    "#000",
+    "#56b4e9",
+    "#888",
+    "#f0e442",
+    "#0072b2",
+    "#cc79a7",
+    "#e69f00",
 ];
-const UP_CENTRE_NAMES: &[&str] = &[
-    "cent-UBL",
-    "cent-UBR",
-    "cent-UF",
-    "cent-BLU",
-    "cent-BLF",
-    "cent-BLBR",
-    "cent-BRU",
-    "cent-BRBL",
-    "cent-BRF",
-    "cent-FU",
-    "cent-FBR",
-    "cent-FBL",
+
+const PRESETS: &[(&str, &[&str])] = &[
+    ("default", COLOURS),
+    ("deuteranopia", DEUTERANOPIA_COLOURS),
+    ("protanopia", PROTANOPIA_COLOURS),
 ];
-const DOWN_CENTRE_NAMES: &[&str] = &[
-    "cent-BR",
-    "cent-BL",
-    "cent-BD",
-    "cent-RL",
-    "cent-RB",
-    "cent-RD",
-    "cent-LB",
-    "cent-LR",
-    "cent-LD",
-    "cent-DL",
-    "cent-DR",
-    "cent-DB",
+
+// The stickers shown by the top view: the U face, the row of each of B, R and L next to it, and the corner
+// stickers on BL, BR and F that touch U at a vertex. These are exactly the stickers a U turn moves.
+const TOP_VIEW_NAMES: &[&str] = &[
+    "corn-UBL-U", "corn-UBR-U", "corn-UF-U", "edge-UB-U", "edge-UL-U", "edge-UR-U", "cent-UBL", "cent-UBR", "cent-UF",
+    "corn-UBL-B", "edge-UB-B", "corn-UBR-B", "cent-BL", "cent-BR",
+    "corn-UBR-R", "edge-UR-R", "corn-UF-R", "cent-RB", "cent-RL",
+    "corn-UF-L", "edge-UL-L", "corn-UBL-L", "cent-LR", "cent-LB",
+    "corn-UBL-BL", "corn-UBR-BR", "corn-UF-F",
 ];
 
+lazy_static! {
+    static ref STICKER_CENTROIDS: HashMap<String, (f32, f32)> = get_sticker_centroids(DEFAULT_TEMPLATE);
+    static ref NET_STICKER_CENTROIDS: HashMap<String, (f32, f32)> = get_sticker_centroids(NET_TEMPLATE);
+    static ref HALF_TURN_STICKERS: HashMap<&'static str, &'static str> = get_half_turn_sticker_map();
+}
+
 const STYLE_PLACEHOLDER: &str = "<!--*style placeholder-->";
+const CAPTION_PLACEHOLDER: &str = "<!--*caption placeholder-->";
+const SVG_END_TAG: &str = "</svg>";
+
+const VIEW_PREFIXES: [&str; 2] = ["v1-", "v2-"];
+const VIEW_GAP: f32 = 10.0;
+
+const LABEL_STYLE: &str = ".label{font:3px sans-serif;text-anchor:middle;dominant-baseline:central} ";
+const CAPTION_STYLE: &str = ".caption{font:bold 6px sans-serif;text-anchor:middle;fill:#000;stroke:#fff;stroke-width:.8;paint-order:stroke} ";
+
+const ARROW_STYLE: &str = ".arrow-corner{stroke:#000;stroke-width:.8} .arrow-edge{stroke:#000;stroke-width:.6;stroke-dasharray:2 1} .arrow-centre{stroke:#000;stroke-width:.4;stroke-dasharray:.6 .6} ";
+const ARROW_HEAD: &str = "<marker id=\"arrowhead\" viewBox=\"0 0 4 4\" refX=\"4\" refY=\"2\" markerWidth=\"4\" markerHeight=\"4\" orient=\"auto\"><path d=\"M0 0L4 2L0 4z\" fill=\"#000\"/></marker>";
+// How much of the distance between two centroids is cut from each end of an arrow, so the arrows don't meet
+const ARROW_INSET: f32 = 0.15;
+
+
+#[derive(Debug)]
+pub enum DrawError {
+    UnknownPreset(String),
+    Io { path: String, source: io::Error },
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPreset(name) => write!(f, "unknown colour scheme preset '{}'", name),
+            Self::Io { path, source } => write!(f, "could not write '{}': {}", path, source),
+        }
+    }
+}
+
+impl std::error::Error for DrawError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The parts of a template that were missing, listed by the class name of each missing sticker, or by the
+/// placeholder or attribute text for anything else.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "template is missing {}", self.missing.join(", "))
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// The fill colour used for each face, indexed in the same order as the face constants (U, F, BL, BR, D, B, L, R).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColourScheme {
+    colours: Vec<String>,
+}
+
+impl ColourScheme {
+    pub fn new(colours: [&str; 8]) -> Self {
+        Self::from_slice(&colours)
+    }
+
+    /// Looks up one of the built in schemes by name: "default", "deuteranopia" or "protanopia".
+    pub fn preset(name: &str) -> Result<Self, DrawError> {
+        match PRESETS.iter().find(|(preset_name, _)| *preset_name == name) {
+            Some((_, colours)) => Ok(Self::from_slice(colours)),
+            None => Err(DrawError::UnknownPreset(name.to_string())),
+        }
+    }
+
+    pub fn get_preset_names() -> Vec<&'static str> {
+        PRESETS.iter().map(|(name, _)| *name).collect()
+    }
+
+    pub fn get_colour(&self, face: u8) -> &str {
+        &self.colours[face as usize]
+    }
 
+    fn from_slice(colours: &[&str]) -> Self {
+        Self {
+            colours: colours.iter().map(|colour| colour.to_string()).collect(),
+        }
+    }
+}
 
-struct StickerState {
-    corner_up_good: [u8; 6],
-    corner_up_flipped: [u8; 6],
-    corner_down_good: [u8; 6],
-    corner_down_flipped: [u8; 6],
-    edge_up: [u8; 12],
-    edge_down: [u8; 12],
-    up_centres: [u8; 12],
-    down_centres: [u8; 12],
+impl Default for ColourScheme {
+    fn default() -> Self {
+        Self::from_slice(COLOURS)
+    }
 }
 
-impl StickerState {
-    pub fn get_initial() -> Self {
+/// Which arrangement of the faces to draw. All the templates use the same sticker class names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// The front and back of the puzzle side by side, each centred on a corner.
+    #[default]
+    Default,
+    /// All eight faces unfolded flat into a net.
+    Net,
+    /// Just the U face and the stickers around it that turn with it, for drills on the last layer.
+    TopView,
+}
+
+/// A set of pieces to draw in full colour, with every other sticker greyed out. Pieces are identified by their
+/// type and position, using the orderings documented in `state.rs`, so the highlight stays in the same place on
+/// the puzzle rather than following pieces as they move.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Highlight {
+    pieces: HashSet<(PieceType, usize)>,
+}
+
+impl Highlight {
+    pub fn new(pieces: &[(PieceType, usize)]) -> Self {
         Self {
-            corner_up_good: [U,U,U,BL,BR,F],
-            corner_up_flipped: [BL,BR,F,BR,F,BL],
-            corner_down_good: [L,B,R,D,D,D],
-            corner_down_flipped: [B,R,L,B,R,L],
-            edge_up: [U,U,U,BL,BL,BL,BR,BR,BR,F,F,F],
-            edge_down: [B,R,L,B,L,D,R,B,D,L,R,D],
-            up_centres: [U,U,U,BL,BL,BL,BR,BR,BR,F,F,F],
-            down_centres: [B,B,B,R,R,R,L,L,L,D,D,D],
+            pieces: pieces.iter().copied().collect(),
+        }
+    }
+
+    /// Every piece of the given types.
+    pub fn piece_types(piece_types: &[PieceType]) -> Self {
+        let mut highlight = Self::default();
+        for piece_type in piece_types {
+            for position in 0..piece_type.get_num_pieces() {
+                highlight.add(*piece_type, position);
+            }
         }
+        highlight
     }
 
-    pub fn create_from_raw_state(state: &RawState) -> Self {
-        let mut stickers = StickerState::get_initial();
+    /// The edges and up centres, which phase 1 groups onto their own up faces.
+    pub fn phase1_pieces() -> Self {
+        Self::piece_types(&[PieceType::Edge, PieceType::UpCentre])
+    }
 
-        apply_raw_permutation(&mut stickers.corner_up_good, &state.corners);
-        apply_raw_permutation(&mut stickers.corner_up_flipped, &state.corners);
-        apply_raw_permutation(&mut stickers.corner_down_good, &state.corners);
-        apply_raw_permutation(&mut stickers.corner_down_flipped, &state.corners);
-        apply_sticker_orientation(&mut stickers.corner_up_good, &mut stickers.corner_up_flipped, &state.corner_orientation);
-        apply_sticker_orientation(&mut stickers.corner_down_good, &mut stickers.corner_down_flipped, &state.corner_orientation);
+    /// The corners and down centres, which together make up the triples.
+    pub fn triples() -> Self {
+        Self::piece_types(&[PieceType::Corner, PieceType::DownCentre])
+    }
 
-        apply_raw_permutation(&mut stickers.edge_up, &state.edges);
-        apply_raw_permutation(&mut stickers.edge_down, &state.edges);
+    /// The three edges and three up centres that form a hex on up face `n` (U, BL, BR or F).
+    pub fn hex(n: usize) -> Self {
+        let mut highlight = Self::default();
+        for position in (3 * n)..(3 * n + 3) {
+            highlight.add(PieceType::Edge, position);
+            highlight.add(PieceType::UpCentre, position);
+        }
+        highlight
+    }
 
-        apply_raw_permutation(&mut stickers.up_centres, &state.up_centres);
-        apply_raw_permutation(&mut stickers.down_centres, &state.down_centres);
+    pub fn add(&mut self, piece_type: PieceType, position: usize) {
+        self.pieces.insert((piece_type, position));
+    }
+
+    pub fn contains(&self, piece_type: PieceType, position: usize) -> bool {
+        self.pieces.contains(&(piece_type, position))
+    }
 
-        stickers
+    /// Every highlighted piece, in no particular order.
+    pub fn get_pieces(&self) -> Vec<(PieceType, usize)> {
+        self.pieces.iter().copied().collect()
     }
+
+    fn is_sticker_highlighted(&self, name: &str) -> bool {
+        match get_piece_for_sticker(name) {
+            Some((piece_type, position)) => self.contains(piece_type, position),
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOptions {
+    pub colour_scheme: ColourScheme,
+    pub layout: Layout,
+    /// Stamps the letter of the face each sticker belongs to on top of the sticker, so that colours never
+    /// need to be told apart.
+    pub letter_overlay: bool,
+    /// When set, only the highlighted pieces are drawn in colour.
+    pub highlight: Option<Highlight>,
+    /// Text to show along the bottom edge of the image.
+    pub caption: Option<String>,
+    /// The size of the image when embedded in a document. When only one of the two is given, the other follows
+    /// from the aspect ratio of the image, and when neither is given the size is left up to the viewer.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Space to leave around the puzzle, in the template's units, where a sticker in the default layout is 20
+    /// units wide.
+    pub margin: u32,
+    /// Colour to fill the whole image with behind the puzzle. The image is transparent otherwise.
+    pub background: Option<String>,
 }
 
 
 pub fn get_svg_for_state(state: &RawState) -> String {
-    let template = get_svg_template();
-    let styles = get_style_section(&state);
-    template.replace(STYLE_PLACEHOLDER, &styles)
+    get_svg_for_state_with_options(state, &RenderOptions::default())
 }
 
-fn apply_sticker_orientation(good_stickers: &mut [u8], flipped_stickers: &mut [u8], effect: &u8) {
-    let flip = flip_num_to_bool_array(effect);
+/// Renders a coordinate state, returning an error rather than a nonsense picture if the coordinates do not
+/// describe a state that can exist.
+pub fn get_svg_for_coord_state(state: &CoordState) -> Result<String, StateError> {
+    let raw_state = state.try_to_raw()?;
+    Ok(get_svg_for_state(&raw_state))
+}
+
+pub fn get_svg_for_state_with_options(state: &RawState, options: &RenderOptions) -> String {
+    let mut svg = Vec::new();
+    write_svg_to(&mut svg, state, options).expect("writing to a Vec should not fail");
+    String::from_utf8(svg).expect("the template and generated styles should be valid UTF-8")
+}
 
-    for i in 0..flip.len() {
-        if flip[i] {
-            std::mem::swap(&mut good_stickers[i], &mut flipped_stickers[i]);
+/// Renders the state straight into `writer`, so the image can go to memory, a file or stdout without first
+/// being collected into one String.
+pub fn write_svg_to(writer: &mut impl Write, state: &RawState, options: &RenderOptions) -> io::Result<()> {
+    let stickers = StickerState::create_from_raw_state(state);
+    write_svg_with_template_to(writer, get_svg_template(options.layout), &stickers, options)
+}
+
+/// Renders stickers given one at a time rather than a state, such as from an image description that colours
+/// stickers freely. `facelets` holds the face constant of the colour each sticker shows, in the order of
+/// `get_facelets`.
+pub fn get_svg_for_facelets(facelets: &[u8; NUM_FACELETS], options: &RenderOptions) -> String {
+    let mut svg = Vec::new();
+    write_svg_with_template_to(&mut svg, get_svg_template(options.layout), &StickerState::from_facelets(facelets), options)
+        .expect("writing to a Vec should not fail");
+    String::from_utf8(svg).expect("the template and generated styles should be valid UTF-8")
+}
+
+/// Renders the state using a template other than the embedded ones. The template must be laid out like them:
+/// every sticker as a path with its class name, and the style placeholder where the colours should go. The
+/// layout in the options is ignored.
+pub fn get_svg_for_state_with_template(state: &RawState, template: &str, options: &RenderOptions) -> Result<String, TemplateError> {
+    validate_template(template)?;
+    let options = RenderOptions { layout: Layout::Default, ..options.clone() };
+    let mut svg = Vec::new();
+    let stickers = StickerState::create_from_raw_state(state);
+    write_svg_with_template_to(&mut svg, template, &stickers, &options).expect("writing to a Vec should not fail");
+    Ok(String::from_utf8(svg).expect("the template and generated styles should be valid UTF-8"))
+}
+
+/// Checks that the template has everything rendering needs, so that a misspelt class name shows up as an error
+/// rather than a sticker that never gets coloured. Every problem is reported, not just the first.
+pub fn validate_template(template: &str) -> Result<(), TemplateError> {
+    let mut missing: Vec<String> = get_sticker_name_arrays().concat().into_iter()
+        .filter(|name| !template.contains(&format!("class=\"{}\"", name)))
+        .map(String::from)
+        .collect();
+    if !template.contains(STYLE_PLACEHOLDER) {
+        missing.push(STYLE_PLACEHOLDER.to_string());
+    }
+    if get_view_box_size(template).is_none() {
+        missing.push(String::from("viewBox"));
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(TemplateError { missing })
+    }
+}
+
+fn write_svg_with_template_to(writer: &mut impl Write, template: &str, stickers: &StickerState, options: &RenderOptions) -> io::Result<()> {
+    let (width, height) = get_view_box_size(template).expect("templates should have a view box");
+    let template_root = &template[..template.find('>').expect("templates should start with an svg element") + 1];
+    let root = get_svg_root(width, height, options);
+    let styles = get_style_section(stickers, options);
+    let caption = get_caption(template, options.caption.as_deref());
+    let end = if options.letter_overlay {
+        get_sticker_labels(&get_sticker_faces(stickers), template, &options.colour_scheme, 0.0) + SVG_END_TAG
+    } else {
+        String::from(SVG_END_TAG)
+    };
+    write_template(writer, template, &[
+        (template_root, &root),
+        (STYLE_PLACEHOLDER, &styles),
+        (CAPTION_PLACEHOLDER, &caption),
+        (SVG_END_TAG, &end),
+    ])
+}
+
+/// Writes the template with each placeholder swapped for its content in a single pass. The placeholders must
+/// be given in the order they appear in the template, and any that are missing are skipped.
+fn write_template(writer: &mut impl Write, template: &str, substitutions: &[(&str, &str)]) -> io::Result<()> {
+    let mut rest = template;
+    for (placeholder, content) in substitutions {
+        if let Some((before, after)) = rest.split_once(placeholder) {
+            writer.write_all(before.as_bytes())?;
+            writer.write_all(content.as_bytes())?;
+            rest = after;
         }
     }
+    writer.write_all(rest.as_bytes())
 }
 
-fn get_svg_template() -> String {
-    fs::read_to_string(SVG_TEMPLATE_FILE)
-    .expect("should read the template file")
+pub fn render_sequence(start: &RawState, sequence: &[Turn]) -> Vec<String> {
+    render_sequence_with_options(start, sequence, &RenderOptions::default(), false)
 }
 
-fn get_style_section(state: &RawState) -> String {
+/// Renders one image of the starting state followed by one after each turn in the sequence. With captions
+/// turned on, every image after the first is captioned with the turn that was just applied.
+pub fn render_sequence_with_options(start: &RawState, sequence: &[Turn], options: &RenderOptions, captions: bool) -> Vec<String> {
+    let mut state = start.clone();
+    let mut frames = vec![get_svg_for_state_with_options(&state, options)];
+
+    for turn in sequence {
+        state.apply(turn);
+        let mut frame_options = options.clone();
+        if captions {
+            frame_options.caption = Some(turn.to_string());
+        }
+        frames.push(get_svg_for_state_with_options(&state, &frame_options));
+    }
+    frames
+}
+
+/// Writes the images from `render_sequence_with_options` to numbered files in `dir`, named with the prefix
+/// followed by the frame number, and returns the paths written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_sequence_svgs(dir: &str, prefix: &str, start: &RawState, sequence: &[Turn], options: &RenderOptions, captions: bool) -> Result<Vec<String>, DrawError> {
+    let frames = render_sequence_with_options(start, sequence, options, captions);
+    let digits = (frames.len() - 1).to_string().len();
+
+    let mut paths = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let path = Path::new(dir).join(format!("{}{:0width$}.svg", prefix, i, width = digits));
+        let path = path.to_string_lossy().to_string();
+        write_svg(&path, frame)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Renders the state with an arrow from every sticker that the turn moves to where the turn takes it. Corner,
+/// edge and centre arrows are drawn in different styles.
+pub fn render_turn_effect(state: &RawState, turn: &Turn) -> String {
+    let svg = get_svg_for_state(state);
+    svg.replace(SVG_END_TAG, &(get_turn_arrows(turn) + SVG_END_TAG))
+}
+
+/// Renders the puzzle twice side by side: as it is, and after a half turn of the whole puzzle about the axis
+/// through the UB and FD edges, which swaps the front and back of the default layout. The class names in each
+/// copy are given their own prefix so that the two sets of styles don't clash.
+pub fn get_two_view_svg_for_state(state: &RawState, options: &RenderOptions) -> String {
+    let template = get_svg_template(options.layout);
+    let (width, height) = get_view_box_size(template).expect("templates should have a view box");
+    let body = get_template_body(template);
+
+    let stickers = StickerState::create_from_raw_state(state);
+    let sticker_faces = get_sticker_faces(&stickers);
+    let rotated_faces = get_half_turn_sticker_faces(&sticker_faces);
+
+    let mut styles = String::from("<style>");
+    let mut groups = String::from("");
+    let mut labels = String::from("");
+
+    for (i, view_faces) in [sticker_faces, rotated_faces].iter().enumerate() {
+        let prefix = VIEW_PREFIXES[i];
+        let x_offset = i as f32 * (width + VIEW_GAP);
+
+        for (name, face) in view_faces {
+            let fill = get_sticker_fill(name, *face, &options.colour_scheme, options.highlight.as_ref());
+            styles += &get_style_for_sticker(&format!("{}{}", prefix, name), &fill);
+        }
+        groups += &format!("<g transform=\"translate({} 0)\">{}</g>",
+            x_offset, body.replace("class=\"", &format!("class=\"{}", prefix)));
+        if options.letter_overlay {
+            labels += &get_sticker_labels(view_faces, template, &options.colour_scheme, x_offset);
+        }
+    }
+    if options.letter_overlay {
+        styles += LABEL_STYLE;
+    }
+    styles += "</style>";
+
+    format!("{}{}{}{}{}", get_svg_root(2.0 * width + VIEW_GAP, height, options), styles, groups, labels, SVG_END_TAG)
+}
+
+/// Renders several states into one image, laid out in rows of `columns` puzzles, each with its own caption
+/// along its bottom edge if one is given. As in the two view image, every puzzle's class names get their own
+/// prefix. The caption in the options is not used, and a `columns` of 0 is taken as 1.
+///
+/// Gives `None` when there are no states, rather than an image with nothing in it and no size.
+pub fn render_grid(states: &[(RawState, Option<String>)], columns: usize, options: &RenderOptions) -> Option<String> {
+    if states.is_empty() {
+        return None;
+    }
+    let template = get_svg_template(options.layout);
+    let (width, height) = get_view_box_size(template).expect("templates should have a view box");
+    let body = get_template_body(template);
+
+    let columns = columns.clamp(1, states.len());
+    let rows = states.len().div_ceil(columns);
+
+    let mut styles = String::from("<style>");
+    let mut groups = String::from("");
+
+    for (i, (state, caption)) in states.iter().enumerate() {
+        let prefix = format!("g{}-", i);
+        let x_offset = (i % columns) as f32 * (width + VIEW_GAP);
+        let y_offset = (i / columns) as f32 * (height + VIEW_GAP);
+        let sticker_faces = get_state_sticker_faces(state);
+
+        for (name, face) in sticker_faces.iter() {
+            let fill = get_sticker_fill(name, *face, &options.colour_scheme, options.highlight.as_ref());
+            styles += &get_style_for_sticker(&format!("{}{}", prefix, name), &fill);
+        }
+        let mut contents = body.replace("class=\"", &format!("class=\"{}", prefix));
+        if options.letter_overlay {
+            contents += &get_sticker_labels(&sticker_faces, template, &options.colour_scheme, 0.0);
+        }
+        contents += &get_caption(template, caption.as_deref());
+        groups += &format!("<g transform=\"translate({} {})\">{}</g>", x_offset, y_offset, contents);
+    }
+    if options.letter_overlay {
+        styles += LABEL_STYLE;
+    }
+    if states.iter().any(|(_, caption)| caption.is_some()) {
+        styles += CAPTION_STYLE;
+    }
+    styles += "</style>";
+
+    let grid_width = get_grid_length(columns, width);
+    let grid_height = get_grid_length(rows, height);
+    Some(format!("{}{}{}{}", get_svg_root(grid_width, grid_height, options), styles, groups, SVG_END_TAG))
+}
+
+fn get_grid_length(count: usize, size: f32) -> f32 {
+    count as f32 * (size + VIEW_GAP) - VIEW_GAP
+}
+
+/// Creates the opening svg tag for a picture of the given size in template units, applying the size, margin and
+/// background from the options. The background is drawn first, so it goes straight after the tag.
+fn get_svg_root(width: f32, height: f32, options: &RenderOptions) -> String {
+    let margin = options.margin as f32;
+    let (view_width, view_height) = (width + 2.0 * margin, height + 2.0 * margin);
+    let view_box = format!("{} {} {} {}", 0.0 - margin, 0.0 - margin, view_width, view_height);
+
+    let size = match (options.width, options.height) {
+        (Some(width), Some(height)) => format!(" width=\"{}\" height=\"{}\"", width, height),
+        (Some(width), None) => {
+            let height = (width as f32 * view_height / view_width).round();
+            format!(" width=\"{}\" height=\"{}\"", width, height)
+        }
+        (None, Some(height)) => {
+            let width = (height as f32 * view_width / view_height).round();
+            format!(" width=\"{}\" height=\"{}\"", width, height)
+        }
+        (None, None) => String::from(""),
+    };
+    let background = match &options.background {
+        Some(colour) => format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            0.0 - margin, 0.0 - margin, view_width, view_height, escape_xml(colour)),
+        None => String::from(""),
+    };
+
+    format!("<svg version=\"1.1\" viewBox=\"{}\"{} xmlns=\"http://www.w3.org/2000/svg\">{}", view_box, size, background)
+}
+
+/// Returns the centre of every sticker in the net layout, keyed by the sticker's class name, in the units of the
+/// template's view box. This is for annotating a net drawing, such as marking both stickers of an edge, without
+/// having to read the template.
+pub fn get_net_layout_positions() -> HashMap<&'static str, (f32, f32)> {
+    NET_STICKER_CENTROIDS.iter().map(|(name, position)| (name.as_str(), *position)).collect()
+}
+
+pub(crate) fn get_svg_template(layout: Layout) -> &'static str {
+    match layout {
+        Layout::Default => DEFAULT_TEMPLATE,
+        Layout::Net => NET_TEMPLATE,
+        Layout::TopView => TOP_VIEW_TEMPLATE,
+    }
+}
+
+/// The class names of the stickers drawn by a layout, or `None` when it draws all of them.
+fn get_layout_sticker_names(layout: Layout) -> Option<&'static [&'static str]> {
+    match layout {
+        Layout::TopView => Some(TOP_VIEW_NAMES),
+        _ => None,
+    }
+}
+
+fn get_style_section(stickers: &StickerState, options: &RenderOptions) -> String {
     let header: String = String::from("<style>");
     let footer: String = String::from("</style>");
 
-    let stickers = StickerState::create_from_raw_state(&state);
-    let styles = get_sticker_styles(&stickers);
+    let mut styles = match get_layout_sticker_names(options.layout) {
+        Some(names) => get_sticker_faces(stickers).into_iter()
+            .filter(|(name, _)| names.contains(name))
+            .map(|(name, face)| {
+                let fill = get_sticker_fill(name, face, &options.colour_scheme, options.highlight.as_ref());
+                get_style_for_sticker(name, &fill)
+            })
+            .collect(),
+        None => get_sticker_styles(stickers, &options.colour_scheme, options.highlight.as_ref()),
+    };
+    if options.letter_overlay {
+        styles += LABEL_STYLE;
+    }
+    if options.caption.is_some() {
+        styles += CAPTION_STYLE;
+    }
 
     header + &styles + &footer
 }
 
-fn get_sticker_styles<'a>(stickers: &'a StickerState) -> String {
+fn get_sticker_styles(stickers: &StickerState, scheme: &ColourScheme, highlight: Option<&Highlight>) -> String {
     let mut styles: String = String::from("");
-    let sticker_arrays: Vec<&[u8]> = vec![
-        &stickers.corner_up_good, &stickers.corner_up_flipped, &stickers.corner_down_good, &stickers.corner_down_flipped,
-        &stickers.edge_up, &stickers.edge_down,
-        &stickers.up_centres, &stickers.down_centres];
-    let names = vec![
-        CORNER_NAMES_UP_GOOD, CORNER_NAMES_UP_FLIPPED, CORNER_NAMES_DOWN_GOOD, CORNER_NAMES_DOWN_FLIPPED,
-        EDGE_UP_NAMES, EDGE_DOWN_NAMES,
-        UP_CENTRE_NAMES, DOWN_CENTRE_NAMES];
+    let sticker_arrays = get_sticker_arrays(stickers);
+    let names = get_sticker_name_arrays();
 
     for i in 0..8 {
-        let new_style = get_style_for_sticker_set(sticker_arrays[i], names[i]);
+        let new_style = get_style_for_sticker_set(sticker_arrays[i], names[i], scheme, highlight);
         styles += &new_style;
     }
 
     styles
 }
 
-fn get_style_for_sticker_set<'a>(set: &'a [u8], names: &[&str]) -> String {
+fn get_style_for_sticker_set(set: &[u8], names: &[&str], scheme: &ColourScheme, highlight: Option<&Highlight>) -> String {
     let mut styles: String = String::from("");
     for i in 0..set.len() {
-        let colour = COLOURS[set[i] as usize];
-        let next_style: String = get_style_for_sticker(names[i], colour);
+        let colour = get_sticker_fill(names[i], set[i], scheme, highlight);
+        let next_style: String = get_style_for_sticker(names[i], &colour);
         styles += &next_style;
     }
     styles
 }
 
 fn get_style_for_sticker(name: &str, fill: &str) -> String {
-    format!(".{}{{fill:{}}} ", name, fill)
+    format!(".{}{{fill:{}}} ", name, escape_xml(fill))
+}
+
+fn get_sticker_fill(name: &str, face: u8, scheme: &ColourScheme, highlight: Option<&Highlight>) -> String {
+    let colour = scheme.get_colour(face);
+    match highlight {
+        Some(highlight) if !highlight.is_sticker_highlighted(name) => get_dimmed_colour(colour),
+        _ => colour.to_string(),
+    }
+}
+
+/// Washes a colour out to a grey, keeping a little of its brightness so that dimmed stickers recede without all
+/// looking identical.
+fn get_dimmed_colour(colour: &str) -> String {
+    match parse_hex_colour(colour) {
+        Some([red, green, blue]) => {
+            let luminance = 0.299 * red as f32 + 0.587 * green as f32 + 0.114 * blue as f32;
+            let grey = (0.25 * luminance + 135.0).round() as u8;
+            format!("#{:02x}{:02x}{:02x}", grey, grey, grey)
+        }
+        None => colour.to_string(),
+    }
+}
+
+/// Moves every sticker to where it ends up after the half turn used for the second of the two views.
+pub(crate) fn get_half_turn_sticker_faces(sticker_faces: &[(&'static str, u8)]) -> Vec<(&'static str, u8)> {
+    sticker_faces.iter()
+        .map(|(name, face)| (HALF_TURN_STICKERS[name], *face))
+        .collect()
+}
+
+/// In the default template the half turn carries each face in the front half onto the face in the same place in
+/// the back half, and vice versa, so each sticker swaps places with the one half the template's width away.
+fn get_half_turn_sticker_map() -> HashMap<&'static str, &'static str> {
+    let (width, _) = get_view_box_size(DEFAULT_TEMPLATE).expect("the default template should have a view box");
+    let centroids = &*STICKER_CENTROIDS;
+    let names: Vec<&'static str> = get_sticker_name_arrays().concat();
+
+    let mut map = HashMap::new();
+    for name in names.iter() {
+        let (x, y) = centroids[*name];
+        let target = ((x + width / 2.0) % width, y);
+        let rotated = names.iter()
+            .find(|other| {
+                let (other_x, other_y) = centroids[**other];
+                (other_x - target.0).abs() < 0.01 && (other_y - target.1).abs() < 0.01
+            })
+            .expect("every sticker should have a partner in the other half of the template");
+        map.insert(*name, *rotated);
+    }
+    map
+}
+
+/// Lists the stickers a turn moves, as pairs of where each one starts and where it finishes.
+fn get_turn_sticker_moves(turn: &Turn) -> Vec<(&'static str, &'static str)> {
+    // The solved state numbers centres by face, since centres on the same face can't be told apart, so number
+    // them individually to see which one goes where.
+    let mut state = RawState {
+        up_centres: (0..NUM_CENTRES as u8).collect(),
+        down_centres: (0..NUM_CENTRES as u8).collect(),
+        ..RawState::solved()
+    };
+    state.apply(turn);
+    let names: Vec<&'static str> = get_sticker_name_arrays().concat();
+
+    get_sticker_faces(&StickerState::get_numbered().moved_by(&state)).into_iter()
+        .map(|(destination, number)| (names[number as usize], destination))
+        .filter(|(origin, destination)| origin != destination)
+        .collect()
+}
+
+fn get_turn_arrows(turn: &Turn) -> String {
+    let mut arrows = String::from("");
+    for (origin, destination) in get_turn_sticker_moves(turn) {
+        let (x1, y1) = STICKER_CENTROIDS[origin];
+        let (x2, y2) = STICKER_CENTROIDS[destination];
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let class = match get_piece_for_sticker(origin) {
+            Some((PieceType::Corner, _)) => "arrow-corner",
+            Some((PieceType::Edge, _)) => "arrow-edge",
+            _ => "arrow-centre",
+        };
+        arrows += &format!("<path class=\"{}\" d=\"M{:.2} {:.2}L{:.2} {:.2}\" marker-end=\"url(#arrowhead)\"/>",
+            class, x1 + ARROW_INSET * dx, y1 + ARROW_INSET * dy, x2 - ARROW_INSET * dx, y2 - ARROW_INSET * dy);
+    }
+    format!("<g><style>{}</style><defs>{}</defs>{}</g>", ARROW_STYLE, ARROW_HEAD, arrows)
+}
+
+fn get_template_body(template: &str) -> &str {
+    let start = template.find("<g").expect("templates should group their stickers");
+    let end = template.rfind("</g>").expect("templates should group their stickers") + "</g>".len();
+    &template[start..end]
+}
+
+fn get_caption(template: &str, caption: Option<&str>) -> String {
+    match caption {
+        Some(text) => {
+            let (width, height) = get_view_box_size(template).expect("templates should have a view box");
+            format!("<text class=\"caption\" x=\"{}\" y=\"{}\">{}</text>", width / 2.0, height - 2.0, escape_xml(text))
+        }
+        None => String::from(""),
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn get_sticker_labels(sticker_faces: &[(&str, u8)], template: &str, scheme: &ColourScheme, x_offset: f32) -> String {
+    let centroids = get_sticker_centroids(template);
+    let mut labels = String::from("");
+
+    for (name, face) in sticker_faces {
+        if let Some((x, y)) = centroids.get(*name) {
+            let fill = get_label_colour(scheme.get_colour(*face));
+            labels += &format!("<text class=\"label\" x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                x + x_offset, y, fill, FACE_LETTERS[*face as usize]);
+        }
+    }
+    labels
+}
+
+/// Picks black or white text, whichever contrasts best with the given CSS hex colour.
+pub(crate) fn get_label_colour(fill: &str) -> &'static str {
+    let [red, green, blue] = match parse_hex_colour(fill) {
+        Some(channels) => channels.map(|channel| channel as f32),
+        None => return "#000",
+    };
+    let luminance = 0.299 * red + 0.587 * green + 0.114 * blue;
+    if luminance > 128.0 {
+        "#000"
+    } else {
+        "#fff"
+    }
+}
+
+/// Converts a CSS hex colour in either the short (#rgb) or long (#rrggbb) form to its red, green and blue channels.
+pub(crate) fn parse_hex_colour(colour: &str) -> Option<[u8; 3]> {
+    let hex = colour.trim_start_matches('#');
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => Some([
+            channel(&hex[0..1])? * 17,
+            channel(&hex[1..2])? * 17,
+            channel(&hex[2..3])? * 17,
+        ]),
+        6 => Some([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ]),
+        _ => None,
+    }
+}
+
+/// Finds the centre point of every sticker path in the template, keyed by the sticker's class name.
+pub(crate) fn get_sticker_centroids(template: &str) -> HashMap<String, (f32, f32)> {
+    get_sticker_polygons(template).into_iter()
+        .map(|(class, points)| {
+            let n = points.len() as f32;
+            let x = points.iter().map(|p| p.0).sum::<f32>() / n;
+            let y = points.iter().map(|p| p.1).sum::<f32>() / n;
+            (class, (x, y))
+        })
+        .collect()
+}
+
+/// Finds the outline of every sticker path in the template, keyed by the sticker's class name.
+pub(crate) fn get_sticker_polygons(template: &str) -> HashMap<String, Vec<(f32, f32)>> {
+    let mut polygons = HashMap::new();
+    for element in template.split('<').filter(|element| element.starts_with("path")) {
+        let class = get_attribute(element, "class");
+        let path = get_attribute(element, "d");
+        if let (Some(class), Some(path)) = (class, path) {
+            let points = parse_path_points(path);
+            if !points.is_empty() {
+                polygons.insert(class.to_string(), points);
+            }
+        }
+    }
+    polygons
 }
 
-pub fn write_svg(filename: &str, svg_data: &str) {
-    fs::write(filename, svg_data)
-    .expect("should write the SVG file.");
+/// Reads the width and height of the template's view box.
+pub(crate) fn get_view_box_size(template: &str) -> Option<(f32, f32)> {
+    let view_box = get_attribute(template, "viewBox")?;
+    let values: Vec<f32> = view_box.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+    match values[..] {
+        [_, _, width, height] => Some((width, height)),
+        _ => None,
+    }
+}
+
+fn get_attribute<'a>(element: &'a str, attribute: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", attribute);
+    let start = element.find(&key)? + key.len();
+    let end = start + element[start..].find('"')?;
+    Some(&element[start..end])
+}
+
+/// Returns the vertices of a polygon described by SVG path data. Only the straight line commands
+/// (M, L, H, V and Z, in absolute or relative form) are supported, which is all the templates use.
+fn parse_path_points(path: &str) -> Vec<(f32, f32)> {
+    let mut points = Vec::new();
+    let mut command = 'M';
+    let mut current: (f32, f32) = (0.0, 0.0);
+    let mut pending: Vec<f32> = Vec::new();
+
+    for token in tokenise_path(path) {
+        match token {
+            PathToken::Command(c) => {
+                command = c;
+                pending.clear();
+            }
+            PathToken::Number(value) => {
+                pending.push(value);
+                let relative = command.is_ascii_lowercase();
+                match command.to_ascii_uppercase() {
+                    'M' | 'L' if pending.len() == 2 => {
+                        let origin = if relative { current } else { (0.0, 0.0) };
+                        current = (origin.0 + pending[0], origin.1 + pending[1]);
+                        points.push(current);
+                        pending.clear();
+                        // Any coordinates following a move are treated as line commands
+                        command = if relative { 'l' } else { 'L' };
+                    }
+                    'H' => {
+                        let origin = if relative { current.0 } else { 0.0 };
+                        current = (origin + value, current.1);
+                        points.push(current);
+                        pending.clear();
+                    }
+                    'V' => {
+                        let origin = if relative { current.1 } else { 0.0 };
+                        current = (current.0, origin + value);
+                        points.push(current);
+                        pending.clear();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    points
+}
+
+enum PathToken {
+    Command(char),
+    Number(f32),
+}
+
+fn tokenise_path(path: &str) -> Vec<PathToken> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+
+    let flush = |number: &mut String, tokens: &mut Vec<PathToken>| {
+        if !number.is_empty() {
+            if let Ok(value) = number.parse::<f32>() {
+                tokens.push(PathToken::Number(value));
+            }
+            number.clear();
+        }
+    };
+
+    for c in path.chars() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+        } else if c == '-' {
+            flush(&mut number, &mut tokens);
+            number.push(c);
+        } else if c.is_ascii_alphabetic() {
+            flush(&mut number, &mut tokens);
+            tokens.push(PathToken::Command(c));
+        } else {
+            flush(&mut number, &mut tokens);
+        }
+    }
+    flush(&mut number, &mut tokens);
+    tokens
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_svg(filename: &str, svg_data: &str) -> Result<(), DrawError> {
+    fs::write(filename, svg_data).map_err(|source| DrawError::Io {
+        path: filename.to_string(),
+        source,
+    })
+}
+
+/// Renders the state into a file using `write_svg_to`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_state_svg(filename: &str, state: &RawState, options: &RenderOptions) -> Result<(), DrawError> {
+    let to_error = |source| DrawError::Io {
+        path: filename.to_string(),
+        source,
+    };
+    let mut writer = BufWriter::new(fs::File::create(filename).map_err(to_error)?);
+    write_svg_to(&mut writer, state, options).map_err(to_error)?;
+    writer.flush().map_err(to_error)
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::movedefs::BL;
+    use crate::movedefs::{Face, Turn};
+    use crate::state::SOLVED_COORD_STATE;
     use test_case::test_case;
 
     #[test_case(&"class", &"#fff", &".class{fill:#fff} ")]
+    #[test_case(&"class", &"red}</style>", &".class{fill:red}&lt;/style&gt;} ")]
     fn test_get_style_for_sticker(name: &str, fill: &str, expected: &str) {
         assert_eq!(get_style_for_sticker(name, fill), expected);
     }
 
-    #[test_case(&[0,2,5], &[&"piece-a", &"piece-b", &"piece-c"], &".piece-a{fill:#fff} .piece-b{fill:#f80} .piece-c{fill:#00f} ")]
+    #[test_case(&[0,2,5], &["piece-a", "piece-b", "piece-c"], &".piece-a{fill:#fff} .piece-b{fill:#f80} .piece-c{fill:#00f} ")]
     fn test_get_style_for_sticker_set(set: &[u8], names: &[&str], expected: &str) {
-        assert_eq!(get_style_for_sticker_set(set, names), expected);
-    }
-
-    #[test]
-    fn test_sticker_iniital_state() {
-        let stickers = StickerState::get_initial();
-        assert_eq!(stickers.corner_up_good[0], U);
+        assert_eq!(get_style_for_sticker_set(set, names, &ColourScheme::default(), None), expected);
     }
 
     #[test]
     fn test_get_style_section() {
-        let style: String = get_style_section(&RawState::solved());
+        let stickers = StickerState::create_from_raw_state(&RawState::solved());
+        let style: String = get_style_section(&stickers, &RenderOptions::default());
         let start = &style[0..7];
         assert_eq!(start, "<style>");
         let end = &style[style.len() - 8 ..];
@@ -273,22 +957,474 @@ mod tests {
     #[test]
     fn test_get_sticker_styles_does_not_error() {
         let stickers = StickerState::get_initial();
-        let styles = get_sticker_styles(&stickers);
-        assert!(styles.contains(&".corn-UBL-L{fill:#808}"));
-        assert!(styles.contains(&".edge-FR-R{fill:#080}"));
-        assert!(styles.contains(&".cent-UF{fill:#fff}"));
+        let styles = get_sticker_styles(&stickers, &ColourScheme::default(), None);
+        assert!(styles.contains(".corn-UBL-L{fill:#808}"));
+        assert!(styles.contains(".edge-FR-R{fill:#080}"));
+        assert!(styles.contains(".cent-UF{fill:#fff}"));
+    }
+
+    #[test_case(Layout::Default)]
+    #[test_case(Layout::Net)]
+    fn test_template_has_every_sticker(layout: Layout) {
+        let template = get_svg_template(layout);
+        assert!(template.contains(STYLE_PLACEHOLDER));
+
+        let polygons = get_sticker_polygons(template);
+        let names: Vec<&str> = get_sticker_name_arrays().concat();
+        assert_eq!(polygons.len(), names.len());
+        for name in names {
+            assert!(polygons.contains_key(name), "{} is missing from the {:?} template", name, layout);
+        }
+    }
+
+    #[test]
+    fn test_get_net_layout_positions() {
+        let positions = get_net_layout_positions();
+        let (width, height) = get_view_box_size(NET_TEMPLATE).unwrap();
+        let names: Vec<&str> = get_sticker_name_arrays().concat();
+        assert_eq!(positions.len(), names.len());
+        for name in names {
+            let (x, y) = positions[name];
+            assert!(x > 0.0 && x < width && y > 0.0 && y < height, "{} is at ({}, {})", name, x, y);
+        }
+        // The centre of the triangle with corners (10, 34.64), (15, 43.3) and (20, 34.64)
+        let (x, y) = positions["cent-UF"];
+        assert!((x - 15.0).abs() < 1e-4 && (y - 37.5267).abs() < 1e-3);
     }
 
     #[test]
-    fn test_get_template_does_not_error() {
-        let _ = get_svg_template();
+    fn test_get_svg_for_state_with_net_layout() {
+        let options = RenderOptions {
+            layout: Layout::Net,
+            ..Default::default()
+        };
+        let svg = get_svg_for_state_with_options(&RawState::solved(), &options);
+        assert!(svg.contains("viewBox=\"0 0 105 77.94\""));
+        assert!(svg.contains(".cent-UF{fill:#fff}"));
+        assert!(!svg.contains(STYLE_PLACEHOLDER));
     }
 
     #[test]
     fn test_get_svg_for_state_does_not_error() {
         let svg = get_svg_for_state(&RawState::solved());
-        assert!(svg.contains(&".corn-UBL-L{fill:#808}"));
-        assert!(svg.contains(&".edge-FR-R{fill:#080}"));
-        assert!(svg.contains(&".cent-UF{fill:#fff}"));
+        assert!(svg.contains(".corn-UBL-L{fill:#808}"));
+        assert!(svg.contains(".edge-FR-R{fill:#080}"));
+        assert!(svg.contains(".cent-UF{fill:#fff}"));
+    }
+
+    #[test_case("default", "#f80")]
+    #[test_case("deuteranopia", "#56b4e9")]
+    #[test_case("protanopia", "#56b4e9")]
+    fn test_colour_scheme_preset(name: &str, expected_bl_colour: &str) {
+        let scheme = ColourScheme::preset(name).unwrap();
+        assert_eq!(scheme.get_colour(BL), expected_bl_colour);
+    }
+
+    #[test]
+    fn test_unknown_colour_scheme_preset() {
+        let result = ColourScheme::preset("tritanopia");
+        assert!(matches!(result, Err(DrawError::UnknownPreset(name)) if name == "tritanopia"));
+    }
+
+    #[test]
+    fn test_presets_have_distinct_colours() {
+        for name in ColourScheme::get_preset_names() {
+            let scheme = ColourScheme::preset(name).unwrap();
+            for i in 0..8 {
+                for j in (i+1)..8 {
+                    assert_ne!(scheme.get_colour(i), scheme.get_colour(j));
+                }
+            }
+        }
+    }
+
+    #[test_case("m0 0 10 10 10-10z", &[(0.0, 0.0), (10.0, 10.0), (20.0, 0.0)])]
+    #[test_case("m40 0h20l-10 10z", &[(40.0, 0.0), (60.0, 0.0), (50.0, 10.0)])]
+    #[test_case("m10 50v-20l10 10z", &[(10.0, 50.0), (10.0, 30.0), (20.0, 40.0)])]
+    #[test_case("M1 2L3 4H5V6Z", &[(1.0, 2.0), (3.0, 4.0), (5.0, 4.0), (5.0, 6.0)])]
+    fn test_parse_path_points(path: &str, expected: &[(f32, f32)]) {
+        assert_eq!(parse_path_points(path), expected);
+    }
+
+    #[test]
+    fn test_letter_overlay_has_one_label_per_sticker() {
+        let options = RenderOptions { letter_overlay: true, ..Default::default() };
+        let svg = get_svg_for_state_with_options(&RawState::solved(), &options);
+        assert_eq!(svg.matches("<text").count(), 72);
+        assert!(svg.contains(">BL</text>"));
+        assert!(svg.trim_end().ends_with("</text></svg>"));
+    }
+
+    #[test]
+    fn test_no_labels_without_overlay() {
+        let svg = get_svg_for_state(&RawState::solved());
+        assert_eq!(svg.matches("<text").count(), 0);
+    }
+
+    #[test_case("#fff", "#000")]
+    #[test_case("#000", "#fff")]
+    #[test_case("#0072b2", "#fff")]
+    #[test_case("#f0e442", "#000")]
+    fn test_get_label_colour(fill: &str, expected: &str) {
+        assert_eq!(get_label_colour(fill), expected);
+    }
+
+    #[test]
+    fn test_get_svg_for_coord_state() {
+        let mut raw_state = RawState::solved();
+        raw_state.apply(&Turn::new(Face::U, false));
+        let svg = get_svg_for_coord_state(&raw_state.to_coords()).unwrap();
+        let solved_svg = get_svg_for_state(&RawState::solved());
+
+        // The U face keeps its colour, but the stickers around it cycle
+        assert!(svg.contains(".corn-UBL-U{fill:#fff}"));
+        assert!(svg.contains(".edge-UB-U{fill:#fff}"));
+        for sticker in [".corn-UBL-L{fill:#808}", ".edge-UB-B{fill:#00f}", ".edge-UR-R{fill:#080}", ".cent-BR{fill:#00f}"] {
+            assert!(solved_svg.contains(sticker));
+            assert!(!svg.contains(sticker));
+        }
+        assert!(svg.contains(".corn-UBL-L{fill:#080}"));
+    }
+
+    #[test]
+    fn test_get_svg_for_invalid_coord_state() {
+        let mut state = SOLVED_COORD_STATE;
+        state.corners = u32::MAX;
+        assert!(get_svg_for_coord_state(&state).is_err());
+    }
+
+    #[test]
+    fn test_half_turn_swaps_stickers_in_pairs() {
+        let names: Vec<&str> = get_sticker_name_arrays().concat();
+        for name in names {
+            let rotated = HALF_TURN_STICKERS[name];
+            assert_ne!(rotated, name);
+            assert_eq!(HALF_TURN_STICKERS[rotated], name);
+        }
+        assert_eq!(HALF_TURN_STICKERS["cent-BR"], "cent-UBL");
+        assert_eq!(HALF_TURN_STICKERS["corn-UF-U"], "corn-DB-B");
+    }
+
+    #[test]
+    fn test_get_two_view_svg_for_state() {
+        let mut state = RawState::solved();
+        state.apply(&Turn::new(Face::U, false));
+        let svg = get_two_view_svg_for_state(&state, &RenderOptions::default());
+
+        for name in get_sticker_name_arrays().concat() {
+            for prefix in VIEW_PREFIXES {
+                assert!(svg.contains(&format!("class=\"{}{}\"", prefix, name)));
+                assert!(svg.contains(&format!(".{}{}{{fill:", prefix, name)));
+            }
+        }
+
+        // The B face is on the back of the first view, so its stickers appear on the front of the second view.
+        // Its top row is turned with U, so the stickers there now show the L face colour.
+        assert!(svg.contains(".v1-cent-BD{fill:#00f}"));
+        assert!(svg.contains(".v2-cent-UF{fill:#00f}"));
+        assert!(svg.contains(".v1-cent-BR{fill:#808}"));
+        assert!(svg.contains(".v2-cent-UBL{fill:#808}"));
+        assert!(svg.contains(".v1-edge-UB-B{fill:#808}"));
+        assert!(svg.contains(".v2-edge-UB-U{fill:#808}"));
+        assert!(!svg.contains(STYLE_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_highlight_dims_other_stickers() {
+        let options = RenderOptions {
+            highlight: Some(Highlight::hex(0)),
+            ..Default::default()
+        };
+        let svg = get_svg_for_state_with_options(&RawState::solved(), &options);
+
+        // The U hex keeps its colours
+        assert!(svg.contains(".edge-UB-U{fill:#fff}"));
+        assert!(svg.contains(".edge-UB-B{fill:#00f}"));
+        assert!(svg.contains(".cent-UF{fill:#fff}"));
+        // Everything else is greyed out, including the corners on the U face
+        let dimmed_white = get_dimmed_colour("#fff");
+        assert!(svg.contains(&format!(".corn-UBL-U{{fill:{}}}", dimmed_white)));
+        assert!(svg.contains(&format!(".cent-BR{{fill:{}}}", get_dimmed_colour("#00f"))));
+        assert!(!svg.contains(".edge-FR-R{fill:#080}"));
+    }
+
+    #[test_case("#fff", "#c7c7c7")]
+    #[test_case("#000", "#878787")]
+    fn test_get_dimmed_colour(colour: &str, expected: &str) {
+        assert_eq!(get_dimmed_colour(colour), expected);
+    }
+
+    #[test]
+    fn test_highlight_constructors() {
+        let phase1 = Highlight::phase1_pieces();
+        assert!(phase1.contains(PieceType::Edge, 11));
+        assert!(phase1.contains(PieceType::UpCentre, 0));
+        assert!(!phase1.contains(PieceType::Corner, 0));
+
+        let triples = Highlight::triples();
+        assert!(triples.contains(PieceType::Corner, 5));
+        assert!(triples.contains(PieceType::DownCentre, 11));
+        assert!(!triples.contains(PieceType::Edge, 0));
+
+        let hex = Highlight::hex(3);
+        assert!(hex.contains(PieceType::Edge, 9));
+        assert!(hex.contains(PieceType::UpCentre, 11));
+        assert!(!hex.contains(PieceType::Edge, 8));
+    }
+
+    #[test]
+    fn test_write_svg_to_missing_directory() {
+        let path = std::env::temp_dir().join("fto-missing-directory").join("state.svg");
+        let path = path.to_str().unwrap();
+        let error = write_svg(path, "<svg/>").unwrap_err();
+        assert!(matches!(&error, DrawError::Io { path: error_path, .. } if error_path == path));
+        assert!(error.to_string().contains(path));
+    }
+
+    #[test]
+    fn test_write_svg_to_memory_matches_file() {
+        let mut state = RawState::solved();
+        state.apply(&Turn::new(Face::R, false));
+        let options = RenderOptions {
+            letter_overlay: true,
+            caption: Some(String::from("R")),
+            ..RenderOptions::default()
+        };
+
+        let mut in_memory = Vec::new();
+        write_svg_to(&mut in_memory, &state, &options).unwrap();
+
+        let path = std::env::temp_dir().join(format!("fto-write-to-{}.svg", std::process::id()));
+        write_state_svg(path.to_str().unwrap(), &state, &options).unwrap();
+        let in_file = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(in_memory, in_file);
+        assert_eq!(String::from_utf8(in_memory).unwrap(), get_svg_for_state_with_options(&state, &options));
+    }
+
+    #[test]
+    fn test_write_template_substitutes_in_order() {
+        let mut output = Vec::new();
+        write_template(&mut output, "a<1>b<2>c</svg>", &[("<1>", "x"), ("<missing>", "y"), ("<2>", "z"), ("</svg>", "w</svg>")]).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "axbzcw</svg>");
+    }
+
+    #[test]
+    fn test_render_turn_effect_has_an_arrow_per_moved_sticker() {
+        let raw_turn = Face::U.get_raw_turn();
+        let count_moved = |permutation: &[u8]| permutation.iter().enumerate().filter(|(i, p)| *i != **p as usize).count();
+        let expected = 4 * count_moved(&raw_turn.corner_permutation) + 2 * count_moved(&raw_turn.edges)
+            + count_moved(&raw_turn.up_centres) + count_moved(&raw_turn.down_centres);
+
+        let svg = render_turn_effect(&RawState::solved(), &Turn::new(Face::U, false));
+        let arrows = svg.matches("class=\"arrow-").count();
+        assert_eq!(arrows, expected);
+        assert_eq!(svg.matches("class=\"arrow-corner\"").count(), 4 * count_moved(&raw_turn.corner_permutation));
+        let plain = get_svg_for_state(&RawState::solved());
+        assert!(svg.starts_with(&plain[..plain.rfind(SVG_END_TAG).unwrap()]));
+    }
+
+    #[test]
+    fn test_turn_sticker_moves_follow_the_turn() {
+        let turn = Turn::new(Face::R, true);
+        let mut state = RawState::solved();
+        state.apply(&turn);
+        let faces: HashMap<&str, u8> = get_state_sticker_faces(&RawState::solved()).into_iter().collect();
+        let turned_faces: HashMap<&str, u8> = get_state_sticker_faces(&state).into_iter().collect();
+
+        for (origin, destination) in get_turn_sticker_moves(&turn) {
+            assert_eq!(turned_faces[destination], faces[origin]);
+        }
+    }
+
+    #[test_case(None, None, 0, "0 0 120 60", None, None ; "template size")]
+    #[test_case(Some(240), Some(100), 0, "0 0 120 60", Some("240"), Some("100") ; "both dimensions")]
+    #[test_case(Some(300), None, 0, "0 0 120 60", Some("300"), Some("150") ; "width only")]
+    #[test_case(None, Some(70), 5, "-5 -5 130 70", Some("130"), Some("70") ; "height only with margin")]
+    #[test_case(None, None, 10, "-10 -10 140 80", None, None ; "margin only")]
+    fn test_svg_root_attributes(width: Option<u32>, height: Option<u32>, margin: u32, view_box: &str, expected_width: Option<&str>, expected_height: Option<&str>) {
+        let options = RenderOptions { width, height, margin, ..RenderOptions::default() };
+        let svg = get_svg_for_state_with_options(&RawState::solved(), &options);
+        let root = &svg[..svg.find('>').unwrap()];
+
+        assert_eq!(get_attribute(root, "viewBox"), Some(view_box));
+        assert_eq!(get_attribute(root, "width"), expected_width);
+        assert_eq!(get_attribute(root, "height"), expected_height);
+        assert!(!svg.contains("<rect"));
+    }
+
+    #[test]
+    fn test_default_root_matches_template() {
+        let svg = get_svg_for_state(&RawState::solved());
+        let template = get_svg_template(Layout::Default);
+        assert_eq!(svg[..svg.find('>').unwrap()], template[..template.find('>').unwrap()]);
+    }
+
+    #[test]
+    fn test_svg_background() {
+        let options = RenderOptions {
+            margin: 2,
+            background: Some(String::from("#eee")),
+            layout: Layout::Net,
+            ..RenderOptions::default()
+        };
+        let svg = get_svg_for_state_with_options(&RawState::solved(), &options);
+        let root_end = svg.find('>').unwrap() + 1;
+        assert!(svg[root_end..].starts_with("<rect x=\"-2\" y=\"-2\" width=\"109\" height=\"81.94\" fill=\"#eee\"/>"));
+
+        let two_view = get_two_view_svg_for_state(&RawState::solved(), &options);
+        let root = &two_view[..two_view.find('>').unwrap()];
+        assert_eq!(get_attribute(root, "viewBox"), Some("-2 -2 224 81.94"));
+        assert_eq!(two_view.matches("<rect").count(), 1);
+    }
+
+    #[test_case(Layout::Default ; "default")]
+    #[test_case(Layout::Net ; "net")]
+    fn test_embedded_templates_are_valid(layout: Layout) {
+        assert_eq!(validate_template(get_svg_template(layout)), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_template_reports_missing_names() {
+        let template = get_svg_template(Layout::Default)
+            .replace("class=\"cent-BR\"", "class=\"cent-XX\"")
+            .replace(STYLE_PLACEHOLDER, "");
+        let error = validate_template(&template).unwrap_err();
+        assert_eq!(error.missing, vec![String::from("cent-BR"), STYLE_PLACEHOLDER.to_string()]);
+
+        let result = get_svg_for_state_with_template(&RawState::solved(), &template, &RenderOptions::default());
+        assert_eq!(result, Err(error));
+    }
+
+    #[test]
+    fn test_get_svg_for_state_with_template() {
+        let options = RenderOptions { layout: Layout::Net, ..RenderOptions::default() };
+        let svg = get_svg_for_state_with_template(&RawState::solved(), get_svg_template(Layout::Net), &RenderOptions::default());
+        assert_eq!(svg, Ok(get_svg_for_state_with_options(&RawState::solved(), &options)));
+    }
+
+    #[test]
+    fn test_top_view_template_has_its_stickers() {
+        let polygons = get_sticker_polygons(get_svg_template(Layout::TopView));
+        assert_eq!(polygons.len(), TOP_VIEW_NAMES.len());
+        for name in TOP_VIEW_NAMES {
+            assert!(polygons.contains_key(*name), "{} is missing from the top view template", name);
+        }
+    }
+
+    #[test]
+    fn test_top_view_is_rotated_by_a_u_turn() {
+        // A clockwise U turn rotates every sticker in the top view a third of a turn clockwise about the middle of U
+        let centroids = get_sticker_centroids(get_svg_template(Layout::TopView));
+        let corners = ["corn-UBL-U", "corn-UBR-U", "corn-UF-U"].map(|name| centroids[name]);
+        let centre_x = corners.iter().map(|corner| corner.0).sum::<f32>() / 3.0;
+        let centre_y = corners.iter().map(|corner| corner.1).sum::<f32>() / 3.0;
+        let angle = 2.0 * std::f32::consts::PI / 3.0;
+
+        let moves = get_turn_sticker_moves(&Turn::new(Face::U, false));
+        assert_eq!(moves.len(), TOP_VIEW_NAMES.len());
+        for (origin, destination) in moves {
+            let (x, y) = (centroids[origin].0 - centre_x, centroids[origin].1 - centre_y);
+            let rotated = (centre_x + x * angle.cos() - y * angle.sin(), centre_y + x * angle.sin() + y * angle.cos());
+            let (expected_x, expected_y) = centroids[destination];
+            assert!((rotated.0 - expected_x).abs() < 0.01 && (rotated.1 - expected_y).abs() < 0.01,
+                "{} should move to {}", origin, destination);
+        }
+    }
+
+    #[test]
+    fn test_top_view_ignores_d_turns() {
+        let options = RenderOptions { layout: Layout::TopView, ..RenderOptions::default() };
+        let solved = get_svg_for_state_with_options(&RawState::solved(), &options);
+
+        let mut state = RawState::solved();
+        state.apply_sequence(&[&Turn::new(Face::D, false), &Turn::new(Face::D, false), &Turn::new(Face::D, true)]);
+        assert_ne!(get_svg_for_state(&state), get_svg_for_state(&RawState::solved()));
+        assert_eq!(get_svg_for_state_with_options(&state, &options), solved);
+        assert_eq!(solved.matches("{fill:").count(), TOP_VIEW_NAMES.len());
+    }
+
+    #[test]
+    fn test_render_grid() {
+        let mut turned = RawState::solved();
+        turned.apply(&Turn::new(Face::F, false));
+        let states = vec![
+            (RawState::solved(), Some(String::from("solved"))),
+            (turned.clone(), Some(String::from("F"))),
+            (turned, None),
+            (RawState::solved(), Some(String::from("<none>"))),
+            (RawState::solved(), None),
+        ];
+        let svg = render_grid(&states, 2, &RenderOptions::default()).unwrap();
+        let root = &svg[..svg.find('>').unwrap()];
+
+        assert_eq!(get_attribute(root, "viewBox"), Some("0 0 250 200"));
+        assert_eq!(svg.matches("<g transform=").count(), 5);
+        assert!(svg.contains("class=\"g4-cent-UF\""));
+        assert!(svg.contains(".g1-cent-FU{fill:"));
+        assert_eq!(svg.matches("<text class=\"caption\"").count(), 3);
+        assert!(svg.contains(">F</text>"));
+        assert!(svg.contains(">&lt;none&gt;</text>"));
+        assert!(svg.contains(CAPTION_STYLE));
+    }
+
+    #[test]
+    fn test_render_grid_without_states() {
+        assert_eq!(render_grid(&[], 3, &RenderOptions::default()), None);
+        assert_eq!(render_grid(&[], 0, &RenderOptions::default()), None);
+    }
+
+    #[test]
+    fn test_render_grid_with_no_columns() {
+        let states = vec![(RawState::solved(), None), (RawState::solved(), None)];
+        let svg = render_grid(&states, 0, &RenderOptions::default()).unwrap();
+        let root = &svg[..svg.find('>').unwrap()];
+        assert_eq!(get_attribute(root, "viewBox"), Some("0 0 120 130"));
+    }
+
+    #[test]
+    fn test_render_sequence() {
+        let sequence = [
+            Turn::new(Face::R, false),
+            Turn::new(Face::U, false),
+            Turn::new(Face::U, true),
+            Turn::new(Face::R, true),
+        ];
+        let frames = render_sequence(&RawState::solved(), &sequence);
+
+        assert_eq!(frames.len(), sequence.len() + 1);
+        assert_eq!(frames[0], get_svg_for_state(&RawState::solved()));
+        assert_ne!(frames[1], frames[0]);
+        assert_eq!(frames[4], get_svg_for_state(&RawState::solved()));
+    }
+
+    #[test]
+    fn test_render_sequence_captions() {
+        let sequence = [Turn::new(Face::BL, true), Turn::new(Face::D, false)];
+        let frames = render_sequence_with_options(&RawState::solved(), &sequence, &RenderOptions::default(), true);
+
+        assert!(!frames[0].contains("class=\"caption\""));
+        for (frame, turn) in frames[1..].iter().zip(sequence.iter()) {
+            assert!(frame.contains(&format!("\">{:?}</text>", turn)));
+            assert!(frame.contains(CAPTION_STYLE));
+        }
+        assert!(frames.iter().all(|frame| !frame.contains(CAPTION_PLACEHOLDER)));
+    }
+
+    #[test]
+    fn test_write_sequence_svgs() {
+        let dir = std::env::temp_dir().join(format!("fto-sequence-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let sequence = vec![Turn::new(Face::F, false); 10];
+
+        let paths = write_sequence_svgs(dir.to_str().unwrap(), "step-", &RawState::solved(), &sequence, &RenderOptions::default(), false).unwrap();
+        assert_eq!(paths.len(), 11);
+        assert!(paths[0].ends_with("step-00.svg"));
+        assert!(paths[10].ends_with("step-10.svg"));
+        for path in paths.iter() {
+            assert!(Path::new(path).exists());
+        }
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
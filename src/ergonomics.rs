@@ -0,0 +1,93 @@
+/*
+Scores how easy a sequence is to turn, for choosing between solutions that the search counts as equally good. A
+few things make a sequence easier or harder to execute quickly:
+- Two turns in a row on the same axis, such as U then D, mean turning faces on opposite sides of the puzzle, which
+  usually needs a regrip.
+- Triggers, where a face comes back two turns later, such as R U R', flow well and are quick to learn.
+- B and D are the hardest faces to reach, so several of them in a row slow things down.
+
+With the default weights, R U R' U' (two triggers) scores well above U D B D (a same axis pair and two B and D turns
+in a row), even though both are four turns.
+*/
+
+use crate::movedefs::{Face, Turn};
+
+
+/// The weights for `score_sequence`, each for one of the things listed at the top of this file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scoring {
+    /// Taken off for every turn, so that a longer sequence only ranks above a shorter one if it is much easier.
+    pub per_turn: f32,
+    /// Taken off for every turn on the same axis as the turn before it.
+    pub same_axis: f32,
+    /// Added for every turn of the same face as the turn two before it.
+    pub trigger: f32,
+    /// Taken off for every turn of B or D that comes straight after another turn of B or D.
+    pub back_down: f32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self { per_turn: 1.0, same_axis: 1.0, trigger: 0.5, back_down: 0.5 }
+    }
+}
+
+fn is_back_or_down(turn: &Turn) -> bool {
+    matches!(turn.get_face(), Face::B | Face::D)
+}
+
+/// How easy `sequence` is to turn, with higher scores being easier. Scores can be negative, and are only meant for
+/// comparing sequences with each other.
+pub fn score_sequence(sequence: &[Turn], scoring: &Scoring) -> f32 {
+    let mut score = -scoring.per_turn * sequence.len() as f32;
+    for (i, turn) in sequence.iter().enumerate().skip(1) {
+        let face = turn.get_face();
+        let prev = &sequence[i - 1];
+        if face != prev.get_face() && face.get_primary_face() == prev.get_face().get_primary_face() {
+            score -= scoring.same_axis;
+        }
+        if i >= 2 && face == sequence[i - 2].get_face() && face != prev.get_face() {
+            score += scoring.trigger;
+        }
+        if is_back_or_down(turn) && is_back_or_down(prev) {
+            score -= scoring.back_down;
+        }
+    }
+    score
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(sequence: &str) -> f32 {
+        score_sequence(&Turn::parse_sequence(sequence).unwrap(), &Scoring::default())
+    }
+
+    #[test]
+    fn test_score_sequence() {
+        assert_eq!(score(""), 0.0);
+        assert_eq!(score("R U R' U'"), -3.0);
+        assert_eq!(score("U D B D"), -5.5);
+        assert_eq!(score("F B"), -3.0);
+        assert_eq!(score("B D"), -2.5);
+
+        let no_length = Scoring { per_turn: 0.0, ..Default::default() };
+        assert_eq!(score_sequence(&Turn::parse_sequence("R U BL").unwrap(), &no_length), 0.0);
+    }
+
+    #[test]
+    fn test_hand_ranked_pairs() {
+        // The first of each pair is the easier one to turn, and both are the same length
+        let pairs = [
+            ("R U R' U'", "U D B D"),
+            ("R U R'", "R L BR"),
+            ("F U F' U", "B D B' D'"),
+            ("BL U BL'", "U D U"),
+        ];
+        for (easier, harder) in pairs {
+            assert!(score(easier) > score(harder), "{} should score above {}", easier, harder);
+        }
+    }
+}
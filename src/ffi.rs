@@ -0,0 +1,333 @@
+/*
+A C interface to the solver, for programs such as timers written in C or C++. It is built with the `capi` feature,
+as a shared or static library with
+
+    cargo rustc --release --lib --features capi --crate-type cdylib
+
+and `include/fto_scramble.h` declares the functions, in the layout cbindgen generates with `cbindgen.toml`.
+
+A context from `fto_init` holds the tables and must be released with `fto_free`. Strings passed in and out are
+UTF-8 and null terminated, and output goes into a buffer the caller allocates, with `out_len` giving its size
+including the terminator. Every function that can fail returns one of the negative `FTO_ERR_` codes, or `NULL`
+for `fto_init`, and `fto_last_error_message` describes the last failure on the calling thread.
+*/
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::coordinates::CoordinateType;
+use crate::movedefs::{Face, Turn, get_random_turns};
+use crate::movetables::{MoveTables, MOVE_TABLE_FILE_NAME};
+use crate::pruningtables::{SimplePruningTable, PRUNING_TABLE_FILE_NAME};
+use crate::search::solve_phase_1;
+use crate::state::SOLVED_COORD_STATE;
+
+
+pub const FTO_ERR_NULL_POINTER: i32 = -1;
+pub const FTO_ERR_INVALID_UTF8: i32 = -2;
+pub const FTO_ERR_TABLES: i32 = -3;
+pub const FTO_ERR_NOTATION: i32 = -4;
+pub const FTO_ERR_NO_SOLUTION: i32 = -5;
+pub const FTO_ERR_BUFFER_TOO_SMALL: i32 = -6;
+pub const FTO_ERR_PANIC: i32 = -7;
+
+const MAX_DEPTH: u8 = 9;
+const SCRAMBLE_LENGTH: usize = 30;
+
+
+/// The tables and random number generator used by the other functions. C code only ever sees a pointer to it.
+pub struct FtoContext {
+    move_tables: MoveTables,
+    pruning_tables: SimplePruningTable,
+    rng: StdRng,
+}
+
+/// A failure to pass back to C, as a code and the message for `fto_last_error_message`.
+struct FfiError {
+    code: i32,
+    message: String,
+}
+
+impl FfiError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Loads the tables from `tables_dir`, as filled by `fto-scramble gen-tables`, or generates them if `tables_dir`
+/// is `NULL`, which takes several minutes. Returns `NULL` if the tables couldn't be loaded.
+///
+/// # Safety
+///
+/// `tables_dir` must be `NULL` or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn fto_init(tables_dir: *const c_char) -> *mut FtoContext {
+    let result = catch_panic(|| {
+        let (move_tables, pruning_tables) = match tables_dir.is_null() {
+            true => generate_tables(),
+            false => {
+                let tables_dir = read_string(tables_dir)?;
+                load_tables(Path::new(tables_dir))?
+            }
+        };
+        Ok(FtoContext { move_tables, pruning_tables, rng: StdRng::from_entropy() })
+    });
+    match result {
+        Ok(context) => Box::into_raw(Box::new(context)),
+        Err(error) => {
+            set_last_error(error.message);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Writes a phase 1 solution for `scramble`, such as "U R' BL", to `out_buf` as turns separated by spaces, and
+/// returns its length in bytes, not counting the terminator.
+///
+/// # Safety
+///
+/// `ctx` must come from `fto_init` and not have been freed, `scramble` must point to a null terminated string,
+/// and `out_buf` must point to at least `out_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fto_solve(ctx: *mut FtoContext, scramble: *const c_char, out_buf: *mut c_char, out_len: usize) -> i32 {
+    to_return_code(catch_panic(|| {
+        let context = ctx.as_ref().ok_or_else(|| FfiError::new(FTO_ERR_NULL_POINTER, "the context is NULL"))?;
+        let scramble = Turn::parse_sequence(read_string(scramble)?)
+            .map_err(|error| FfiError::new(FTO_ERR_NOTATION, error.to_string()))?;
+
+        let mut state = SOLVED_COORD_STATE;
+        for turn in scramble.iter() {
+            state.apply(&context.move_tables, turn);
+        }
+        let solution = solve_phase_1(&state, &context.move_tables, &context.pruning_tables, MAX_DEPTH, false)
+            .ok_or_else(|| FfiError::new(FTO_ERR_NO_SOLUTION, format!("no solution within {} moves", MAX_DEPTH)))?;
+        write_string(&Turn::format_sequence(&solution), out_buf, out_len)
+    }))
+}
+
+/// Writes a random move scramble to `out_buf`, and returns its length in bytes, not counting the terminator.
+///
+/// # Safety
+///
+/// `ctx` must come from `fto_init` and not have been freed, and `out_buf` must point to at least `out_len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fto_scramble(ctx: *mut FtoContext, out_buf: *mut c_char, out_len: usize) -> i32 {
+    to_return_code(catch_panic(|| {
+        let context = ctx.as_mut().ok_or_else(|| FfiError::new(FTO_ERR_NULL_POINTER, "the context is NULL"))?;
+        let scramble = get_random_turns(SCRAMBLE_LENGTH, &mut context.rng);
+        write_string(&Turn::format_sequence(&scramble), out_buf, out_len)
+    }))
+}
+
+/// Releases a context. Passing `NULL` does nothing.
+///
+/// # Safety
+///
+/// `ctx` must be `NULL` or come from `fto_init`, and must not be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn fto_free(ctx: *mut FtoContext) {
+    if !ctx.is_null() {
+        drop(Box::from_raw(ctx));
+    }
+}
+
+/// Describes the last failure on the calling thread, or is empty if nothing has failed. The string belongs to the
+/// library, and stays valid until the next call that fails on the same thread.
+#[no_mangle]
+pub extern "C" fn fto_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+fn generate_tables() -> (MoveTables, SimplePruningTable) {
+    let move_tables = MoveTables::generate(&mut |_| {});
+    let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+    pruning_tables.populate(&move_tables);
+    (move_tables, pruning_tables)
+}
+
+fn load_tables(dir: &Path) -> Result<(MoveTables, SimplePruningTable), FfiError> {
+    let to_error = |path: &Path, reason: String| FfiError::new(FTO_ERR_TABLES,
+        format!("could not load the tables in '{}': {}", path.display(), reason));
+
+    let path = dir.join(MOVE_TABLE_FILE_NAME);
+    let move_tables = MoveTables::load_from(&path).map_err(|error| to_error(&path, error.to_string()))?;
+    if !move_tables.all_tables_generated() {
+        return Err(to_error(&path, format!("missing {:?}", move_tables.missing_tables())));
+    }
+
+    let path = dir.join(PRUNING_TABLE_FILE_NAME);
+    let pruning_tables = SimplePruningTable::load_from(&path).map_err(|error| to_error(&path, error.to_string()))?;
+    let missing: Vec<CoordinateType> = CoordinateType::iter()
+        .filter(|coord_type| !pruning_tables.populated_coordinate_types().contains(coord_type))
+        .collect();
+    if !missing.is_empty() {
+        return Err(to_error(&path, format!("missing {:?}", missing)));
+    }
+    Ok((move_tables, pruning_tables))
+}
+
+/// Borrows a string from C.
+///
+/// # Safety
+///
+/// `text` must be `NULL` or point to a null terminated string that outlives the result.
+unsafe fn read_string<'a>(text: *const c_char) -> Result<&'a str, FfiError> {
+    if text.is_null() {
+        return Err(FfiError::new(FTO_ERR_NULL_POINTER, "a string argument is NULL"));
+    }
+    CStr::from_ptr(text).to_str().map_err(|error| FfiError::new(FTO_ERR_INVALID_UTF8, error.to_string()))
+}
+
+/// Copies `text` and a terminator into a buffer from C, returning the length of the text.
+///
+/// # Safety
+///
+/// `out_buf` must be `NULL` or point to at least `out_len` writable bytes.
+unsafe fn write_string(text: &str, out_buf: *mut c_char, out_len: usize) -> Result<i32, FfiError> {
+    if out_buf.is_null() {
+        return Err(FfiError::new(FTO_ERR_NULL_POINTER, "the output buffer is NULL"));
+    }
+    if text.len() >= out_len {
+        return Err(FfiError::new(FTO_ERR_BUFFER_TOO_SMALL,
+            format!("the output needs {} bytes, but the buffer only has {}", text.len() + 1, out_len)));
+    }
+    ptr::copy_nonoverlapping(text.as_ptr(), out_buf as *mut u8, text.len());
+    *out_buf.add(text.len()) = 0;
+    Ok(text.len() as i32)
+}
+
+/// Turns a panic into an error, as unwinding into C code would abort the program.
+fn catch_panic<T>(f: impl FnOnce() -> Result<T, FfiError>) -> Result<T, FfiError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| String::from("unknown panic"));
+        Err(FfiError::new(FTO_ERR_PANIC, format!("panicked: {}", message)))
+    })
+}
+
+fn to_return_code(result: Result<i32, FfiError>) -> i32 {
+    result.unwrap_or_else(|error| {
+        set_last_error(error.message);
+        error.code
+    })
+}
+
+fn set_last_error(message: String) {
+    // A message from Rust can't contain a null byte unless it quotes one from the input, which is cut off there
+    let message = CString::new(message).unwrap_or_else(|error| {
+        let end = error.nul_position();
+        CString::new(&error.into_vec()[..end]).expect("the text before the first null byte has no null bytes")
+    });
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+    use crate::movetables::get_test_move_tables;
+
+    /// A directory holding the test move tables and pruning tables built from them, shared by the tests.
+    fn get_tables_dir() -> &'static Path {
+        static DIR: OnceLock<std::path::PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("fto-ffi-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let move_tables = get_test_move_tables();
+            move_tables.save_to(&dir.join(MOVE_TABLE_FILE_NAME)).unwrap();
+            let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+            pruning_tables.populate(move_tables);
+            pruning_tables.save_to(&dir.join(PRUNING_TABLE_FILE_NAME)).unwrap();
+            dir
+        })
+    }
+
+    fn init() -> *mut FtoContext {
+        let dir = CString::new(get_tables_dir().to_str().unwrap()).unwrap();
+        let ctx = unsafe { fto_init(dir.as_ptr()) };
+        assert!(!ctx.is_null(), "{}", get_last_error());
+        ctx
+    }
+
+    fn get_last_error() -> String {
+        unsafe { CStr::from_ptr(fto_last_error_message()) }.to_str().unwrap().to_string()
+    }
+
+    /// Calls `f` with a buffer of `size` bytes, returning the code and the text written into the buffer.
+    fn call_with_buffer(size: usize, f: impl FnOnce(*mut c_char, usize) -> i32) -> (i32, String) {
+        let mut buffer: Vec<c_char> = vec![1; size];
+        let code = f(buffer.as_mut_ptr(), buffer.len());
+        let text = match code >= 0 {
+            true => unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap().to_string(),
+            false => String::new(),
+        };
+        (code, text)
+    }
+
+    #[test]
+    fn test_solve() {
+        let ctx = init();
+        let scramble = CString::new("U R' BL").unwrap();
+        let (code, solution) = call_with_buffer(64, |buf, len| unsafe { fto_solve(ctx, scramble.as_ptr(), buf, len) });
+        unsafe { fto_free(ctx) };
+
+        assert_eq!(code, solution.len() as i32);
+        let solution = Turn::parse_sequence(&solution).unwrap();
+        assert!(!solution.is_empty() && solution.len() <= MAX_DEPTH as usize);
+    }
+
+    #[test]
+    fn test_scramble() {
+        let ctx = init();
+        let (code, scramble) = call_with_buffer(256, |buf, len| unsafe { fto_scramble(ctx, buf, len) });
+        unsafe { fto_free(ctx) };
+
+        assert_eq!(code, scramble.len() as i32);
+        assert_eq!(Turn::parse_sequence(&scramble).unwrap().len(), SCRAMBLE_LENGTH);
+    }
+
+    #[test]
+    fn test_errors() {
+        let ctx = init();
+        let scramble = CString::new("U R2").unwrap();
+        let (code, _) = call_with_buffer(64, |buf, len| unsafe { fto_solve(ctx, scramble.as_ptr(), buf, len) });
+        assert_eq!(code, FTO_ERR_NOTATION);
+        assert_eq!(get_last_error(), "unrecognised turn 'R2'");
+
+        // The buffer has to have room for the terminator as well
+        let scramble = CString::new("U").unwrap();
+        let (length, solution) = call_with_buffer(64, |buf, len| unsafe { fto_solve(ctx, scramble.as_ptr(), buf, len) });
+        let (code, _) = call_with_buffer(solution.len(), |buf, len| unsafe { fto_solve(ctx, scramble.as_ptr(), buf, len) });
+        assert_eq!(code, FTO_ERR_BUFFER_TOO_SMALL);
+        let (code, _) = call_with_buffer(solution.len() + 1, |buf, len| unsafe { fto_solve(ctx, scramble.as_ptr(), buf, len) });
+        assert_eq!(code, length);
+
+        let (code, _) = call_with_buffer(64, |buf, len| unsafe { fto_solve(ctx, ptr::null(), buf, len) });
+        assert_eq!(code, FTO_ERR_NULL_POINTER);
+        let (code, _) = call_with_buffer(64, |buf, len| unsafe { fto_scramble(ptr::null_mut(), buf, len) });
+        assert_eq!(code, FTO_ERR_NULL_POINTER);
+        unsafe { fto_free(ctx) };
+        unsafe { fto_free(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_init_with_missing_tables() {
+        let dir = CString::new("/no/such/dir").unwrap();
+        assert!(unsafe { fto_init(dir.as_ptr()) }.is_null());
+        assert!(get_last_error().starts_with("could not load the tables in '/no/such/dir/movetables.dat'"),
+            "{}", get_last_error());
+    }
+}
@@ -0,0 +1,87 @@
+/*
+Finds insertions for fewest moves solving. A skeleton is a sequence that solves a scramble except for a few pieces,
+usually a 3-cycle. Rather than adding a cycle at the end, the cycle can be inserted at any point in the skeleton,
+as long as it is the cycle that the pieces need at that point, and some of its turns may then cancel with the
+skeleton's turns on either side.
+
+If the scramble is S and the skeleton is A followed by B, with the insertion X to go between them, then S A X B has
+to be solved. S A B leaves the residual R, so X is B R' B', the inverse of the residual conjugated by B. As a
+sequence of turns that is B, then the inverse of the skeleton and of the scramble, then the inverse of B, which is
+far too long, so the cycle finder is used to find the shortest sequence with the same effect.
+*/
+
+use crate::cycles::CycleFinder;
+use crate::movedefs::Turn;
+use crate::movetables::MoveTables;
+use crate::state::RawState;
+
+
+/// A sequence inserted into a skeleton, and the solution that gives once the turns that cancel are taken out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Insertion {
+    /// How many turns of the skeleton come before the inserted sequence.
+    pub position: usize,
+    pub inserted: Vec<Turn>,
+    /// The skeleton with the sequence inserted and simplified.
+    pub solution: Vec<Turn>,
+    /// How many turns were saved by simplifying, so the skeleton and insertion together were this many turns longer.
+    pub cancelled: usize,
+}
+
+fn get_inverse_sequence(sequence: &[Turn]) -> Vec<Turn> {
+    sequence.iter().rev().map(Turn::inverse).collect()
+}
+
+/// Every place in `skeleton` where a sequence of at most `max_len` turns can be inserted so that it solves
+/// `scramble`, with the shortest such sequence at each, sorted by the length of the solution with the shortest
+/// first. Places with no short enough sequence are left out. The skeleton can leave any residual, but the search
+/// takes time and memory that grow quickly with `max_len`, so it is only practical for short ones such as 3-cycles.
+pub fn find_insertions(skeleton: &[Turn], scramble: &[Turn], move_tables: &MoveTables, max_len: u8) -> Vec<Insertion> {
+    let finder = CycleFinder::new(move_tables, max_len);
+    let undo_all = get_inverse_sequence(&[scramble, skeleton].concat());
+
+    let mut insertions: Vec<Insertion> = (0..=skeleton.len())
+        .filter_map(|position| {
+            let after = &skeleton[position..];
+            let mut goal = RawState::solved();
+            for turn in after.iter().chain(&undo_all).chain(&get_inverse_sequence(after)) {
+                goal.apply(turn);
+            }
+            let inserted = finder.find(&goal)?;
+
+            let spliced = [&skeleton[..position], &inserted, after].concat();
+            let solution = Turn::simplify_sequence(&spliced);
+            let cancelled = spliced.len() - solution.len();
+            Some(Insertion { position, inserted, solution, cancelled })
+        })
+        .collect();
+    insertions.sort_by_key(|insertion| insertion.solution.len());
+    insertions
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movetables::get_test_move_tables;
+
+    #[test]
+    fn test_find_insertions() {
+        let scramble = Turn::parse_sequence("R U BL' D").unwrap();
+        // Undoes the scramble and then a corner 3-cycle, so the cycle is left to insert
+        let skeleton = Turn::parse_sequence("D' BL U' R' L R' L BL L' R L BL' L").unwrap();
+
+        let insertions = find_insertions(&skeleton, &scramble, get_test_move_tables(), 9);
+        assert!(!insertions.is_empty());
+        assert!(insertions.windows(2).all(|pair| pair[0].solution.len() <= pair[1].solution.len()));
+        assert!(insertions[0].cancelled > 0 && insertions[0].solution.len() < skeleton.len());
+        for insertion in insertions {
+            assert_eq!(insertion.solution.len() + insertion.cancelled, skeleton.len() + insertion.inserted.len());
+            let mut state = RawState::solved();
+            for turn in scramble.iter().chain(&insertion.solution) {
+                state.apply(turn);
+            }
+            assert_eq!(state.to_coords(), RawState::solved().to_coords());
+        }
+    }
+}
@@ -0,0 +1,26 @@
+#[cfg(feature = "svg")]
+pub mod drawstate;
+#[cfg(feature = "svg")]
+pub mod drawascii;
+#[cfg(feature = "svg")]
+pub mod drawquery;
+#[cfg(feature = "png")]
+pub mod drawpng;
+pub mod movedefs;
+pub mod coordinates;
+pub mod state;
+pub mod patterns;
+pub mod cycles;
+pub mod insertions;
+pub mod ergonomics;
+pub mod movetables;
+pub mod pruningtables;
+pub mod search;
+pub mod verify;
+pub mod bench;
+pub mod selftest;
+pub mod timings;
+#[cfg(feature = "serde-tables")]
+pub mod serdetables;
+#[cfg(all(feature = "capi", not(target_arch = "wasm32")))]
+pub mod ffi;
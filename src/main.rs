@@ -1,57 +1,42 @@
-use core::time;
-use std::time::Instant;
-use std::thread::sleep;
+mod cli;
+mod config;
+mod serve;
 
-mod drawstate;
-mod movedefs;
-mod coordinates;
-mod state;
-mod movetables;
-mod pruningtables;
-mod search;
+use std::io::{self, Write};
 
-use crate::movedefs::{Face, Turn};
-use crate::pruningtables::SimplePruningTable;
-use crate::search::search_phase_1;
-use crate::state::{CoordState, RawState};
-use crate::movetables::MoveTables;
+use log::Level;
 
+use cli::{CliError, DefaultPaths, Tables};
 
-fn main() {
-
-    let now = Instant::now();
-    println!("Loading move tables");
-    let move_tables = MoveTables::try_load_or_generate();
-    println!("Total time taken: {} seconds", (now.elapsed().as_micros() as f64 / 1_000_000.0));
-
-    let now = Instant::now();
-    println!("Loading pruning tables");
-    let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
-    pruning_tables.populate(&move_tables);
-    println!("Total time taken: {} seconds", (now.elapsed().as_micros() as f64 / 1_000_000.0));
-
-    // let random = CoordState::get_random();
-    let mut random = CoordState::solved();
-    random.apply_sequence(&move_tables, &[
-        &Turn::new(Face::R, false),
-        &Turn::new(Face::U, false),
-        &Turn::new(Face::R, false),
-        &Turn::new(Face::U, false),
-    ]);
-
-    // let svg_data_random = drawstate::get_svg_for_state(&random.to_raw());
-    // drawstate::write_svg("random_start.svg", &svg_data_random);
 
-    let now = Instant::now();
-
-    let limit = 9;
-    println!("Searching to depth {}.", limit);
-
-    let solution = search_phase_1(&random, &move_tables, &pruning_tables, limit, None);
-    println!("{:?}", solution);
+/// Exits with the code for the kind of error, rather than returning it, so that scripts can tell failures apart.
+fn main() {
+    if let Err(error) = try_main() {
+        match error {
+            CliError::Usage(error) => error.exit(),
+            error => {
+                eprintln!("error: {}", error);
+                std::process::exit(error.get_exit_code());
+            }
+        }
+    }
+}
 
-    println!("Total time taken: {} seconds", (now.elapsed().as_micros() as f64 / 1_000_000.0));
+fn try_main() -> Result<(), CliError> {
+    let cli = cli::parse(std::env::args())?;
+    init_logger(&cli);
+    cli::run(cli, &DefaultPaths::for_platform(), &mut Tables::new(), &mut io::stdout())
+}
 
-    sleep(time::Duration::from_secs(1));
-    println!("Done")
+/// Sends log messages to stderr, at the level chosen by -v and -q unless `RUST_LOG` says otherwise. Progress
+/// messages are shown as they are, while anything else is labelled with its level.
+fn init_logger(cli: &cli::Cli) {
+    env_logger::Builder::new()
+        .filter_level(cli.get_log_level())
+        .parse_default_env()
+        .format(|buf, record| match record.level() {
+            Level::Info => writeln!(buf, "{}", record.args()),
+            level => writeln!(buf, "{}: {}", level.to_string().to_lowercase(), record.args()),
+        })
+        .init();
 }
@@ -4,8 +4,9 @@
     to that index. For example, if the move vector for corner permutation is [1, 2, 0, 3, 4, 5], then the corner in the UBL position moves to
     the UBR position, the corner in the UBR postion moves to UF, and the corner in UF moves to UBL. The D corners do not move.
 
-    The effect of a move on orientation is defined as a vector of booleans. Orientation is applied after permutation, and each element of
-    the vector indicates whether or not the piece that arrives in that position is flipped relative to it's starting orientation.
+    The effect of a move on orientation is defined as a bitmask with one bit per corner. Orientation is applied after permutation, and each
+    bit indicates whether or not the piece that arrives in that position is flipped relative to it's starting orientation. See RawTurn for
+    what flipped means.
 */
 
 // Corners:
@@ -23,12 +24,20 @@
 
 use std::fmt;
 use std::borrow::Cow;
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use rand::Rng;
+
+use crate::state::{RawState, apply_orientation, apply_raw_permutation, apply_raw_permutation_buffered, as_fixed_size,
+    flip_num_to_bool_array};
 
 pub const NUM_FACES: usize = 8;
 
 pub const NUM_CORNERS: usize = 6;
 pub const NUM_EDGES: usize = 12;
 pub const NUM_CENTRES: usize = 12;
+pub const NUM_FACELETS: usize = 72;
 
 
 
@@ -112,6 +121,7 @@ const RAW_TURN_D: RawTurn = RawTurn {
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Face {
     U,
     F,
@@ -134,6 +144,27 @@ pub enum TurnEffectType {
     TripleCentre,
 }
 
+/// The effect of a clockwise turn of one face on every piece, using the orderings at the top of this file.
+///
+/// Corner orientation:
+///
+/// Each corner sits on a vertex of the octahedron and touches four faces, two of them up faces (U, F, BL, BR) and
+/// two of them down faces (D, B, L, R). Up and down faces alternate around every vertex, and no turn can move a
+/// sticker from an up face to a down face, so a corner in a given position can only be in one of two orientations.
+/// The two differ by a half turn of the corner about the axis through its vertex, which swaps its two up stickers
+/// with each other and its two down stickers with each other.
+///
+/// A corner is unflipped when its reference up face is showing the same sticker as when solved. The reference
+/// faces are U for the UBL, UBR and UF positions, and BL, BR and F for the DB, DR and DL positions respectively.
+/// The reference down faces follow from that: L, B, R for the U corners and D for the D corners. For example, the
+/// UBL corner in the UBL position is flipped when its U sticker is on BL (and so its L sticker is on B).
+///
+/// `corner_orientation` holds a single 6 bit mask, with the most significant of the six bits for corner 0 (UBL)
+/// and the least significant for corner 5 (DL), so `0b001001` means UF and DL. Orientation is applied after
+/// permutation: a set bit means that the corner arriving in that position has been flipped by the turn, relative
+/// to how it was oriented in the position it came from. U and D turns keep every corner's stickers on the same
+/// reference faces, so they flip nothing. Every other turn flips two of the three corners it moves, so the number
+/// of flipped corners is always even and the orientation of the last corner is fixed by the other five.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RawTurn {
     corner_full_state: [u8; 7],
@@ -145,9 +176,13 @@ pub struct RawTurn {
     pub triple_centres: [u8; 12],
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Turn {
+    /// Deprecated: use `get_face` instead. The field stays public for now so existing code keeps compiling, but
+    /// it may be replaced when the representation of turns changes.
     pub face: Face,
+    /// Deprecated: use `is_inverse` instead, for the same reason as `face`.
     pub invert: bool
 }
 
@@ -160,6 +195,8 @@ impl Face {
         [Self::L, Self::R, Self::B, Self::D]
     }
 
+    /// All faces, in index order: `get_all_faces()[i].to_index() == i`. Code that uses the face index as an
+    /// array subscript, such as the move tables, relies on this.
     pub fn get_all_faces() -> [Self; 8] {
         [Self::U, Self::F, Self::BL, Self::BR, Self::L, Self::R, Self::B, Self::D]
     }
@@ -168,6 +205,12 @@ impl Face {
         RawTurn::get(self)
     }
 
+    /// The four axes of the puzzle, each a pair of opposite faces, with the up face of the pair first. Turns of
+    /// the two faces on an axis commute, and the up face is the one `get_primary_face` gives for both.
+    pub fn get_all_axes() -> [(Self, Self); 4] {
+        [(Self::U, Self::D), (Self::F, Self::B), (Self::BL, Self::R), (Self::BR, Self::L)]
+    }
+
     pub fn get_primary_face(&self) -> Self {
         match self {
             Self::U => Self::U,
@@ -208,7 +251,9 @@ impl Face {
         }
     }
 
-    pub fn to_index(self) -> usize {
+    /// The position of the face in `get_all_faces`. This is a `const fn` so lookup tables indexed by face can be
+    /// built at compile time.
+    pub const fn to_index(self) -> usize {
         match self {
             Self::U => 0,
             Self::F => 1,
@@ -221,7 +266,7 @@ impl Face {
         }
     }
 
-    pub fn from_index(value: usize) -> Self {
+    pub const fn from_index(value: usize) -> Self {
         match value {
             0 => Self::U,
             1 => Self::F,
@@ -270,14 +315,14 @@ impl RawTurn {
         Self::get_for_faces(&Face::get_down_faces())
     }
 
-    fn get_corner_full_state(&self) -> Cow<[u8]> {
+    fn get_corner_full_state(&self) -> Cow<'_, [u8]> {
         let mut state = Vec::with_capacity(NUM_CORNERS + 1);
         state.extend_from_slice(&self.corner_permutation);
         state.extend_from_slice(&self.corner_permutation);
         Cow::Owned(state)
     }
 
-    pub fn get_effect(&self, effect_type: TurnEffectType) -> Cow<[u8]> {
+    pub fn get_effect(&self, effect_type: TurnEffectType) -> Cow<'_, [u8]> {
         match effect_type {
             TurnEffectType::Corner => self.get_corner_full_state(),
             TurnEffectType::CornerPermutation => Cow::Borrowed(&self.corner_permutation),
@@ -289,8 +334,46 @@ impl RawTurn {
             TurnEffectType::TripleCentre => Cow::Borrowed(&self.triple_centres),
         }
     }
+
+    /// Applies a clockwise turn of this face to every kind of piece in a raw state. This is the one place that raw
+    /// states are turned, and `RawState::apply` calls it.
+    pub fn apply_to_raw_state_in_place(&self, state: &mut RawState) {
+        let corner_permutation = self.get_effect(TurnEffectType::CornerPermutation);
+        apply_raw_permutation_buffered::<u8, NUM_CORNERS>(as_fixed_size(&mut state.corners),
+            get_fixed_size_effect(&corner_permutation));
+        apply_orientation(&mut state.corner_orientation, &corner_permutation,
+            &self.get_effect(TurnEffectType::CornerOrientation)[0]);
+
+        let edge_effect = self.get_effect(TurnEffectType::EdgeInFace);
+        apply_raw_permutation_buffered::<u8, NUM_EDGES>(as_fixed_size(&mut state.edges),
+            get_fixed_size_effect(&edge_effect));
+
+        for (centres, effect_type) in [
+            (&mut state.up_centres, TurnEffectType::UpCentre),
+            (&mut state.down_centres, TurnEffectType::DownCentre),
+        ] {
+            let effect = self.get_effect(effect_type);
+            apply_raw_permutation_buffered::<u8, NUM_CENTRES>(as_fixed_size(centres), get_fixed_size_effect(&effect));
+        }
+    }
+}
+
+fn get_fixed_size_effect<const N: usize>(effect: &[u8]) -> &[u8; N] {
+    effect.try_into().expect("every turn moves as many pieces as the state has")
 }
 
+// Each face clockwise then anticlockwise, in the order of `Face::get_all_faces`
+const ALL_TURNS: [(Face, bool); 2 * NUM_FACES] = [
+    (Face::U, false), (Face::U, true),
+    (Face::F, false), (Face::F, true),
+    (Face::BL, false), (Face::BL, true),
+    (Face::BR, false), (Face::BR, true),
+    (Face::L, false), (Face::L, true),
+    (Face::R, false), (Face::R, true),
+    (Face::B, false), (Face::B, true),
+    (Face::D, false), (Face::D, true),
+];
+
 impl Turn {
     pub fn new(face: Face, invert: bool) -> Self {
         Self {
@@ -299,6 +382,15 @@ impl Turn {
          }
     }
 
+    pub fn get_face(&self) -> Face {
+        self.face
+    }
+
+    /// Whether the turn is anticlockwise, written with a prime such as U'.
+    pub fn is_inverse(&self) -> bool {
+        self.invert
+    }
+
     pub fn get_allowed_turns_for_faces(faces: &[Face]) -> Vec<Self> {
         let mut turns = Vec::new();
         for face in faces {
@@ -308,8 +400,58 @@ impl Turn {
         turns
     }
 
+    /// Reads a sequence of turns separated by whitespace, such as "U R' BL".
+    pub fn parse_sequence(sequence: &str) -> Result<Vec<Self>, ParseTurnError> {
+        sequence.split_whitespace().map(Self::from_str).collect()
+    }
+
+    /// The same sequence with turns of the same face merged, such as "R R" into "R'", and turns that cancel out
+    /// removed. Turns on the same axis commute, so a turn also merges across a turn of the opposite face.
+    pub fn simplify_sequence(sequence: &[Self]) -> Vec<Self> {
+        // Each face with how many clockwise turns it makes, from 1 to 2
+        let mut simplified: Vec<(Face, u8)> = Vec::new();
+        for turn in sequence {
+            let amount = if turn.invert { 2 } else { 1 };
+            let len = simplified.len();
+            let same_face_index = match simplified.last() {
+                Some((face, _)) if *face == turn.face => Some(len - 1),
+                Some((face, _)) if face.get_primary_face() == turn.face.get_primary_face()
+                    && len > 1 && simplified[len - 2].0 == turn.face => Some(len - 2),
+                _ => None,
+            };
+            match same_face_index {
+                Some(index) => {
+                    let merged = (simplified[index].1 + amount) % 3;
+                    if merged == 0 {
+                        simplified.remove(index);
+                    } else {
+                        simplified[index].1 = merged;
+                    }
+                },
+                None => simplified.push((turn.face, amount)),
+            }
+        }
+        simplified.into_iter().map(|(face, amount)| Self::new(face, amount == 2)).collect()
+    }
+
+    /// The turn of the same face in the other direction, which undoes this one.
+    pub fn inverse(&self) -> Self {
+        Self::new(self.face, !self.invert)
+    }
+
+    /// The sequence in the notation that `parse_sequence` reads, with the turns separated by spaces.
+    pub fn format_sequence(sequence: &[Self]) -> String {
+        sequence.iter().map(Self::to_string).collect::<Vec<String>>().join(" ")
+    }
+
     pub fn get_all_turns() -> Vec<Self> {
-        Self::get_allowed_turns_for_faces(&Face::get_all_faces())
+        Self::iter_all().collect()
+    }
+
+    /// Every turn, in the same order as `get_all_turns`, without allocating. Searches use this in their inner
+    /// loop, where a new `Vec` for every node would add up.
+    pub fn iter_all() -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator + Clone {
+        ALL_TURNS.iter().map(|(face, invert)| Self::new(*face, *invert))
     }
 
     pub fn get_up_turns() -> Vec<Self> {
@@ -321,7 +463,47 @@ impl Turn {
     }
 }
 
-impl fmt::Debug for Turn {
+/// A turn written in a way that isn't recognised, holding the text that couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTurnError {
+    pub token: String,
+}
+
+impl fmt::Display for ParseTurnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unrecognised turn '{}'", self.token)
+    }
+}
+
+impl std::error::Error for ParseTurnError {}
+
+impl FromStr for Turn {
+    type Err = ParseTurnError;
+
+    /// Reads a face name followed by an optional `'` for an anticlockwise turn.
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let (face_name, invert) = match token.strip_suffix('\'') {
+            Some(face_name) => (face_name, true),
+            None => (token, false),
+        };
+        let face = match face_name {
+            "U" => Face::U,
+            "F" => Face::F,
+            "BL" => Face::BL,
+            "BR" => Face::BR,
+            "L" => Face::L,
+            "R" => Face::R,
+            "B" => Face::B,
+            "D" => Face::D,
+            _ => return Err(ParseTurnError { token: token.to_string() }),
+        };
+        Ok(Self::new(face, invert))
+    }
+}
+
+/// Writes the turn in the usual notation, such as `BL` for a clockwise turn and `BL'` for an anticlockwise one,
+/// which `from_str` reads back.
+impl fmt::Display for Turn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let invert_symbol = if self.invert {
             "'"
@@ -332,10 +514,531 @@ impl fmt::Debug for Turn {
     }
 }
 
+impl fmt::Debug for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+
+/// Whether `curr_turn` is wasted straight after `prev_turn`, either because it turns the same face again or because
+/// it puts two commuting turns in the order that searches and scrambles skip.
+pub fn is_redundant_turn(prev_turn: Option<&Turn>, curr_turn: &Turn) -> bool {
+    match prev_turn {
+        Some(prev_turn) => {
+            let prev_face = prev_turn.get_face();
+            let curr_face = curr_turn.get_face();
+
+            // Don't turn the same face twice
+            prev_face == curr_face ||
+            // Turns on the same axis commute, so only allow the primary face before the secondary one, not after it
+            // (favour the secondary face in phase 1)
+            Face::get_all_axes().contains(&(curr_face, prev_face))
+        },
+        None => false,
+    }
+}
+
+/// Picks `num_moves` random turns, never choosing one that would be redundant after the turn before it.
+pub fn get_random_turns(num_moves: usize, rng: &mut impl Rng) -> Vec<Turn> {
+    let turns = Turn::get_all_turns();
+    let mut sequence: Vec<Turn> = Vec::with_capacity(num_moves);
+
+    for _ in 0..num_moves {
+        let allowed_turns: Vec<&Turn> = turns.iter()
+            .filter(|turn| !is_redundant_turn(sequence.last(), turn))
+            .collect();
+        sequence.push(*allowed_turns[rng.gen_range(0..allowed_turns.len())]);
+    }
+    sequence
+}
+
+
+/// The puzzle definition as KPuzzle JSON, as used by cubing.js and twizzle, with an orbit for each kind of piece
+/// in the orders given at the top of this file. Each move's permutation lists, for every position, the position
+/// its new piece came from, which is the same convention as `RawTurn`, so the arrays are written as they are. The
+/// corner orientation bitmask becomes one 0 or 1 per corner, first corner first. Edges have no orientation here,
+/// and the centres of each colour are identical, so the solved state labels each set of three with the same piece.
+pub fn export_kpuzzle() -> String {
+    let pieces_in_threes: Vec<u8> = (0..NUM_CENTRES as u8).map(|i| i / 3).collect();
+    let no_orientation = [0; NUM_CENTRES];
+
+    let moves: serde_json::Map<String, serde_json::Value> = Face::get_all_faces().iter().map(|face| {
+        let raw_turn = face.get_raw_turn();
+        let corner_flips = flip_num_to_bool_array(&raw_turn.corner_orientation[0]).map(u8::from);
+        let definition = serde_json::json!({
+            "CORNERS": { "permutation": raw_turn.corner_permutation, "orientationDelta": corner_flips },
+            "EDGES": { "permutation": raw_turn.edges, "orientationDelta": no_orientation },
+            "UP_CENTRES": { "permutation": raw_turn.up_centres, "orientationDelta": no_orientation },
+            "DOWN_CENTRES": { "permutation": raw_turn.down_centres, "orientationDelta": no_orientation },
+        });
+        (format!("{:?}", face), definition)
+    }).collect();
+
+    let definition = serde_json::json!({
+        "name": "FTO",
+        "orbits": [
+            { "orbitName": "CORNERS", "numPieces": NUM_CORNERS, "numOrientations": 2 },
+            { "orbitName": "EDGES", "numPieces": NUM_EDGES, "numOrientations": 1 },
+            { "orbitName": "UP_CENTRES", "numPieces": NUM_CENTRES, "numOrientations": 1 },
+            { "orbitName": "DOWN_CENTRES", "numPieces": NUM_CENTRES, "numOrientations": 1 },
+        ],
+        "defaultPattern": {
+            "CORNERS": { "pieces": [0, 1, 2, 3, 4, 5], "orientation": [0, 0, 0, 0, 0, 0] },
+            "EDGES": { "pieces": (0..NUM_EDGES).collect::<Vec<_>>(), "orientation": no_orientation },
+            "UP_CENTRES": { "pieces": pieces_in_threes, "orientation": no_orientation },
+            "DOWN_CENTRES": { "pieces": pieces_in_threes, "orientation": no_orientation },
+        },
+        "moves": moves,
+    });
+    serde_json::to_string_pretty(&definition).expect("the definition only holds numbers and strings")
+}
+
+
+/*
+    Stickers:
+    Each sticker is named after the position of the piece it belongs to and, for corners and edges, the face it is
+    on, such as "corn-UF-F" for the F sticker of the UF corner. These are the class names used in the SVG templates,
+    and the drawings, text renderings and solution checks all find stickers by them. Sticker faces are numbered
+    U=0, F=1, BL=2, BR=3, D=4, B=5, L=6, R=7, which is not the same order as `Face::to_index`.
+*/
+
+pub(crate) const FACE_LETTERS: &[&str] = &["U", "F", "BL", "BR", "D", "B", "L", "R"];
+
+pub(crate) const U: u8 = 0;
+pub(crate) const F: u8 = 1;
+pub(crate) const BL: u8 = 2;
+pub(crate) const BR: u8 = 3;
+pub(crate) const D: u8 = 4;
+pub(crate) const B: u8 = 5;
+pub(crate) const L: u8 = 6;
+pub(crate) const R: u8 = 7;
+
+pub(crate) const CORNER_NAMES_UP_GOOD: &[&str] = &[
+    "corn-UBL-U",
+    "corn-UBR-U",
+    "corn-UF-U",
+    "corn-DB-BL",
+    "corn-DR-BR",
+    "corn-DL-F",
+];
+pub(crate) const CORNER_NAMES_UP_FLIPPED: &[&str] = &[
+    "corn-UBL-BL",
+    "corn-UBR-BR",
+    "corn-UF-F",
+    "corn-DB-BR",
+    "corn-DR-F",
+    "corn-DL-BL",
+];
+pub(crate) const CORNER_NAMES_DOWN_GOOD: &[&str] = &[
+    "corn-UBL-L",
+    "corn-UBR-B",
+    "corn-UF-R",
+    "corn-DB-D",
+    "corn-DR-D",
+    "corn-DL-D",
+];
+pub(crate) const CORNER_NAMES_DOWN_FLIPPED: &[&str] = &[
+    "corn-UBL-B",
+    "corn-UBR-R",
+    "corn-UF-L",
+    "corn-DB-B",
+    "corn-DR-R",
+    "corn-DL-L",
+];
+pub(crate) const EDGE_UP_NAMES: &[&str] = &[
+    "edge-UB-U",
+    "edge-UR-U",
+    "edge-UL-U",
+    "edge-BLB-BL",
+    "edge-BLL-BL",
+    "edge-BLD-BL",
+    "edge-BRR-BR",
+    "edge-BRB-BR",
+    "edge-BRD-BR",
+    "edge-FL-F",
+    "edge-FR-F",
+    "edge-FD-F",
+];
+pub(crate) const EDGE_DOWN_NAMES: &[&str] = &[
+    "edge-UB-B",
+    "edge-UR-R",
+    "edge-UL-L",
+    "edge-BLB-B",
+    "edge-BLL-L",
+    "edge-BLD-D",
+    "edge-BRR-R",
+    "edge-BRB-B",
+    "edge-BRD-D",
+    "edge-FL-L",
+    "edge-FR-R",
+    "edge-FD-D",
+];
+pub(crate) const UP_CENTRE_NAMES: &[&str] = &[
+    "cent-UBL",
+    "cent-UBR",
+    "cent-UF",
+    "cent-BLU",
+    "cent-BLF",
+    "cent-BLBR",
+    "cent-BRU",
+    "cent-BRBL",
+    "cent-BRF",
+    "cent-FU",
+    "cent-FBR",
+    "cent-FBL",
+];
+pub(crate) const DOWN_CENTRE_NAMES: &[&str] = &[
+    "cent-BR",
+    "cent-BL",
+    "cent-BD",
+    "cent-RL",
+    "cent-RB",
+    "cent-RD",
+    "cent-LB",
+    "cent-LR",
+    "cent-LD",
+    "cent-DL",
+    "cent-DR",
+    "cent-DB",
+];
+
+lazy_static! {
+    static ref CORNER_NAMES: Vec<&'static str> = [
+        CORNER_NAMES_UP_GOOD, CORNER_NAMES_UP_FLIPPED, CORNER_NAMES_DOWN_GOOD, CORNER_NAMES_DOWN_FLIPPED].concat();
+    static ref EDGE_NAMES: Vec<&'static str> = [EDGE_UP_NAMES, EDGE_DOWN_NAMES].concat();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PieceType {
+    Corner,
+    Edge,
+    UpCentre,
+    DownCentre,
+}
+
+impl PieceType {
+    pub fn get_num_pieces(self) -> usize {
+        match self {
+            Self::Corner => NUM_CORNERS,
+            Self::Edge => NUM_EDGES,
+            Self::UpCentre | Self::DownCentre => NUM_CENTRES,
+        }
+    }
+}
+
+pub(crate) struct StickerState {
+    pub(crate) corner_up_good: [u8; 6],
+    pub(crate) corner_up_flipped: [u8; 6],
+    pub(crate) corner_down_good: [u8; 6],
+    pub(crate) corner_down_flipped: [u8; 6],
+    pub(crate) edge_up: [u8; 12],
+    pub(crate) edge_down: [u8; 12],
+    pub(crate) up_centres: [u8; 12],
+    pub(crate) down_centres: [u8; 12],
+}
+
+impl StickerState {
+    pub(crate) fn get_initial() -> Self {
+        Self {
+            corner_up_good: [U,U,U,BL,BR,F],
+            corner_up_flipped: [BL,BR,F,BR,F,BL],
+            corner_down_good: [L,B,R,D,D,D],
+            corner_down_flipped: [B,R,L,B,R,L],
+            edge_up: [U,U,U,BL,BL,BL,BR,BR,BR,F,F,F],
+            edge_down: [B,R,L,B,L,D,R,B,D,L,R,D],
+            up_centres: [U,U,U,BL,BL,BL,BR,BR,BR,F,F,F],
+            down_centres: [B,B,B,R,R,R,L,L,L,D,D,D],
+        }
+    }
+
+    /// Gives every sticker its own number, its index in the order of `get_sticker_name_arrays`, so that it can
+    /// be followed as it moves.
+    pub(crate) fn get_numbered() -> Self {
+        fn numbered<const N: usize>(first: u8) -> [u8; N] {
+            std::array::from_fn(|i| first + i as u8)
+        }
+        Self {
+            corner_up_good: numbered(0),
+            corner_up_flipped: numbered(6),
+            corner_down_good: numbered(12),
+            corner_down_flipped: numbered(18),
+            edge_up: numbered(24),
+            edge_down: numbered(36),
+            up_centres: numbered(48),
+            down_centres: numbered(60),
+        }
+    }
+
+    /// The stickers in the order of `get_sticker_name_arrays`, the reverse of `get_facelets`.
+    #[cfg(feature = "svg")]
+    pub(crate) fn from_facelets(facelets: &[u8; NUM_FACELETS]) -> Self {
+        let mut stickers = Self::get_initial();
+        let mut rest = &facelets[..];
+        for set in [&mut stickers.corner_up_good, &mut stickers.corner_up_flipped, &mut stickers.corner_down_good,
+                &mut stickers.corner_down_flipped] {
+            let (first, after) = rest.split_at(NUM_CORNERS);
+            set.copy_from_slice(first);
+            rest = after;
+        }
+        for set in [&mut stickers.edge_up, &mut stickers.edge_down, &mut stickers.up_centres, &mut stickers.down_centres] {
+            let (first, after) = rest.split_at(NUM_CENTRES);
+            set.copy_from_slice(first);
+            rest = after;
+        }
+        stickers
+    }
+
+    pub(crate) fn create_from_raw_state(state: &RawState) -> Self {
+        StickerState::get_initial().moved_by(state)
+    }
+
+    /// Moves the stickers the way the pieces in the state have been moved from solved.
+    pub(crate) fn moved_by(self, state: &RawState) -> Self {
+        let mut stickers = self;
+
+        apply_raw_permutation(&mut stickers.corner_up_good, &state.corners);
+        apply_raw_permutation(&mut stickers.corner_up_flipped, &state.corners);
+        apply_raw_permutation(&mut stickers.corner_down_good, &state.corners);
+        apply_raw_permutation(&mut stickers.corner_down_flipped, &state.corners);
+        apply_sticker_orientation(&mut stickers.corner_up_good, &mut stickers.corner_up_flipped, &state.corner_orientation);
+        apply_sticker_orientation(&mut stickers.corner_down_good, &mut stickers.corner_down_flipped, &state.corner_orientation);
+
+        apply_raw_permutation(&mut stickers.edge_up, &state.edges);
+        apply_raw_permutation(&mut stickers.edge_down, &state.edges);
+
+        apply_raw_permutation(&mut stickers.up_centres, &state.up_centres);
+        apply_raw_permutation(&mut stickers.down_centres, &state.down_centres);
+
+        stickers
+    }
+}
+
+/// Returns the class names of every sticker belonging to the given type of piece. Corners and edges have more
+/// than one sticker, so their names are listed one full set of positions at a time, which means the position of
+/// the piece a sticker belongs to is its index modulo the number of pieces.
+pub fn get_sticker_names(piece_type: PieceType) -> &'static [&'static str] {
+    match piece_type {
+        PieceType::Corner => &CORNER_NAMES,
+        PieceType::Edge => &EDGE_NAMES,
+        PieceType::UpCentre => UP_CENTRE_NAMES,
+        PieceType::DownCentre => DOWN_CENTRE_NAMES,
+    }
+}
+
+/// Finds the type and position of the piece that a sticker class name belongs to.
+pub fn get_piece_for_sticker(name: &str) -> Option<(PieceType, usize)> {
+    [PieceType::Corner, PieceType::Edge, PieceType::UpCentre, PieceType::DownCentre].into_iter()
+        .find_map(|piece_type| {
+            get_sticker_names(piece_type).iter()
+                .position(|sticker| *sticker == name)
+                .map(|index| (piece_type, index % piece_type.get_num_pieces()))
+        })
+}
+
+fn apply_sticker_orientation(good_stickers: &mut [u8], flipped_stickers: &mut [u8], effect: &u8) {
+    let flip = flip_num_to_bool_array(effect);
+
+    for i in 0..flip.len() {
+        if flip[i] {
+            std::mem::swap(&mut good_stickers[i], &mut flipped_stickers[i]);
+        }
+    }
+}
+
+pub(crate) fn get_sticker_arrays(stickers: &StickerState) -> [&[u8]; 8] {
+    [
+        &stickers.corner_up_good, &stickers.corner_up_flipped, &stickers.corner_down_good, &stickers.corner_down_flipped,
+        &stickers.edge_up, &stickers.edge_down,
+        &stickers.up_centres, &stickers.down_centres]
+}
+
+pub(crate) fn get_sticker_name_arrays() -> [&'static [&'static str]; 8] {
+    [
+        CORNER_NAMES_UP_GOOD, CORNER_NAMES_UP_FLIPPED, CORNER_NAMES_DOWN_GOOD, CORNER_NAMES_DOWN_FLIPPED,
+        EDGE_UP_NAMES, EDGE_DOWN_NAMES,
+        UP_CENTRE_NAMES, DOWN_CENTRE_NAMES]
+}
+
+/// The face constant of the colour every sticker shows, one sticker position at a time in the order of the
+/// sticker names, which is the same order `export_gap` numbers the positions in.
+pub fn get_facelets(state: &RawState) -> [u8; NUM_FACELETS] {
+    let stickers = StickerState::create_from_raw_state(state);
+    get_sticker_arrays(&stickers).concat().try_into().expect("there is one face for every sticker")
+}
+
+/// Lists the class name of every sticker along with the index of the face whose colour it shows.
+pub(crate) fn get_state_sticker_faces(state: &RawState) -> Vec<(&'static str, u8)> {
+    get_sticker_faces(&StickerState::create_from_raw_state(state))
+}
+
+pub(crate) fn get_sticker_faces(stickers: &StickerState) -> Vec<(&'static str, u8)> {
+    let sticker_arrays = get_sticker_arrays(stickers);
+    let names = get_sticker_name_arrays();
+
+    let mut faces = Vec::new();
+    for i in 0..8 {
+        for (face, name) in sticker_arrays[i].iter().zip(names[i]) {
+            faces.push((*name, *face));
+        }
+    }
+    faces
+}
+
+/// The face a sticker position is on, as one of the face constants. Corner and edge sticker names end with their
+/// face, while centres are listed three to a face in the order of the face constants for their half.
+pub(crate) fn get_sticker_position_face(name: &str) -> u8 {
+    let (prefix, suffix) = name.rsplit_once('-').expect("sticker names have a piece type prefix");
+    if prefix == "cent" {
+        if let Some(index) = UP_CENTRE_NAMES.iter().position(|centre| *centre == name) {
+            return [U, BL, BR, F][index / 3];
+        }
+        let index = DOWN_CENTRE_NAMES.iter().position(|centre| *centre == name).expect("centre names are all listed");
+        return [B, R, L, D][index / 3];
+    }
+    FACE_LETTERS.iter().position(|letter| *letter == suffix).expect("sticker names end with a face") as u8
+}
+
+/// The eight face turns as a GAP script, with each turn a permutation of the 72 sticker positions and
+/// `G := Group(gen_U, …)` generated by them. The positions are numbered from 1 in the order of
+/// `get_sticker_name_arrays`, which the script lists in a comment at the top:
+///
+/// | Numbers | Stickers                                                |
+/// | ------- | ------------------------------------------------------- |
+/// | 1-6     | corner up stickers, on their reference up face          |
+/// | 7-12    | corner up stickers, on the other up face                |
+/// | 13-18   | corner down stickers, on their reference down face      |
+/// | 19-24   | corner down stickers, on the other down face            |
+/// | 25-36   | edge up stickers                                        |
+/// | 37-48   | edge down stickers                                      |
+/// | 49-60   | up centres                                              |
+/// | 61-72   | down centres                                            |
+///
+/// Within each range, the stickers are in the piece order given at the top of this file. A flipped corner swaps
+/// its two up stickers and its two down stickers, so the orientation is part of the sticker permutation. The
+/// generators act on positions as GAP does, with `i^gen_U` the position that the sticker in position `i` moves to.
+pub fn export_gap() -> String {
+    let names = get_sticker_name_arrays();
+    let mut script = String::from("# The FTO face turns as permutations of the sticker positions, which are numbered:\n");
+    let mut first = 1;
+    for names in names {
+        script.push_str(&format!("#   {}-{}: {}\n", first, first + names.len() - 1, names.join(" ")));
+        first += names.len();
+    }
+
+    // The solved state labels centres by colour, so number them all instead to follow each one
+    let numbered: Vec<u8> = (0..NUM_CENTRES as u8).collect();
+    let mut generators = Vec::new();
+    for face in Face::get_all_faces() {
+        let mut state = RawState::new(&numbered[..NUM_CORNERS], 0, &numbered, &numbered, &numbered);
+        state.apply(&Turn::new(face, false));
+        // Each position holds the number of the position its sticker came from
+        let sources: Vec<u8> = get_sticker_arrays(&StickerState::get_numbered().moved_by(&state)).concat();
+        let mut destinations = vec![0; sources.len()];
+        for (position, source) in sources.iter().enumerate() {
+            destinations[*source as usize] = position;
+        }
+        let generator = format!("gen_{:?}", face);
+        script.push_str(&format!("{} := {};\n", generator, get_gap_cycles(&destinations)));
+        generators.push(generator);
+    }
+    script.push_str(&format!("G := Group({});\n", generators.join(", ")));
+    script
+}
+
+/// Writes a permutation in cycle notation, numbering from 1 and leaving out the positions that don't move.
+fn get_gap_cycles(destinations: &[usize]) -> String {
+    let mut seen = vec![false; destinations.len()];
+    let mut cycles = String::new();
+    for start in 0..destinations.len() {
+        if seen[start] || destinations[start] == start {
+            continue;
+        }
+        let mut cycle = Vec::new();
+        let mut position = start;
+        while !seen[position] {
+            seen[position] = true;
+            cycle.push((position + 1).to_string());
+            position = destinations[position];
+        }
+        cycles.push_str(&format!("({})", cycle.join(",")));
+    }
+    if cycles.is_empty() {
+        cycles.push_str("()");
+    }
+    cycles
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_case::test_case;
+
+    const FACE_BYTES: [u8; NUM_FACES] = {
+        let mut bytes = [0; NUM_FACES];
+        bytes[Face::U.to_index()] = b'U';
+        bytes[Face::F.to_index()] = b'F';
+        bytes[Face::BL.to_index()] = b'P';
+        bytes[Face::BR.to_index()] = b'S';
+        bytes[Face::L.to_index()] = b'L';
+        bytes[Face::R.to_index()] = b'R';
+        bytes[Face::B.to_index()] = b'B';
+        bytes[Face::D.to_index()] = b'D';
+        bytes
+    };
+
+    const LAST_FACE: Face = Face::from_index(NUM_FACES - 1);
+
+    #[test]
+    fn test_turn_getters() {
+        for turn in Turn::get_all_turns() {
+            assert_eq!(Turn::new(turn.get_face(), turn.is_inverse()), turn);
+        }
+        assert_eq!(Turn::new(Face::BL, true).get_face(), Face::BL);
+        assert!(Turn::new(Face::BL, true).is_inverse());
+        assert!(!Turn::new(Face::D, false).is_inverse());
+    }
+
+    #[test]
+    fn test_face_indices_in_const_context() {
+        for face in Face::get_all_faces() {
+            assert_eq!(FACE_BYTES[face.to_index()], face.to_byte());
+            assert_eq!(Face::from_index(face.to_index()), face);
+        }
+        assert_eq!(LAST_FACE, Face::D);
+    }
+
+    #[test]
+    fn test_turns_flip_two_of_the_corners_they_move() {
+        for face in Face::get_all_faces() {
+            let raw_turn = face.get_raw_turn();
+            let flipped: Vec<usize> = (0..NUM_CORNERS)
+                .filter(|i| (raw_turn.corner_orientation[0] >> (NUM_CORNERS - 1 - i)) & 1 == 1)
+                .collect();
+            let expected_flips = match face {
+                Face::U | Face::D => 0,
+                _ => 2,
+            };
+            assert_eq!(flipped.len(), expected_flips, "{:?}", face);
+            assert!(flipped.iter().all(|i| raw_turn.corner_permutation[*i] as usize != *i), "{:?}", face);
+        }
+    }
+
+    #[test]
+    fn test_apply_to_raw_state_in_place() {
+        let mut state = RawState::solved();
+        RawTurn::get(Face::U).apply_to_raw_state_in_place(&mut state);
+        assert_eq!(state, RawState::new(&[2, 0, 1, 3, 4, 5], 0, &[2, 0, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9], &[6, 6, 0, 0, 0, 3, 3, 3, 6, 9, 9, 9]));
+
+        for face in Face::get_all_faces() {
+            let mut state = RawState::solved();
+            state.apply(&Turn::new(face, true));
+            RawTurn::get(face).apply_to_raw_state_in_place(&mut state);
+            assert_eq!(state, RawState::solved(), "{:?}", face);
+        }
+    }
 
     #[test]
     fn test_get_all_faces() {
@@ -344,6 +1047,14 @@ mod tests {
         assert_eq!(turns, expected);
     }
 
+    #[test]
+    fn test_get_all_faces_is_in_index_order() {
+        for (i, face) in Face::get_all_faces().iter().enumerate() {
+            assert_eq!(face.to_index(), i);
+            assert_eq!(Face::from_index(i), *face);
+        }
+    }
+
     #[test]
     fn test_get_up_faces() {
         let turns = Face::get_up_faces();
@@ -370,6 +1081,14 @@ mod tests {
         assert_eq!(RawTurn::get(Face::BL), &RAW_TURN_BL);
     }
 
+    // Phase 1 relies on up face turns leaving every triple where it is
+    #[test]
+    fn test_up_face_triple_centres_are_identity() {
+        for face in Face::get_up_faces() {
+            assert_eq!(RawTurn::get(face).triple_centres, [0,1,2,3,4,5,6,7,8,9,10,11], "{:?}", face);
+        }
+    }
+
     #[test]
     fn test_get_single_turn_for_face() {
         assert_eq!(Face::U.get_raw_turn(), &RAW_TURN_U);
@@ -382,6 +1101,48 @@ mod tests {
         assert_eq!(Face::BL.get_raw_turn(), &RAW_TURN_BL);
     }
 
+    // A face turn moves 3 corners with 4 stickers each, 3 edges with 2 stickers each, the 3 centres on the face
+    // and the 6 centres next to it, all in 3-cycles
+    #[test]
+    fn test_export_gap() {
+        let script = export_gap();
+        let generators: Vec<&str> = script.lines().filter(|line| line.starts_with("gen_")).collect();
+        assert_eq!(generators.len(), NUM_FACES);
+        for generator in generators {
+            let (name, permutation) = generator.split_once(" := ").unwrap();
+            let cycles: Vec<Vec<u8>> = permutation.trim_end_matches(';')
+                .trim_start_matches('(').trim_end_matches(')')
+                .split(")(")
+                .map(|cycle| cycle.split(',').map(|point| point.parse().unwrap()).collect())
+                .collect();
+            assert!(cycles.iter().all(|cycle| cycle.len() == 3), "{}", name);
+            assert_eq!(cycles.iter().map(Vec::len).sum::<usize>(), 12 + 6 + 3 + 6, "{}", name);
+            assert!(cycles.iter().flatten().all(|point| (1..=72).contains(point)), "{}", name);
+        }
+        assert!(script.contains("gen_U := (1,2,3)"));
+        assert!(script.contains("#   49-60: cent-UBL cent-UBR"));
+        assert!(script.ends_with("G := Group(gen_U, gen_F, gen_BL, gen_BR, gen_L, gen_R, gen_B, gen_D);\n"));
+    }
+
+    #[test]
+    fn test_get_gap_cycles() {
+        assert_eq!(get_gap_cycles(&[1, 2, 0, 3, 5, 4]), "(1,2,3)(5,6)");
+        assert_eq!(get_gap_cycles(&[0, 1]), "()");
+    }
+
+    #[test]
+    fn test_get_all_axes() {
+        let axes = Face::get_all_axes();
+        let mut faces: Vec<Face> = axes.iter().flat_map(|&(primary, secondary)| [primary, secondary]).collect();
+        faces.sort_by_key(|face| face.to_index());
+        assert_eq!(faces, Face::get_all_faces());
+        for (primary, secondary) in axes {
+            assert!(Face::get_up_faces().contains(&primary));
+            assert_eq!(primary.get_primary_face(), primary);
+            assert_eq!(secondary.get_primary_face(), primary);
+        }
+    }
+
     #[test]
     fn test_get_opposing_faces() {
         assert_eq!(Face::U.get_primary_face(), Face::U);
@@ -408,6 +1169,59 @@ mod tests {
         assert_eq!(turns, expected);
     }
 
+    fn get_numbers(value: &serde_json::Value) -> Vec<u8> {
+        value.as_array().unwrap().iter().map(|number| number.as_u64().unwrap() as u8).collect()
+    }
+
+    #[test]
+    fn test_export_kpuzzle() {
+        let definition: serde_json::Value = serde_json::from_str(&export_kpuzzle()).unwrap();
+
+        let orbits: Vec<&str> = definition["orbits"].as_array().unwrap().iter()
+            .map(|orbit| orbit["orbitName"].as_str().unwrap())
+            .collect();
+        assert_eq!(orbits, ["CORNERS", "EDGES", "UP_CENTRES", "DOWN_CENTRES"]);
+        assert_eq!(definition["orbits"][0]["numOrientations"], 2);
+        assert_eq!(get_numbers(&definition["defaultPattern"]["UP_CENTRES"]["pieces"]), [0,0,0,1,1,1,2,2,2,3,3,3]);
+        assert_eq!(definition["moves"].as_object().unwrap().len(), NUM_FACES);
+
+        // KPuzzle and RawTurn both list where each position's new piece came from, so no conversion is needed
+        assert_eq!(get_numbers(&definition["moves"]["U"]["CORNERS"]["permutation"]), RAW_TURN_U.corner_permutation);
+        assert_eq!(get_numbers(&definition["moves"]["F"]["CORNERS"]["orientationDelta"]), [0,0,1,0,0,1]);
+    }
+
+    // Applies each move the way cubing.js does and checks it against RawState::apply
+    #[test]
+    fn test_export_kpuzzle_moves_match_raw_state() {
+        let definition: serde_json::Value = serde_json::from_str(&export_kpuzzle()).unwrap();
+        for face in Face::get_all_faces() {
+            let mut state = RawState::solved();
+            state.apply(&Turn::new(face, false));
+
+            let mut moved = vec![];
+            for orbit in ["CORNERS", "EDGES", "UP_CENTRES", "DOWN_CENTRES"] {
+                let pattern = &definition["defaultPattern"][orbit];
+                let effect = &definition["moves"][format!("{:?}", face)][orbit];
+                let pieces = get_numbers(&pattern["pieces"]);
+                let orientation = get_numbers(&pattern["orientation"]);
+                let permutation = get_numbers(&effect["permutation"]);
+                let delta = get_numbers(&effect["orientationDelta"]);
+                let new_pieces: Vec<u8> = permutation.iter().map(|&from| pieces[from as usize]).collect();
+                let new_orientation: Vec<u8> = permutation.iter().zip(&delta)
+                    .map(|(&from, change)| (orientation[from as usize] + change) % 2)
+                    .collect();
+                moved.push((new_pieces, new_orientation));
+            }
+
+            let flips: Vec<u8> = flip_num_to_bool_array(&state.corner_orientation).map(u8::from).to_vec();
+            let in_threes = |centres: &[u8]| centres.iter().map(|centre| centre / 3).collect::<Vec<u8>>();
+            assert_eq!(moved[0], (state.corners.clone(), flips), "{:?}", face);
+            assert_eq!(moved[1].0, state.edges, "{:?}", face);
+            assert_eq!(moved[2].0, in_threes(&state.up_centres), "{:?}", face);
+            assert_eq!(moved[3].0, in_threes(&state.down_centres), "{:?}", face);
+        }
+    }
+
     #[test]
     fn test_get_down_raw_turns() {
         let turns = RawTurn::get_for_down_faces();
@@ -452,6 +1266,66 @@ mod tests {
         assert_turn_list_contains_each_face_both_directions(&turns, &Face::get_up_faces())
     }
 
+    #[test]
+    fn test_turn_from_str_round_trips_debug() {
+        for turn in Turn::get_all_turns() {
+            assert_eq!(format!("{:?}", turn).parse::<Turn>(), Ok(turn));
+        }
+    }
+
+    #[test]
+    fn test_display_turn() {
+        assert_eq!(Turn::new(Face::BL, false).to_string(), "BL");
+        assert_eq!(Turn::new(Face::R, true).to_string(), "R'");
+        for turn in Turn::get_all_turns() {
+            assert_eq!(turn.to_string(), format!("{:?}", turn));
+            assert_eq!(turn.to_string().parse::<Turn>(), Ok(turn));
+        }
+        assert_eq!(format!("{:?}", Turn::parse_sequence("U BR' D").unwrap()), "[U, BR', D]");
+    }
+
+    #[test]
+    fn test_iter_all_matches_allowed_turns_for_all_faces() {
+        let turns: Vec<Turn> = Turn::iter_all().collect();
+        assert_eq!(turns, Turn::get_allowed_turns_for_faces(&Face::get_all_faces()));
+        assert_eq!(Turn::iter_all().len(), 16);
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        let expected = vec![Turn::new(Face::U, false), Turn::new(Face::R, true), Turn::new(Face::BL, false)];
+        assert_eq!(Turn::parse_sequence(" U  R'\tBL "), Ok(expected));
+        assert_eq!(Turn::parse_sequence(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_simplify_sequence() {
+        let simplify = |sequence| Turn::simplify_sequence(&Turn::parse_sequence(sequence).unwrap());
+        assert_eq!(simplify("U R R"), Turn::parse_sequence("U R'").unwrap());
+        assert_eq!(simplify("U R R' U'"), []);
+        assert_eq!(simplify("U D U BL"), Turn::parse_sequence("U' D BL").unwrap());
+        assert_eq!(simplify("R F U U U F' L"), Turn::parse_sequence("R L").unwrap());
+        assert_eq!(simplify("U F D"), Turn::parse_sequence("U F D").unwrap());
+    }
+
+    #[test]
+    fn test_inverse() {
+        assert_eq!(Turn::new(Face::R, false).inverse(), Turn::new(Face::R, true));
+        assert_eq!(Turn::new(Face::BL, true).inverse(), Turn::new(Face::BL, false));
+    }
+
+    #[test]
+    fn test_format_sequence() {
+        assert_eq!(Turn::format_sequence(&Turn::parse_sequence("U R' BL").unwrap()), "U R' BL");
+        assert_eq!(Turn::format_sequence(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_sequence_reports_bad_token() {
+        assert_eq!(Turn::parse_sequence("U R2 BL"), Err(ParseTurnError { token: String::from("R2") }));
+        assert_eq!(Turn::parse_sequence("U X'").unwrap_err().to_string(), "unrecognised turn 'X''");
+    }
+
     #[test]
     fn test_face_to_and_from_byte() {
         let mut seen_bytes = Vec::new();
@@ -483,4 +1357,91 @@ mod tests {
         assert_eq!(RAW_TURN_D.get_effect(TurnEffectType::UpCentre).as_ref(), &RAW_TURN_D.up_centres);
         assert_eq!(RAW_TURN_D.get_effect(TurnEffectType::TripleCentre).as_ref(), &RAW_TURN_D.triple_centres);
     }
+
+    #[test]
+    fn test_sticker_initial_state() {
+        let stickers = StickerState::get_initial();
+        assert_eq!(stickers.corner_up_good[0], U);
+        assert_eq!(StickerState::create_from_raw_state(&RawState::solved()).edge_down, stickers.edge_down);
+    }
+
+    #[test]
+    fn test_get_sticker_names() {
+        let piece_types = [(PieceType::Corner, 24), (PieceType::Edge, 24), (PieceType::UpCentre, 12), (PieceType::DownCentre, 12)];
+        for (piece_type, expected_len) in piece_types {
+            let names = get_sticker_names(piece_type);
+            assert_eq!(names.len(), expected_len);
+            for (index, name) in names.iter().enumerate() {
+                assert_eq!(get_piece_for_sticker(name), Some((piece_type, index % piece_type.get_num_pieces())));
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_piece_for_sticker() {
+        assert_eq!(get_piece_for_sticker("corn-UF-F"), Some((PieceType::Corner, 2)));
+        assert_eq!(get_piece_for_sticker("corn-DL-L"), Some((PieceType::Corner, 5)));
+        assert_eq!(get_piece_for_sticker("edge-FD-D"), Some((PieceType::Edge, 11)));
+        assert_eq!(get_piece_for_sticker("cent-BLF"), Some((PieceType::UpCentre, 4)));
+        assert_eq!(get_piece_for_sticker("cent-DB"), Some((PieceType::DownCentre, 11)));
+        assert_eq!(get_piece_for_sticker("not-a-sticker"), None);
+    }
+
+    #[test]
+    fn test_get_sticker_position_face() {
+        assert_eq!(get_sticker_position_face("corn-UF-F"), F);
+        assert_eq!(get_sticker_position_face("edge-BRD-D"), D);
+        assert_eq!(get_sticker_position_face("cent-BLBR"), BL);
+        assert_eq!(get_sticker_position_face("cent-LD"), L);
+    }
+
+    #[test_case(Face::U, Face::U, true ; "same face twice")]
+    #[test_case(Face::U, Face::D, false ; "primary then secondary")]
+    #[test_case(Face::D, Face::U, true ; "secondary then primary")]
+    #[test_case(Face::F, Face::BL, false ; "different axes")]
+    #[test_case(Face::L, Face::BR, true ; "secondary then primary on the BR axis")]
+    #[test_case(Face::BR, Face::L, false ; "primary then secondary on the BR axis")]
+    fn test_is_redundant_turn(prev_face: Face, curr_face: Face, expected: bool) {
+        for (prev_invert, curr_invert) in [(false, false), (false, true), (true, false), (true, true)] {
+            let prev_turn = Turn::new(prev_face, prev_invert);
+            let curr_turn = Turn::new(curr_face, curr_invert);
+            assert_eq!(is_redundant_turn(Some(&prev_turn), &curr_turn), expected);
+        }
+    }
+
+    #[test]
+    fn test_get_random_turns() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let turns = get_random_turns(30, &mut StdRng::seed_from_u64(5));
+        assert_eq!(turns.len(), 30);
+        assert!(turns.windows(2).all(|pair| !is_redundant_turn(Some(&pair[0]), &pair[1])));
+        assert_eq!(turns, get_random_turns(30, &mut StdRng::seed_from_u64(5)));
+    }
+
+    #[test]
+    fn test_first_turn_is_never_redundant() {
+        for turn in Turn::get_all_turns().iter() {
+            assert!(!is_redundant_turn(None, turn));
+        }
+    }
+
+    #[test]
+    fn test_is_redundant_turn_for_all_face_pairs() {
+        // Written out by hand from the axes (U, D), (F, B), (BL, R) and (BR, L): each face repeated, plus each axis
+        // turned secondary face first
+        let redundant_pairs = [
+            (Face::U, Face::U), (Face::F, Face::F), (Face::BL, Face::BL), (Face::BR, Face::BR),
+            (Face::L, Face::L), (Face::R, Face::R), (Face::B, Face::B), (Face::D, Face::D),
+            (Face::D, Face::U), (Face::B, Face::F), (Face::R, Face::BL), (Face::L, Face::BR),
+        ];
+        for prev_face in Face::get_all_faces() {
+            for curr_face in Face::get_all_faces() {
+                let expected = redundant_pairs.contains(&(prev_face, curr_face));
+                let redundant = is_redundant_turn(Some(&Turn::new(prev_face, false)), &Turn::new(curr_face, false));
+                assert_eq!(redundant, expected, "{:?} then {:?}", prev_face, curr_face);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -1,14 +1,38 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
 use std::fs::File;
-use std::io::{BufWriter, Write, BufReader, Read};
-
-use crate::coordinates::CoordinateType;
+use std::io::{self, Write, BufRead};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufWriter, BufReader};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use log::debug;
+#[cfg(not(target_arch = "wasm32"))]
+use log::info;
+
+use crate::coordinates::{CoordinateType, NUM_COORDINATES};
 use crate::movedefs::{RawTurn, Face, TurnEffectType, Turn, NUM_FACES};
-use crate::state::{apply_raw_permutation, apply_full_corner};
+use crate::state::{apply_raw_permutation, apply_full_corner, CoordState};
+#[cfg(feature = "serde-tables")]
+use crate::serdetables;
 
 
+#[cfg(not(target_arch = "wasm32"))]
 const MOVE_TABLE_FILE: &str = "./movetables.dat";
 
+/// The name of the move table file, for saving it somewhere other than the working directory.
+pub const MOVE_TABLE_FILE_NAME: &str = "movetables.dat";
+
+/// The start of a move table file in the serde format. See `serdetables` for the rest of the layout.
+pub const SERDE_MOVE_TABLE_MAGIC: &[u8] = b"FTOM";
+
+// Progress is reported each time another 1/PROGRESS_STEPS of a table has been populated
+const PROGRESS_STEPS: usize = 20;
+
 
 pub trait ApplyMove {
     fn apply_move_to_coord(&self, coord: u32, coord_type: CoordinateType, turn: &Turn) -> u32;
@@ -18,10 +42,44 @@ pub trait SubTables {
     fn get_sub_table<'a, T: ApplyMove>(&'a self, coord_type: &CoordinateType) -> &'a T;
 }
 
+#[derive(PartialEq)]
 pub struct MoveTables {
     pub tables: HashMap<CoordinateType, MoveTable>,
 }
 
+/// How far through populating a move table we are, with a rough estimate of how long is left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopulationProgress {
+    pub coord_type: CoordinateType,
+    pub completed: usize,
+    pub total: usize,
+    pub elapsed: Duration,
+    pub estimated_remaining: Duration,
+}
+
+impl PopulationProgress {
+    /// Assumes the remaining coordinates will take as long on average as the ones done so far.
+    pub fn new(coord_type: CoordinateType, completed: usize, total: usize, elapsed: Duration) -> Self {
+        let estimated_remaining = if completed == 0 {
+            Duration::ZERO
+        } else {
+            elapsed.mul_f64((total - completed) as f64 / completed as f64)
+        };
+        Self {
+            coord_type,
+            completed,
+            total,
+            elapsed,
+            estimated_remaining,
+        }
+    }
+
+    pub fn get_fraction_complete(&self) -> f64 {
+        self.completed as f64 / self.total as f64
+    }
+}
+
+#[derive(PartialEq)]
 pub struct MoveTable {
     initialised: bool,
     populated: bool,
@@ -30,25 +88,43 @@ pub struct MoveTable {
 
     table: [Vec<u32>; NUM_FACES],
     inverse_table: [Vec<u32>; NUM_FACES],
+
+    // For a lazy table, the clockwise turn of every face for each coordinate, worked out the first time the
+    // coordinate is turned. This is empty for a table that is populated up front.
+    lazy_rows: Vec<OnceLock<[u32; NUM_FACES]>>,
 }
 
 impl MoveTables {
-    pub fn try_load_or_generate() -> Self {
-        match File::open(MOVE_TABLE_FILE) {
-            Ok(file) => Self::load(file),
-            _ => {
-                let move_tables = Self::generate();
-                move_tables.save();
-                move_tables
-            }
+    /// Whether there is a table for every coordinate type. A move table file that was cut short when it was
+    /// saved will load without some of them.
+    pub fn all_tables_generated(&self) -> bool {
+        self.missing_tables().is_empty()
+    }
+
+    pub fn missing_tables(&self) -> Vec<CoordinateType> {
+        CoordinateType::iter()
+            .filter(|coord_type| !self.tables.contains_key(coord_type))
+            .collect()
+    }
+
+    /// Lazy tables for every coordinate type, from `MoveTable::new_lazy`, for programs that only turn a small part
+    /// of each table, or don't use every coordinate type.
+    pub fn new_lazy() -> Self {
+        Self {
+            tables: CoordinateType::iter().map(|coord_type| (coord_type, MoveTable::new_lazy(coord_type))).collect(),
         }
     }
 
-    fn generate() -> Self {
+    /// Generates every table from scratch without reading or writing the saved tables.
+    pub fn generate(on_progress: &mut impl FnMut(&PopulationProgress)) -> Self {
         let mut tables: HashMap<CoordinateType, MoveTable> = HashMap::new();
 
         for coord in CoordinateType::iter() {
-            let move_table = MoveTable::new(coord);
+            let start_time = Instant::now();
+            let mut move_table = MoveTable::empty(coord);
+            move_table.init();
+            move_table.populate_with_progress(on_progress);
+            debug!("Generated {:?} move table in {} seconds", coord, start_time.elapsed().as_secs_f64());
             tables.insert(coord, move_table);
         }
 
@@ -57,42 +133,183 @@ impl MoveTables {
         }
     }
 
-    fn save(&self) {
-        let file = File::create(MOVE_TABLE_FILE).expect("Should have created the file");
-        let mut writer = BufWriter::new(file);
+    /// The tables in the same format as `movetables.dat`, for keeping them somewhere other than a file, such as
+    /// in a web page's storage.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).expect("writing to a Vec should not fail");
+        bytes
+    }
 
-        for (coord, table) in self.tables.iter() {
-            writer.write_all(&[0,0,0,coord.to_byte()])
-                .expect("Coordinate type should be written");
+    /// Reads tables in the format written by `to_bytes` or `save_to`, such as a `movetables.dat` that a web page
+    /// has fetched, or by `to_serde_bytes`. A damaged table is reported as an error.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::read_from(bytes)
+    }
 
-            table.save(&mut writer);
+    /// The tables in the serde format described in `serdetables`, which `from_bytes` and `load_from` also read.
+    #[cfg(feature = "serde-tables")]
+    pub fn to_serde_bytes(&self) -> Vec<u8> {
+        serdetables::to_bytes(SERDE_MOVE_TABLE_MAGIC, self)
+    }
 
-            writer.write_all(&[0,0,0,0])
-                .expect("End of table should be written");
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        for coord in CoordinateType::iter() {
+            if let Some(table) = self.tables.get(&coord) {
+                writer.write_all(&[0,0,0,coord.to_byte()])?;
+                table.write_to(writer)?;
+                // End of table
+                writer.write_all(&[0,0,0,0])?;
+            }
         }
 
+        // End of file
         writer.write_all(&[0,0,0,0])
-        .expect("End of move_table file should be written");
     }
 
-    fn load(file: File) -> Self {
-        let mut reader = BufReader::new(file);
+    fn read_from(mut reader: impl BufRead) -> io::Result<Self> {
+        match reader.fill_buf()?.starts_with(SERDE_MOVE_TABLE_MAGIC) {
+            true => Self::read_serde_from(reader),
+            false => Self::read_tables_from(reader),
+        }
+    }
+
+    #[cfg(feature = "serde-tables")]
+    fn read_serde_from(reader: impl BufRead) -> io::Result<Self> {
+        serdetables::read_from(SERDE_MOVE_TABLE_MAGIC, reader)
+    }
+
+    #[cfg(not(feature = "serde-tables"))]
+    fn read_serde_from(_reader: impl BufRead) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "the move tables were saved in the serde format, which needs the serde-tables feature"))
+    }
+
+    fn read_tables_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut reader = TableReader::new(reader);
 
         let mut result = Self { tables: HashMap::new() };
 
         loop {
-            let coord_byte = read_next_num(&mut reader) as u8;
+            let coord_byte = reader.try_read_next_num()? as u8;
 
             if coord_byte == 0 {
                 break
             }
-            let coord = CoordinateType::from_byte(coord_byte);
+            let coord = CoordinateType::iter()
+                .find(|coord| coord.to_byte() == coord_byte)
+                .ok_or_else(|| get_invalid_data_error(&reader, "unrecognised coordinate type"))?;
 
-            let table = MoveTable::read_from_buffer(&mut reader, coord);
+            let table = MoveTable::try_read_from_buffer(&mut reader, coord)?;
             result.tables.insert(coord, table);
         }
 
-        result
+        Ok(result)
+    }
+}
+
+/// The clockwise turns of every coordinate type, as built by `MoveTables::build_lookup_table`. This is an
+/// experiment in turning a `CoordState` without the `HashMap` lookup that `MoveTables` makes for each coordinate,
+/// and the search doesn't use it. Compare the two with `cargo bench --bench benchmarks -- apply`.
+///
+/// There is a table for each coordinate type, in the order of `CoordinateType::iter`, holding only the coordinates
+/// of that type. The entry for turning coordinate `coord` with the face at `face_index` is at
+///
+/// ```text
+/// coord * NUM_FACES + face_index
+/// ```
+///
+/// so every turn of a coordinate is in the same 32 bytes. Anticlockwise turns are made as two clockwise ones, which
+/// keeps the tables to about 37 MB rather than twice that, at the cost of a second lookup.
+pub struct FlatLookupTable {
+    tables: [Box<[u32]>; NUM_COORDINATES],
+}
+
+impl FlatLookupTable {
+    fn get(&self, coord: u32, face_index: usize, coord_index: usize) -> u32 {
+        self.tables[coord_index][coord as usize * NUM_FACES + face_index]
+    }
+
+    /// Turns every coordinate of `state`, giving the same result as `CoordState::apply`.
+    pub fn apply(&self, state: &mut CoordState, turn: &Turn) {
+        let face_index = turn.get_face().to_index();
+        for _ in 0..if turn.is_inverse() { 2 } else { 1 } {
+            state.corners = self.get(state.corners, face_index, 0);
+            state.edges_within_faces = self.get(state.edges_within_faces, face_index, 1);
+            state.edges_across_faces = self.get(state.edges_across_faces, face_index, 2);
+            state.up_centres = self.get(state.up_centres, face_index, 3);
+            state.down_centres = self.get(state.down_centres, face_index, 4);
+        }
+    }
+}
+
+impl MoveTables {
+    /// Copies the clockwise turns of every table into a `FlatLookupTable`. Every table has to be loaded.
+    pub fn build_lookup_table(&self) -> FlatLookupTable {
+        let coord_types: Vec<CoordinateType> = CoordinateType::iter().collect();
+        let tables = std::array::from_fn(|coord_index| {
+            let coord_type = coord_types[coord_index];
+            let move_table = &self.tables[&coord_type];
+            let mut table = vec![0; coord_type.get_size() * NUM_FACES].into_boxed_slice();
+            for face in Face::get_all_faces() {
+                let turn = Turn::new(face, false);
+                for coord in 0..coord_type.get_size() {
+                    table[coord * NUM_FACES + face.to_index()] = move_table.apply_move_to_coord(coord as u32, coord_type, &turn);
+                }
+            }
+            table
+        });
+        FlatLookupTable { tables }
+    }
+}
+
+// Browsers have no filesystem, so web pages fetch the table files themselves and use `from_bytes`
+#[cfg(not(target_arch = "wasm32"))]
+impl MoveTables {
+    pub fn try_load_or_generate() -> Self {
+        Self::try_load_or_generate_with_progress(&mut |_| {})
+    }
+
+    /// As `try_load_or_generate`, but reports progress through `on_progress` if the tables have to be generated.
+    pub fn try_load_or_generate_with_progress(on_progress: &mut impl FnMut(&PopulationProgress)) -> Self {
+        match File::open(MOVE_TABLE_FILE) {
+            Ok(file) => {
+                info!("Loading move tables from {}", MOVE_TABLE_FILE);
+                Self::load(file)
+            }
+            _ => {
+                info!("Generating move tables and saving them to {}", MOVE_TABLE_FILE);
+                let move_tables = Self::generate(on_progress);
+                move_tables.save();
+                move_tables
+            }
+        }
+    }
+
+    fn save(&self) {
+        self.save_to(Path::new(MOVE_TABLE_FILE)).expect("Should have saved the move tables");
+    }
+
+    fn load(file: File) -> Self {
+        Self::read_from(BufReader::new(file)).expect("Should have read the move tables")
+    }
+
+    /// Saves the tables in the same format as `movetables.dat`.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Loads tables saved by `save_to` or `save_serde_to`. Unlike the file loaded by `try_load_or_generate`, a
+    /// damaged file is reported as an error rather than a panic.
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the tables in the serde format described in `serdetables`.
+    #[cfg(feature = "serde-tables")]
+    pub fn save_serde_to(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_serde_bytes())
     }
 }
 
@@ -105,15 +322,17 @@ impl ApplyMove for MoveTables {
 
 impl MoveTable {
     fn empty(coord_type: CoordinateType) -> Self {
-        const empty_vec: Vec<u32> = Vec::new();
+        const EMPTY_VEC: Vec<u32> = Vec::new();
         Self {
             initialised: false,
             populated: false,
 
             coord_type,
 
-            table: [empty_vec; NUM_FACES],
-            inverse_table: [empty_vec; NUM_FACES],
+            table: [EMPTY_VEC; NUM_FACES],
+            inverse_table: [EMPTY_VEC; NUM_FACES],
+
+            lazy_rows: Vec::new(),
         }
     }
 
@@ -124,6 +343,37 @@ impl MoveTable {
         move_table
     }
 
+    /// A table that works out the turns of each coordinate the first time it is turned, rather than all of them
+    /// up front. Creating it is almost instant, which suits programs that only turn a few coordinates, but every
+    /// turn has to check whether its coordinate has been worked out yet, and a table that ends up being used for
+    /// most coordinates takes longer overall than populating it would have.
+    pub fn new_lazy(coord_type: CoordinateType) -> Self {
+        let mut move_table = Self::empty(coord_type);
+        move_table.lazy_rows = (0..coord_type.get_size()).map(|_| OnceLock::new()).collect();
+        move_table
+    }
+
+    fn get_lazy_row(&self, coord: u32) -> &[u32; NUM_FACES] {
+        self.lazy_rows[coord as usize].get_or_init(|| {
+            let state = self.coord_type.coord_to_state(coord);
+            Face::get_all_faces().map(|face| {
+                let mut turned = state.clone();
+                apply_turn_to_state(&mut turned, RawTurn::get(face), self.coord_type.get_turn_effect_type());
+                self.coord_type.state_to_coord(&turned)
+            })
+        })
+    }
+
+    /// The clockwise turn of `face` for every coordinate, which for a lazy table means working out any
+    /// coordinates that haven't been turned yet.
+    fn get_face_table(&self, face_index: usize) -> Cow<'_, [u32]> {
+        if self.lazy_rows.is_empty() {
+            Cow::Borrowed(&self.table[face_index])
+        } else {
+            Cow::Owned((0..self.lazy_rows.len() as u32).map(|coord| self.get_lazy_row(coord)[face_index]).collect())
+        }
+    }
+
 
     pub fn init(&mut self) {
         for face in Face::get_all_faces() {
@@ -134,13 +384,28 @@ impl MoveTable {
     }
 
     pub fn populate(&mut self) {
+        self.populate_with_progress(&mut |_| {});
+    }
 
+    /// Populates the table, calling `on_progress` each time another 5% of the coordinates have been done.
+    pub fn populate_with_progress(&mut self, on_progress: &mut impl FnMut(&PopulationProgress)) {
         let coord_type = self.coord_type;
+        let total = coord_type.get_size();
+        let progress_interval = total.div_ceil(PROGRESS_STEPS);
+        let start_time = Instant::now();
 
         for start_coord in 0..(coord_type.get_size() as u32) {
-            let mut state = coord_type.coord_to_state(start_coord);
+            // Working out the state is the slow part, so it is skipped for coordinates that earlier coordinates'
+            // cycles have already filled in for every face
+            let is_filled = self.table.each_ref().map(|table| table[start_coord as usize] < u32::MAX);
+            let mut state = match is_filled.contains(&false) {
+                true => coord_type.coord_to_state(start_coord),
+                false => Vec::new(),
+            };
+
+            // Three turns of a face bring the state back to where it started, so it is ready for the next face
             for face in Face::get_all_faces() {
-                if self.table[face.to_index()][start_coord as usize] < u32::MAX {
+                if is_filled[face.to_index()] {
                     continue;
                 }
 
@@ -159,62 +424,183 @@ impl MoveTable {
 
                 add_cycle_to_table(table, inv_table, &cycle);
             }
+
+            let completed = start_coord as usize + 1;
+            if completed.is_multiple_of(progress_interval) || completed == total {
+                on_progress(&PopulationProgress::new(coord_type, completed, total, start_time.elapsed()));
+            }
         }
         self.populated = true;
     }
 
-    pub fn save(&self, writer: &mut BufWriter<File>) {
+    /// Splits the permutation a clockwise turn of `face` makes on the coordinates into cycles. Each cycle starts at
+    /// its smallest coordinate and the cycles are in order of their first coordinate. Coordinates the turn
+    /// doesn't change are cycles of length one, so every coordinate is in exactly one cycle.
+    pub fn cycle_decomposition_for_face(&self, face: Face) -> Vec<Vec<u32>> {
+        let table = self.get_face_table(face.to_index());
+        let mut visited = vec![false; table.len()];
+        let mut cycles = Vec::new();
+
+        for start in 0..table.len() {
+            if visited[start] {
+                continue;
+            }
+            let mut cycle = Vec::new();
+            let mut coord = start;
+            while !visited[coord] {
+                visited[coord] = true;
+                cycle.push(coord as u32);
+                coord = table[coord] as usize;
+            }
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    pub fn save(&self, writer: &mut impl Write) {
+        self.write_to(writer).expect("Move table should be written");
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
         for i in 0..self.table.len() {
             let face = Face::from_index(i);
-            let values = &self.table[i];
-            writer.write_all(&[0,0,0,face.to_byte()])
-                .expect("Face should be written");
+            let values = self.get_face_table(i);
+            writer.write_all(&[0,0,0,face.to_byte()])?;
 
             for value in values.iter() {
-                writer.write_all(&value.to_be_bytes())
-                    .expect("Value should be written");
+                writer.write_all(&value.to_be_bytes())?;
             }
         }
+        Ok(())
+    }
+
+    pub fn read_from_buffer<R: BufRead>(reader: &mut TableReader<R>, coord_type: CoordinateType) -> Self {
+        match Self::try_read_from_buffer(reader, coord_type) {
+            Ok(table) => table,
+            Err(error) => panic!("Should have read the move table: {}", error),
+        }
     }
 
-    pub fn read_from_buffer(reader: &mut BufReader<File>, coord_type: CoordinateType) -> Self {
+    /// Reads a table written by `write_to`, checking that every face and coordinate in it is valid.
+    pub fn try_read_from_buffer<R: BufRead>(reader: &mut TableReader<R>, coord_type: CoordinateType) -> io::Result<Self> {
         let mut result = Self::empty(coord_type);
         result.init();
 
         let num_values = coord_type.get_size();
 
         loop {
-            let face_byte = read_next_num(reader) as u8;
+            let face_byte = reader.try_read_next_num()? as u8;
             if face_byte == 0 {
                 break
             }
-            let face = Face::from_byte(face_byte);
+            let face = Face::get_all_faces().into_iter()
+                .find(|face| face.to_byte() == face_byte)
+                .ok_or_else(|| get_invalid_data_error(reader, "unrecognised face"))?;
 
             let table = &mut result.table[face.to_index()];
             let inv_table = &mut result.inverse_table[face.to_index()];
 
-            for coord in 0..num_values {
-                let value = read_next_num(reader);
-                table[coord] = value;
+            for (coord, entry) in table.iter_mut().enumerate().take(num_values) {
+                let value = reader.try_read_next_num()?;
+                if value as usize >= num_values {
+                    return Err(get_invalid_data_error(reader, "coordinate out of range"));
+                }
+                *entry = value;
                 inv_table[value as usize] = coord as u32;
             }
         }
         result.populated = true;
-        result
+        Ok(result)
     }
 }
 
 impl ApplyMove for MoveTable {
     fn apply_move_to_coord(&self, coord: u32, _coord_type: CoordinateType, turn: &Turn) -> u32 {
-        let table = if turn.invert {
-            &self.inverse_table[turn.face.to_index()]
+        if !self.lazy_rows.is_empty() {
+            let face_index = turn.get_face().to_index();
+            let next = self.get_lazy_row(coord)[face_index];
+            // Three clockwise turns get back to the start, so two of them undo one
+            return match turn.is_inverse() {
+                true => self.get_lazy_row(next)[face_index],
+                false => next,
+            };
+        }
+        let table = if turn.is_inverse() {
+            &self.inverse_table[turn.get_face().to_index()]
         } else {
-            &self.table[turn.face.to_index()]
+            &self.table[turn.get_face().to_index()]
         };
         table[coord as usize]
     }
 }
 
+// Only the table for each face is saved, as the inverse tables can be rebuilt from them while they are checked
+#[cfg(feature = "serde-tables")]
+#[derive(serde::Serialize)]
+struct MoveTableRef<'a> {
+    coord_type: CoordinateType,
+    table: [Cow<'a, [u32]>; NUM_FACES],
+}
+
+#[cfg(feature = "serde-tables")]
+#[derive(serde::Deserialize)]
+struct MoveTableData {
+    coord_type: CoordinateType,
+    table: [Vec<u32>; NUM_FACES],
+}
+
+#[cfg(feature = "serde-tables")]
+impl serde::Serialize for MoveTable {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let table = std::array::from_fn(|face_index| self.get_face_table(face_index));
+        MoveTableRef { coord_type: self.coord_type, table }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde-tables")]
+impl<'de> serde::Deserialize<'de> for MoveTable {
+    /// Checks that there is an entry for every coordinate and face, and that each is a valid coordinate.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let MoveTableData { coord_type, table } = MoveTableData::deserialize(deserializer)?;
+        let num_values = coord_type.get_size();
+        let mut result = Self::empty(coord_type);
+        result.init();
+        for (face_index, face_table) in table.into_iter().enumerate() {
+            if face_table.len() != num_values {
+                return Err(D::Error::invalid_length(face_table.len(), &"one entry for every coordinate"));
+            }
+            for (coord, value) in face_table.iter().enumerate() {
+                if *value as usize >= num_values {
+                    return Err(D::Error::custom(format!("coordinate {} out of range for {:?}", value, coord_type)));
+                }
+                result.inverse_table[face_index][*value as usize] = coord as u32;
+            }
+            result.table[face_index] = face_table;
+        }
+        result.populated = true;
+        Ok(result)
+    }
+}
+
+#[cfg(feature = "serde-tables")]
+impl serde::Serialize for MoveTables {
+    /// Writes the tables in the order of `CoordinateType::iter`, so the same tables always give the same bytes.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tables: Vec<&MoveTable> = CoordinateType::iter().filter_map(|coord_type| self.tables.get(&coord_type)).collect();
+        tables.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde-tables")]
+impl<'de> serde::Deserialize<'de> for MoveTables {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tables = Vec::<MoveTable>::deserialize(deserializer)?;
+        Ok(Self { tables: tables.into_iter().map(|table| (table.coord_type, table)).collect() })
+    }
+}
+
 fn add_cycle_to_table(table: &mut [u32], inv_table: &mut [u32], cycle: &[u32]) {
     table[cycle[0] as usize] = cycle[1];
     table[cycle[1] as usize] = cycle[2];
@@ -224,7 +610,7 @@ fn add_cycle_to_table(table: &mut [u32], inv_table: &mut [u32], cycle: &[u32]) {
     inv_table[cycle[1] as usize] = cycle[0];
 }
 
-fn apply_turn_to_state(state: &mut [u8], turn: &RawTurn, effect_type: TurnEffectType) {
+pub(crate) fn apply_turn_to_state(state: &mut [u8], turn: &RawTurn, effect_type: TurnEffectType) {
     match effect_type {
         TurnEffectType::Corner => {
             let perm_effect = turn.get_effect(TurnEffectType::CornerPermutation);
@@ -238,11 +624,56 @@ fn apply_turn_to_state(state: &mut [u8], turn: &RawTurn, effect_type: TurnEffect
     }
 }
 
-fn read_next_num(buf: &mut BufReader<File>) -> u32 {
-    let mut data = [0; 4];
-    buf.read_exact(&mut data)
-        .expect("Should have read the data from the buffer");
-    u32::from_be_bytes(data)
+/// Reads the big-endian 32-bit numbers that make up a saved move table from any buffered source,
+/// keeping track of how far into the data it has got so that failures can report where they happened.
+pub struct TableReader<R: BufRead> {
+    reader: R,
+    offset: usize,
+}
+
+impl<R: BufRead> TableReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+        }
+    }
+
+    pub fn read_next_num(&mut self) -> u32 {
+        match self.try_read_next_num() {
+            Ok(value) => value,
+            Err(error) => panic!("Should have read the data from the buffer at byte offset {}: {}", self.offset, error),
+        }
+    }
+
+    pub fn try_read_next_num(&mut self) -> io::Result<u32> {
+        let mut data = [0; 4];
+        self.reader.read_exact(&mut data)?;
+        self.offset += data.len();
+        Ok(u32::from_be_bytes(data))
+    }
+
+    /// Fills the buffer with the next bytes, for data that isn't stored as 4 byte numbers.
+    pub fn try_read_bytes(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(buffer)?;
+        self.offset += buffer.len();
+        Ok(())
+    }
+
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Full move tables shared between tests, generated once on first use because generation is slow.
+pub(crate) fn get_invalid_data_error<R: BufRead>(reader: &TableReader<R>, reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} at byte offset {}", reason, reader.get_offset()))
+}
+
+#[cfg(test)]
+pub(crate) fn get_test_move_tables() -> &'static MoveTables {
+    static MOVE_TABLES: std::sync::OnceLock<MoveTables> = std::sync::OnceLock::new();
+    MOVE_TABLES.get_or_init(|| MoveTables::generate(&mut |_| {}))
 }
 
 
@@ -281,4 +712,267 @@ mod tests {
         let coord = move_table.apply_move_to_coord(end_coord, coord_type, &Turn::new(Face::F, true));
         assert_eq!(coord, start_coord);
     }
+
+    #[test]
+    fn test_generated_tables_are_unchanged() {
+        // An FNV-1a hash of the saved tables, so that changes to how they are generated can't change their contents
+        let hash = get_test_move_tables().to_bytes().iter()
+            .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+        assert_eq!(hash, 0x123db5591cdd952b, "{:#018x}", hash);
+    }
+
+    #[test]
+    fn test_flat_lookup_table_matches_move_tables() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+        use crate::movedefs::get_random_turns;
+        use crate::state::SOLVED_COORD_STATE;
+
+        assert_eq!(CoordinateType::iter().count(), NUM_COORDINATES);
+        let move_tables = get_test_move_tables();
+        let lookup_table = move_tables.build_lookup_table();
+        let mut state = SOLVED_COORD_STATE;
+        let mut flat_state = SOLVED_COORD_STATE;
+        for turn in get_random_turns(200, &mut StdRng::seed_from_u64(6)) {
+            state.apply(move_tables, &turn);
+            lookup_table.apply(&mut flat_state, &turn);
+            assert_eq!(flat_state, state);
+        }
+    }
+
+    #[test]
+    fn test_all_tables_generated() {
+        let move_tables = get_test_move_tables();
+        assert!(move_tables.all_tables_generated());
+        assert_eq!(move_tables.missing_tables(), vec![]);
+    }
+
+    #[test]
+    fn test_missing_tables() {
+        let mut move_tables = MoveTables { tables: HashMap::new() };
+        move_tables.tables.insert(CoordinateType::CornerState, MoveTable::new(CoordinateType::CornerState));
+
+        let mut expected: Vec<CoordinateType> = CoordinateType::iter().collect();
+        expected.retain(|coord_type| *coord_type != CoordinateType::CornerState);
+        assert!(!move_tables.all_tables_generated());
+        assert_eq!(move_tables.missing_tables(), expected);
+    }
+
+    #[test]
+    fn test_populate_reports_progress_every_five_percent() {
+        let coord_type = CoordinateType::CornerState;
+        let mut move_table = MoveTable::empty(coord_type);
+        move_table.init();
+
+        let mut reports = Vec::new();
+        move_table.populate_with_progress(&mut |progress| reports.push(progress.clone()));
+
+        assert_eq!(reports.len(), PROGRESS_STEPS);
+        assert_eq!(reports[0].completed, coord_type.get_size().div_ceil(PROGRESS_STEPS));
+        let last = reports.last().unwrap();
+        assert_eq!(last.completed, last.total);
+        assert_eq!(last.estimated_remaining, Duration::ZERO);
+        assert!(reports.windows(2).all(|pair| pair[0].elapsed <= pair[1].elapsed));
+        assert!(move_table == MoveTable::new(coord_type));
+    }
+
+    #[test]
+    fn test_populate_reports_progress_once_at_the_end() {
+        // The number of coordinates isn't a multiple of the number of progress steps
+        let coord_type = CoordinateType::EdgeAcrossFaces;
+        let mut move_table = MoveTable::empty(coord_type);
+        move_table.init();
+
+        let mut reports = Vec::new();
+        move_table.populate_with_progress(&mut |progress| reports.push(progress.completed));
+        assert_eq!(reports.len(), PROGRESS_STEPS);
+        assert_eq!(reports.iter().filter(|completed| **completed == coord_type.get_size()).count(), 1);
+    }
+
+    #[test]
+    fn test_population_progress_estimate() {
+        let progress = PopulationProgress::new(CoordinateType::EdgeInFace, 25, 100, Duration::from_secs(10));
+        assert_eq!(progress.estimated_remaining, Duration::from_secs(30));
+        assert_eq!(progress.get_fraction_complete(), 0.25);
+
+        let progress = PopulationProgress::new(CoordinateType::EdgeInFace, 0, 100, Duration::ZERO);
+        assert_eq!(progress.estimated_remaining, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_table_reader_reads_big_endian_numbers() {
+        let data: &[u8] = &[0,0,0,1, 0,0,1,0, 255,255,255,255];
+        let mut reader = TableReader::new(data);
+        assert_eq!(reader.read_next_num(), 1);
+        assert_eq!(reader.read_next_num(), 256);
+        assert_eq!(reader.get_offset(), 8);
+        assert_eq!(reader.read_next_num(), u32::MAX);
+        assert_eq!(reader.get_offset(), 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "byte offset 4")]
+    fn test_table_reader_reports_offset_of_truncated_data() {
+        let data: &[u8] = &[0,0,0,1, 0,0];
+        let mut reader = TableReader::new(data);
+        reader.read_next_num();
+        reader.read_next_num();
+    }
+
+    #[test]
+    fn test_read_move_table_from_memory() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+
+        let mut data: Vec<u8> = Vec::new();
+        move_table.save(&mut data);
+        data.extend_from_slice(&[0,0,0,0]);
+
+        let mut reader = TableReader::new(&data[..]);
+        let loaded = MoveTable::read_from_buffer(&mut reader, coord_type);
+        assert!(loaded == move_table);
+        assert_eq!(reader.get_offset(), data.len());
+    }
+
+    #[test]
+    fn test_save_to_and_load_from() {
+        let mut move_tables = MoveTables { tables: HashMap::new() };
+        move_tables.tables.insert(CoordinateType::CornerState, MoveTable::new(CoordinateType::CornerState));
+        let path = std::env::temp_dir().join(format!("fto-movetables-{}.dat", std::process::id()));
+
+        move_tables.save_to(&path).unwrap();
+        let loaded = MoveTables::load_from(&path);
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() - 10]).unwrap();
+        let truncated = MoveTables::load_from(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.unwrap() == move_tables);
+        assert_eq!(truncated.err().map(|error| error.kind()), Some(io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() {
+        let mut move_tables = MoveTables { tables: HashMap::new() };
+        move_tables.tables.insert(CoordinateType::CornerState, MoveTable::new(CoordinateType::CornerState));
+        move_tables.tables.insert(CoordinateType::EdgeInFace, MoveTable::new(CoordinateType::EdgeInFace));
+
+        let bytes = move_tables.to_bytes();
+        assert!(MoveTables::from_bytes(&bytes).unwrap() == move_tables);
+        // The tables are written in a fixed order, so the same tables always give the same bytes
+        assert_eq!(bytes, MoveTables::from_bytes(&bytes).unwrap().to_bytes());
+        let error = MoveTables::from_bytes(&bytes[..bytes.len() - 10]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[cfg(feature = "serde-tables")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut move_tables = MoveTables { tables: HashMap::new() };
+        move_tables.tables.insert(CoordinateType::CornerState, MoveTable::new(CoordinateType::CornerState));
+        move_tables.tables.insert(CoordinateType::EdgeInFace, MoveTable::new(CoordinateType::EdgeInFace));
+
+        let bytes = move_tables.to_serde_bytes();
+        assert_eq!(&bytes[..5], b"FTOM\x01");
+        let loaded = MoveTables::from_bytes(&bytes).unwrap();
+        assert!(loaded == move_tables);
+        assert_eq!(bytes, loaded.to_serde_bytes());
+        // Small numbers take fewer bytes in postcard, so the file is smaller than the original format
+        assert!(bytes.len() < move_tables.to_bytes().len());
+
+        let path = std::env::temp_dir().join(format!("fto-movetables-serde-{}.dat", std::process::id()));
+        move_tables.save_serde_to(&path).unwrap();
+        let from_file = MoveTables::load_from(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(from_file.unwrap() == move_tables);
+    }
+
+    #[cfg(feature = "serde-tables")]
+    #[test]
+    fn test_serde_rejects_out_of_range_coordinates() {
+        let mut move_table = MoveTable::new(CoordinateType::CornerState);
+        move_table.table[2][5] = CoordinateType::CornerState.get_size() as u32;
+        let move_tables = MoveTables { tables: HashMap::from([(CoordinateType::CornerState, move_table)]) };
+
+        let error = MoveTables::from_bytes(&move_tables.to_serde_bytes()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(not(feature = "serde-tables"))]
+    #[test]
+    fn test_serde_format_needs_feature() {
+        let error = MoveTables::from_bytes(b"FTOM\x01\x00").err().unwrap();
+        assert!(error.to_string().contains("serde-tables feature"));
+    }
+
+    #[test]
+    fn test_try_read_rejects_out_of_range_coordinates() {
+        let data: &[u8] = &[0,0,0,b'U', 0,1,0,0];
+        let error = MoveTable::try_read_from_buffer(&mut TableReader::new(data), CoordinateType::CornerState).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "coordinate out of range at byte offset 8");
+    }
+
+    #[test]
+    fn test_lazy_table_matches_populated_table() {
+        let move_tables = get_test_move_tables();
+        for coord_type in CoordinateType::iter() {
+            let lazy_table = MoveTable::new_lazy(coord_type);
+            let size = coord_type.get_size() as u32;
+            for coord in (0..size).step_by(997) {
+                for turn in Turn::iter_all() {
+                    assert_eq!(lazy_table.apply_move_to_coord(coord, coord_type, &turn),
+                        move_tables.apply_move_to_coord(coord, coord_type, &turn), "{:?} {} {:?}", coord_type, coord, turn);
+                }
+            }
+            // Only the coordinates that were turned, and the ones their clockwise turns led to, are worked out
+            let num_rows = lazy_table.lazy_rows.iter().filter(|row| row.get().is_some()).count();
+            assert!(num_rows <= 9 * size.div_ceil(997) as usize, "{:?} {}", coord_type, num_rows);
+        }
+    }
+
+    #[test]
+    fn test_lazy_table_writes_the_whole_table() {
+        let coord_type = CoordinateType::CornerState;
+        let mut lazy_data = vec![];
+        MoveTable::new_lazy(coord_type).write_to(&mut lazy_data).unwrap();
+        let mut data = vec![];
+        get_test_move_tables().tables[&coord_type].write_to(&mut data).unwrap();
+        assert!(lazy_data == data);
+        assert_eq!(MoveTable::new_lazy(coord_type).cycle_decomposition_for_face(Face::F).len(), coord_type.get_size() / 3);
+        assert!(MoveTables::new_lazy().all_tables_generated());
+    }
+
+    #[test]
+    fn test_cycle_decomposition_for_face() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+
+        for face in Face::get_all_faces() {
+            let cycles = move_table.cycle_decomposition_for_face(face);
+            // Every turn moves three distinct corners, so no corner state is left unchanged
+            assert!(cycles.iter().all(|cycle| cycle.len() == 3));
+            assert_eq!(cycles.iter().map(|cycle| cycle.len()).sum::<usize>(), coord_type.get_size());
+            for cycle in cycles.iter() {
+                let turn = Turn::new(face, false);
+                for (i, coord) in cycle.iter().enumerate() {
+                    assert_eq!(move_table.apply_move_to_coord(*coord, coord_type, &turn), cycle[(i + 1) % cycle.len()]);
+                }
+            }
+        }
+        assert_eq!(move_table.cycle_decomposition_for_face(Face::F)[0], vec![0, 3327, move_table.apply_move_to_coord(3327, coord_type, &Turn::new(Face::F, false))]);
+    }
+
+    #[test]
+    fn test_cycle_decomposition_includes_fixed_coordinates() {
+        let coord_type = CoordinateType::EdgeAcrossFaces;
+        let move_table = MoveTable::new(coord_type);
+
+        let cycles = move_table.cycle_decomposition_for_face(Face::U);
+        assert!(cycles.iter().all(|cycle| cycle.len() == 1 || cycle.len() == 3));
+        assert!(cycles.iter().any(|cycle| cycle.len() == 1));
+        assert!(cycles.windows(2).all(|pair| pair[0][0] < pair[1][0]));
+        assert!(cycles.iter().all(|cycle| cycle.iter().all(|coord| *coord >= cycle[0])));
+        assert_eq!(cycles.iter().map(|cycle| cycle.len()).sum::<usize>(), coord_type.get_size());
+    }
 }
@@ -0,0 +1,144 @@
+/*
+Named pattern states, for drawing and for trying on a physical puzzle.
+
+Each pattern stores its state explicitly, along with an algorithm that reaches it from solved. The tests apply every
+algorithm and compare the result with the stored state, so the two can't drift apart. The centre arrays label each
+centre by the colour of its face, as in `RawState::solved`, so centres of the same colour are interchangeable.
+*/
+
+use crate::movedefs::Turn;
+use crate::state::RawState;
+
+
+pub struct Pattern {
+    /// The name to pick the pattern by, such as on the command line.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The turns that take the solved puzzle to the pattern.
+    pub algorithm: &'static str,
+    state: fn() -> RawState,
+}
+
+impl Pattern {
+    pub fn get_state(&self) -> RawState {
+        (self.state)()
+    }
+
+    pub fn get_algorithm(&self) -> Vec<Turn> {
+        Turn::parse_sequence(self.algorithm).expect("pattern algorithms are written in valid notation")
+    }
+}
+
+pub const PATTERNS: [Pattern; 6] = [
+    Pattern {
+        name: "checker",
+        description: "Every face shows exactly two colours",
+        algorithm: "U R' L F' R L'",
+        state: || RawState::new(&[0, 1, 2, 3, 4, 5], 0, &[2, 11, 0, 3, 4, 5, 6, 7, 8, 9, 10, 1],
+            &[9, 0, 0, 6, 3, 3, 3, 6, 6, 0, 9, 9], &[6, 6, 0, 9, 9, 3, 0, 0, 6, 3, 3, 9]),
+    },
+    Pattern {
+        name: "centre-exchange",
+        description: "Every centre moved to another face, with the corners and edges solved",
+        algorithm: "BL' U D' R BL' U D' R BL' U D' R BL' U D' R BL' U D' R \
+            BL' U D' R BL' U D' R BL' U D' R BL' U D' R BL' U D' R",
+        state: || RawState::new(&[0, 1, 2, 3, 4, 5], 0, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            &[9, 9, 6, 0, 9, 0, 3, 0, 3, 6, 3, 6], &[6, 9, 6, 0, 0, 6, 9, 9, 3, 3, 0, 3]),
+    },
+    Pattern {
+        name: "centre-swap",
+        description: "Two centres swapped, with everything else solved",
+        algorithm: "F R F R' F R F R' F R F R' F R F R' F R F R' F R F R'",
+        state: || RawState::new(&[0, 1, 2, 3, 4, 5], 0, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            &[0, 0, 0, 3, 3, 3, 6, 6, 9, 9, 9, 6], &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9]),
+    },
+    Pattern {
+        name: "edge-cycle",
+        description: "Three edges cycled, with everything else solved",
+        algorithm: "U F U' F U F U' F",
+        state: || RawState::new(&[0, 1, 2, 3, 4, 5], 0, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 11, 9, 10],
+            &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9], &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9]),
+    },
+    Pattern {
+        name: "edge-exchange",
+        description: "Every edge moved, with the down faces left one colour each",
+        algorithm: "L R B D L R B D L R B D L R B D",
+        state: || RawState::new(&[0, 1, 2, 3, 4, 5], 0, &[7, 10, 4, 0, 9, 8, 1, 3, 11, 2, 6, 5],
+            &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9], &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9]),
+    },
+    Pattern {
+        name: "corner-cycle",
+        description: "Five corners cycled without being flipped, with everything else solved",
+        algorithm: "F R F' D F R F' D F R F' D",
+        state: || RawState::new(&[0, 4, 3, 1, 5, 2], 0, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9], &[0, 0, 0, 3, 3, 3, 6, 6, 6, 9, 9, 9]),
+    },
+];
+
+/// Every pattern's name and state, in the order of `PATTERNS`.
+pub fn all() -> Vec<(&'static str, RawState)> {
+    PATTERNS.iter().map(|pattern| (pattern.name, pattern.get_state())).collect()
+}
+
+pub fn get(name: &str) -> Option<&'static Pattern> {
+    PATTERNS.iter().find(|pattern| pattern.name == name)
+}
+
+pub fn get_names() -> Vec<&'static str> {
+    PATTERNS.iter().map(|pattern| pattern.name).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movedefs::{get_state_sticker_faces, get_sticker_position_face};
+
+    #[test]
+    fn test_algorithms_reach_stored_states() {
+        for pattern in PATTERNS.iter() {
+            let mut state = RawState::solved();
+            for turn in pattern.get_algorithm().iter() {
+                state.apply(turn);
+            }
+            assert_eq!(state, pattern.get_state(), "{}", pattern.name);
+        }
+    }
+
+    #[test]
+    fn test_patterns_are_valid_and_distinct() {
+        let patterns = all();
+        assert!(patterns.len() >= 6);
+        for (index, (name, state)) in patterns.iter().enumerate() {
+            assert!(state.validate().is_ok(), "{}", name);
+            assert_ne!(*state, RawState::solved(), "{}", name);
+            assert!(patterns[..index].iter().all(|(other_name, other)| other_name != name && other != state));
+        }
+    }
+
+    #[test]
+    fn test_pieces_moved() {
+        let get_unsolved = |name| get(name).unwrap().get_state().get_num_unsolved_pieces();
+        assert_eq!(get_unsolved("centre-exchange"), (0, 0, 24));
+        assert_eq!(get_unsolved("centre-swap"), (0, 0, 2));
+        assert_eq!(get_unsolved("edge-cycle"), (0, 3, 0));
+        assert_eq!(get_unsolved("edge-exchange"), (0, 12, 0));
+        assert_eq!(get_unsolved("corner-cycle"), (5, 0, 0));
+    }
+
+    #[test]
+    fn test_checker_has_two_colours_on_each_face() {
+        let mut colours = [0u8; 8];
+        for (name, colour) in get_state_sticker_faces(&get("checker").unwrap().get_state()) {
+            colours[get_sticker_position_face(name) as usize] |= 1 << colour;
+        }
+        assert!(colours.iter().all(|face_colours| face_colours.count_ones() == 2));
+    }
+
+    #[test]
+    fn test_get() {
+        assert_eq!(get("checker").unwrap().algorithm, "U R' L F' R L'");
+        assert!(get("nonsense").is_none());
+        assert_eq!(get_names(), all().iter().map(|(name, _)| *name).collect::<Vec<_>>());
+    }
+}
@@ -1,19 +1,52 @@
 use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufReader, BufWriter};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::trace;
 
 use crate::coordinates::CoordinateType;
 use crate::movedefs::{Face, Turn};
-use crate::movetables::{MoveTables, MoveTable, ApplyMove};
+use crate::movetables::{MoveTables, MoveTable, ApplyMove, TableReader, get_invalid_data_error};
+#[cfg(feature = "serde-tables")]
+use crate::serdetables;
+
+
+/// The name of the file `SimplePruningTable::save_to` is usually given, next to the move table file.
+pub const PRUNING_TABLE_FILE_NAME: &str = "pruningtables.dat";
+
+/// The start of a pruning table file in the serde format. See `serdetables` for the rest of the layout.
+pub const SERDE_PRUNING_TABLE_MAGIC: &[u8] = b"FTOP";
 
 
 pub trait PruningTable {
     fn get_distance_lower_bound(&self, coords: &[u32], coord_types: &[CoordinateType]) -> u8;
 }
 
+#[derive(Debug, PartialEq)]
 pub struct SimplePruningTable {
     tables: HashMap<CoordinateType, Vec<u8>>,
     faces: Vec<Face>,
 }
 
+/// What populating the table for one type of coordinate found, for checking that the table looks right and for
+/// logging how long it took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopulationStats {
+    pub coord_type: CoordinateType,
+    /// The greatest distance of any coordinate that could be reached.
+    pub max_depth: u8,
+    pub fill_duration: Duration,
+    /// How many coordinates could be reached and so have a distance. The rest are left at `u8::MAX`.
+    pub entry_count: usize,
+}
+
+#[allow(dead_code)]
 pub struct CompoundPruningTable {
     tables: HashMap<CoordinateType, Vec<u8>>,
     faces: Vec<Face>,
@@ -27,6 +60,126 @@ impl SimplePruningTable {
         }
     }
 
+    /// The faces whose turns the distances were measured with.
+    pub fn get_faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// The distance from solved of every coordinate of one type, indexed by coordinate, such as for building a
+    /// histogram of distances. This is empty if the table for the coordinate type hasn't been populated.
+    pub fn get_all_distances(&self, coord_type: CoordinateType) -> &[u8] {
+        self.tables.get(&coord_type).map_or(&[], |table| table.as_slice())
+    }
+
+    /// Saves the faces the table was built with and every populated table, in the format of `to_bytes`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Loads tables saved by `save_to` or `save_serde_to`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the tables in the serde format described in `serdetables`.
+    #[cfg(all(feature = "serde-tables", not(target_arch = "wasm32")))]
+    pub fn save_serde_to(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_serde_bytes())
+    }
+
+    /// The faces the table was built with and every populated table. The layout follows the move table file:
+    /// the number of faces and a byte for each face, then each table as its coordinate type byte followed by one
+    /// byte per coordinate, and finally a zero. Everything except the distances is a 4 byte number.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).expect("writing to a Vec should not fail");
+        bytes
+    }
+
+    /// Reads tables in the format written by `to_bytes`, `save_to` or `to_serde_bytes`, so that a web page can
+    /// fetch them instead of populating them itself.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::read_from(bytes)
+    }
+
+    /// The tables in the serde format described in `serdetables`, which `from_bytes` and `load_from` also read.
+    #[cfg(feature = "serde-tables")]
+    pub fn to_serde_bytes(&self) -> Vec<u8> {
+        serdetables::to_bytes(SERDE_PRUNING_TABLE_MAGIC, self)
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&(self.faces.len() as u32).to_be_bytes())?;
+        for face in self.faces.iter() {
+            writer.write_all(&[0,0,0,face.to_byte()])?;
+        }
+
+        for coord_type in CoordinateType::iter() {
+            if let Some(table) = self.tables.get(&coord_type) {
+                writer.write_all(&[0,0,0,coord_type.to_byte()])?;
+                writer.write_all(table)?;
+            }
+        }
+
+        writer.write_all(&[0,0,0,0])
+    }
+
+    fn read_from(mut reader: impl BufRead) -> io::Result<Self> {
+        match reader.fill_buf()?.starts_with(SERDE_PRUNING_TABLE_MAGIC) {
+            true => Self::read_serde_from(reader),
+            false => Self::read_tables_from(reader),
+        }
+    }
+
+    #[cfg(feature = "serde-tables")]
+    fn read_serde_from(reader: impl BufRead) -> io::Result<Self> {
+        serdetables::read_from(SERDE_PRUNING_TABLE_MAGIC, reader)
+    }
+
+    #[cfg(not(feature = "serde-tables"))]
+    fn read_serde_from(_reader: impl BufRead) -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "the pruning tables were saved in the serde format, which needs the serde-tables feature"))
+    }
+
+    fn read_tables_from(reader: impl BufRead) -> io::Result<Self> {
+        let mut reader = TableReader::new(reader);
+
+        let num_faces = reader.try_read_next_num()?;
+        let mut faces = Vec::new();
+        for _ in 0..num_faces {
+            let face_byte = reader.try_read_next_num()? as u8;
+            let face = Face::get_all_faces().into_iter()
+                .find(|face| face.to_byte() == face_byte)
+                .ok_or_else(|| get_invalid_data_error(&reader, "unrecognised face"))?;
+            faces.push(face);
+        }
+        let mut result = Self::init(&faces);
+
+        loop {
+            let coord_byte = reader.try_read_next_num()? as u8;
+            if coord_byte == 0 {
+                break
+            }
+            let coord_type = CoordinateType::iter()
+                .find(|coord_type| coord_type.to_byte() == coord_byte)
+                .ok_or_else(|| get_invalid_data_error(&reader, "unrecognised coordinate type"))?;
+
+            let mut table = vec![0; coord_type.get_size()];
+            reader.try_read_bytes(&mut table)?;
+            result.tables.insert(coord_type, table);
+        }
+        Ok(result)
+    }
+
+    /// The coordinate types that have been populated, in the order of `CoordinateType::iter`.
+    pub fn populated_coordinate_types(&self) -> Vec<CoordinateType> {
+        CoordinateType::iter().filter(|coord_type| self.tables.contains_key(coord_type)).collect()
+    }
+
     fn get_allowed_turns(&self) -> Vec<Turn> {
         let mut turns = Vec::with_capacity(self.faces.len() * 2);
         for face in &self.faces {
@@ -36,85 +189,192 @@ impl SimplePruningTable {
         turns
     }
 
-    pub fn populate(&mut self, move_tables: &MoveTables) {
+    /// Populates the table for every type of coordinate, in the order of `CoordinateType::iter`.
+    pub fn populate(&mut self, move_tables: &MoveTables) -> Vec<PopulationStats> {
+        CoordinateType::iter()
+            .map(|coord_type| self.populate_coordinate_type(move_tables, coord_type))
+            .collect()
+    }
 
-        for coord_type in CoordinateType::iter() {
-            let move_table = move_tables.tables.get(&coord_type).unwrap();
-            self.populate_coordinate(move_table, coord_type);
-        }
+    /// Populates the table for just one type of coordinate.
+    pub fn populate_coordinate_type(&mut self, move_tables: &MoveTables, coord_type: CoordinateType) -> PopulationStats {
+        let move_table = move_tables.tables.get(&coord_type).unwrap();
+        self.populate_coordinate(move_table, coord_type)
+    }
+
+    fn populate_coordinate(&mut self, move_table: &MoveTable, coord_type: CoordinateType) -> PopulationStats {
+        self.populate_from_multiple_start_states(move_table, coord_type, &[0])
     }
 
-    fn populate_coordinate(&mut self, move_table: &MoveTable, coord_type: CoordinateType) {
-        let num_coords = coord_type.get_size();
+    /// Populates the table for one type of coordinate with the distance to the nearest of `start_coords`, rather
+    /// than to solved, such as for a heuristic towards any of several goal states. Like `populate_from_starts`, it
+    /// panics if any of `start_coords` is out of range for `coord_type`.
+    pub fn populate_from_multiple_start_states(&mut self, move_table: &MoveTable, coord_type: CoordinateType, start_coords: &[u32]) -> PopulationStats {
+        let start_time = Instant::now();
+        let mut table = Vec::new();
+        populate_from_starts(&mut table, move_table, &self.get_allowed_turns(), start_coords);
 
-        let mut table: Vec<u8> = Vec::with_capacity(num_coords);
-        for i in 0..coord_type.get_size() {
-            table.push(u8::MAX);
+        let reached = table.iter().filter(|distance| **distance != u8::MAX);
+        let stats = PopulationStats {
+            coord_type,
+            max_depth: reached.clone().max().copied().unwrap_or(0),
+            fill_duration: start_time.elapsed(),
+            entry_count: reached.count(),
+        };
+        self.tables.insert(coord_type, table);
+        stats
+    }
+}
+
+/// Fills `table` with the number of `turns` from each coordinate to the nearest of `start_coords`, replacing
+/// anything already in it. Coordinates that can't be reached from any of them are left at `u8::MAX`.
+///
+/// The search works forwards from the starts, one distance at a time, expanding the coordinates reached at the
+/// last distance. Once those are a third of all the coordinates, there are fewer left unfilled than there would
+/// be to expand, so the rest are filled backwards instead, by checking each unfilled coordinate for a neighbour at
+/// the previous distance.
+///
+/// # Panics
+///
+/// If any of `start_coords` isn't a coordinate of the move table's type.
+pub fn populate_from_starts(table: &mut Vec<u8>, move_table: &MoveTable, turns: &[Turn], start_coords: &[u32]) {
+    let num_coords = move_table.coord_type.get_size();
+    table.clear();
+    table.resize(num_coords, u8::MAX);
+
+    let mut remaining = num_coords;
+    let mut starts: Vec<usize> = Vec::with_capacity(start_coords.len());
+    for coord in start_coords {
+        let coord = *coord as usize;
+        assert!(coord < num_coords, "start coordinate {} is out of range for {:?}", coord, move_table.coord_type);
+        if table[coord] == u8::MAX {
+            table[coord] = 0;
+            starts.push(coord);
+            remaining -= 1;
         }
+    }
 
-        let mut distance: u8 = 1;
-        table[0] = 0;
+    let mut distance: u8 = 1;
+    let forward_stop_point = num_coords / 3;
 
-        let mut remaining = num_coords - 1;
-        let forward_stop_point = num_coords / 3;
+    forward_fill_table(table, move_table, turns, starts, &mut distance, &mut remaining, forward_stop_point);
+    backward_fill_table(table, move_table, turns, &mut distance, &mut remaining);
+}
 
-        self.forward_fill_table(&mut table, move_table, &mut distance, &mut remaining, forward_stop_point);
-        self.backward_fill_table(&mut table, move_table, &mut distance, &mut remaining);
+// The tables are kept in the order of `CoordinateType::iter` rather than as a map, so the same tables always give
+// the same bytes
+#[cfg(feature = "serde-tables")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PruningTableData<T> {
+    faces: Vec<Face>,
+    tables: Vec<(CoordinateType, T)>,
+}
+
+#[cfg(feature = "serde-tables")]
+impl serde::Serialize for SimplePruningTable {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tables = CoordinateType::iter()
+            .filter_map(|coord_type| self.tables.get(&coord_type).map(|table| (coord_type, table)))
+            .collect();
+        PruningTableData { faces: self.faces.clone(), tables }.serialize(serializer)
+    }
+}
 
-        // println!("{:?} pruning table max depth = {:?}", coord_type, distance-1);
+#[cfg(feature = "serde-tables")]
+impl<'de> serde::Deserialize<'de> for SimplePruningTable {
+    /// Checks that each table has a distance for every coordinate.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
 
-        self.tables.insert(coord_type, table);
+        let data = PruningTableData::<Vec<u8>>::deserialize(deserializer)?;
+        let mut result = Self::init(&data.faces);
+        for (coord_type, table) in data.tables {
+            if table.len() != coord_type.get_size() {
+                return Err(D::Error::invalid_length(table.len(), &"one distance for every coordinate"));
+            }
+            result.tables.insert(coord_type, table);
+        }
+        Ok(result)
     }
+}
 
-    fn forward_fill_table(&self, table: &mut Vec<u8>, move_table: &MoveTable, distance: &mut u8, remaining: &mut usize, forward_stop_point: usize) {
-        let mut previous_fill_list: Vec<usize> = vec![0];
-        let allowed_turns = self.get_allowed_turns();
-        while *remaining > 0 && previous_fill_list.len() < forward_stop_point {
-            // println!("  - Forward filling {:?} pruning table for distance {:?}. Checking {:?} coords. ({:?} remaining)",
-            //     move_table.coord_type, distance, previous_fill_list.len(), remaining);
-            let mut next_fill_list: Vec<usize> = vec![];
-            for coord in previous_fill_list {
-                for turn in &allowed_turns {
-                    let next_coord = move_table.apply_move_to_coord(coord as u32, move_table.coord_type, &turn) as usize;
-                    if table[next_coord] == u8::MAX {
-                        table[next_coord] = *distance;
-                        next_fill_list.push(next_coord);
-                        *remaining -= 1;
-                    }
+/// Fills the table outwards from the starts one distance at a time. The coordinates to expand next are kept as a
+/// bitset with a bit for every coordinate, rather than a list, so the memory it takes is fixed at an eighth of a
+/// byte per coordinate however wide the search gets.
+fn forward_fill_table(table: &mut [u8], move_table: &MoveTable, turns: &[Turn], starts: Vec<usize>, distance: &mut u8, remaining: &mut usize, forward_stop_point: usize) {
+    let num_words = table.len().div_ceil(64);
+    let mut previous_fill_set = vec![0_u64; num_words];
+    let mut next_fill_set = vec![0_u64; num_words];
+    for coord in starts.iter() {
+        previous_fill_set[coord / 64] |= 1 << (coord % 64);
+    }
+    let mut previous_fill_count = previous_fill_set.iter().map(|word| word.count_ones() as usize).sum();
+
+    while *remaining > 0 && previous_fill_count > 0 && previous_fill_count < forward_stop_point {
+        trace!("Forward filling {:?} pruning table for distance {}. Checking {} coords ({} remaining)",
+            move_table.coord_type, distance, previous_fill_count, remaining);
+        next_fill_set.fill(0);
+        let mut next_fill_count = 0;
+        for coord in iter_set_bits(&previous_fill_set) {
+            for turn in turns {
+                let next_coord = move_table.apply_move_to_coord(coord as u32, move_table.coord_type, turn) as usize;
+                if table[next_coord] == u8::MAX {
+                    table[next_coord] = *distance;
+                    next_fill_set[next_coord / 64] |= 1 << (next_coord % 64);
+                    next_fill_count += 1;
+                    *remaining -= 1;
                 }
             }
-            previous_fill_list = next_fill_list;
-            *distance += 1;
         }
+        std::mem::swap(&mut previous_fill_set, &mut next_fill_set);
+        previous_fill_count = next_fill_count;
+        *distance += 1;
     }
+}
 
-    fn backward_fill_table(&self, table: &mut Vec<u8>, move_table: &MoveTable, distance: &mut u8, remaining: &mut usize) {
-        let allowed_turns = self.get_allowed_turns();
-        while *remaining > 0 {
-            // println!("  - Backward filling {:?} pruning table for distance {:?}. Checking {:?} coords. ({:?} remaining)",
-            //     move_table.coord_type, distance, remaining, remaining);
+/// The position of every set bit, counting from the lowest bit of the first word.
+fn iter_set_bits(bits: &[u64]) -> impl Iterator<Item = usize> + '_ {
+    bits.iter().enumerate().flat_map(|(index, word)| {
+        let mut word = *word;
+        std::iter::from_fn(move || {
+            (word != 0).then(|| {
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                index * 64 + bit
+            })
+        })
+    })
+}
 
-            let num_coords = move_table.coord_type.get_size();
+/// Fills in the rest of the table by checking each unfilled coordinate for a neighbour at the previous distance.
+/// The unfilled coordinates are listed once and the list shrinks with each pass, so the last few distances, which
+/// usually cover very few coordinates, don't have to look through the whole table. With only some faces allowed,
+/// some coordinates can't be reached at all, so this stops once a pass finds nothing new, leaving those at
+/// `u8::MAX`.
+fn backward_fill_table(table: &mut [u8], move_table: &MoveTable, turns: &[Turn], distance: &mut u8, remaining: &mut usize) {
+    let mut unfilled: Vec<u32> = (0..table.len() as u32).filter(|coord| table[*coord as usize] == u8::MAX).collect();
+    while !unfilled.is_empty() {
+        trace!("Backward filling {:?} pruning table for distance {}. Checking {} coords ({} remaining)",
+            move_table.coord_type, distance, unfilled.len(), remaining);
 
-            for coord in 0..num_coords {
-                if table[coord] == u8::MAX {
-                    for turn in &allowed_turns {
-                        let next_coord = move_table.apply_move_to_coord(coord as u32, move_table.coord_type, &turn) as usize;
-                        if table[next_coord] == *distance - 1 {
-                            table[coord] = *distance;
-                            *remaining -= 1;
-                            break;
-                        }
-                    }
-                    if *remaining == 0 {
-                        break
-                    }
-                }
+        let previous_distance = *distance - 1;
+        let num_unfilled = unfilled.len();
+        // Coordinates filled during a pass are at the current distance, so they can't be mistaken for neighbours
+        // at the previous one by coordinates checked after them
+        unfilled.retain(|coord| {
+            let is_next = turns.iter().any(|turn|
+                table[move_table.apply_move_to_coord(*coord, move_table.coord_type, turn) as usize] == previous_distance);
+            if is_next {
+                table[*coord as usize] = *distance;
             }
-            *distance += 1;
+            !is_next
+        });
+        *remaining = unfilled.len();
+        if unfilled.len() == num_unfilled {
+            break;
         }
+        *distance += 1;
     }
-
 }
 
 impl PruningTable for SimplePruningTable {
@@ -151,4 +411,198 @@ mod tests {
 
 
     }
+
+    #[test]
+    fn test_get_all_distances() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+
+        let mut pruning_table = SimplePruningTable::init(&Face::get_all_faces());
+        assert!(pruning_table.get_all_distances(coord_type).is_empty());
+        pruning_table.populate_coordinate(&move_table, coord_type);
+
+        let distances = pruning_table.get_all_distances(coord_type);
+        assert_eq!(distances.len(), coord_type.get_size());
+        assert_eq!(distances[0], 0);
+        assert!(distances[1..].iter().all(|distance| *distance > 0 && *distance < u8::MAX));
+        assert_eq!(distances[123], pruning_table.get_distance_lower_bound(&[123], &[coord_type]));
+        assert!(pruning_table.get_all_distances(CoordinateType::EdgeInFace).is_empty());
+    }
+
+    #[test]
+    fn test_populate_with_one_face_leaves_unreachable_coords() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+
+        let mut pruning_table = SimplePruningTable::init(&[Face::R]);
+        assert_eq!(pruning_table.get_faces(), &[Face::R]);
+        let stats = pruning_table.populate_coordinate(&move_table, coord_type);
+        assert_eq!(stats.max_depth, 1);
+        assert_eq!(stats.entry_count, 3);
+
+        // Turning one face can only reach the two states a turn away in either direction
+        let distances = pruning_table.get_all_distances(coord_type);
+        assert_eq!(distances.iter().filter(|distance| **distance == 0).count(), 1);
+        assert_eq!(distances.iter().filter(|distance| **distance == 1).count(), 2);
+        assert_eq!(distances.iter().filter(|distance| **distance == u8::MAX).count(), coord_type.get_size() - 3);
+    }
+
+    #[test]
+    fn test_population_stats_match_table() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+
+        let mut pruning_table = SimplePruningTable::init(&Face::get_all_faces());
+        let stats = pruning_table.populate_coordinate(&move_table, coord_type);
+
+        let distances = pruning_table.get_all_distances(coord_type);
+        assert_eq!(stats.coord_type, coord_type);
+        assert_eq!(stats.entry_count, coord_type.get_size());
+        assert_eq!(stats.max_depth, *distances.iter().max().unwrap());
+    }
+
+    #[test]
+    fn test_populate_from_starts() {
+        let coord_type = CoordinateType::CornerState;
+        let move_table = MoveTable::new(coord_type);
+        let turns = Turn::get_all_turns();
+        let mut pruning_table = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_table.populate_coordinate(&move_table, coord_type);
+
+        // Starting from solved alone gives the same table as populating it normally
+        let mut table = vec![7; 3];
+        populate_from_starts(&mut table, &move_table, &turns, &[0]);
+        assert_eq!(table, pruning_table.get_all_distances(coord_type));
+
+        // With two starts, each distance is to whichever start is closer, and repeated starts make no difference
+        let other_start = 1000;
+        let mut from_other = vec![];
+        populate_from_starts(&mut from_other, &move_table, &turns, &[other_start]);
+        let mut from_both = vec![];
+        populate_from_starts(&mut from_both, &move_table, &turns, &[0, other_start, 0]);
+        assert_eq!(from_both[other_start as usize], 0);
+        for coord in 0..coord_type.get_size() {
+            assert_eq!(from_both[coord], table[coord].min(from_other[coord]), "coordinate {}", coord);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "start coordinate 11520 is out of range for CornerState")]
+    fn test_populate_from_starts_out_of_range() {
+        let move_table = MoveTable::new(CoordinateType::CornerState);
+        populate_from_starts(&mut vec![], &move_table, &Turn::get_all_turns(), &[0, 11520]);
+    }
+
+    /// The distances from solved found by a plain breadth-first search, for checking the faster fill against.
+    fn get_breadth_first_distances(move_table: &MoveTable, turns: &[Turn]) -> Vec<u8> {
+        let mut distances = vec![u8::MAX; move_table.coord_type.get_size()];
+        distances[0] = 0;
+        let mut frontier = vec![0];
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            distance += 1;
+            let mut next_frontier = vec![];
+            for coord in frontier {
+                for turn in turns {
+                    let next_coord = move_table.apply_move_to_coord(coord, move_table.coord_type, turn);
+                    if distances[next_coord as usize] == u8::MAX {
+                        distances[next_coord as usize] = distance;
+                        next_frontier.push(next_coord);
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+        distances
+    }
+
+    #[test]
+    fn test_populate_matches_breadth_first_search() {
+        use crate::movetables::get_test_move_tables;
+
+        let move_tables = get_test_move_tables();
+        for coord_type in [CoordinateType::CornerState, CoordinateType::UpCentre] {
+            let move_table = &move_tables.tables[&coord_type];
+            for faces in [Face::get_all_faces().to_vec(), vec![Face::R, Face::U]] {
+                let turns = Turn::get_allowed_turns_for_faces(&faces);
+                let mut table = vec![];
+                populate_from_starts(&mut table, move_table, &turns, &[0]);
+                assert!(table == get_breadth_first_distances(move_table, &turns), "{:?} with {:?}", coord_type, faces);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iter_set_bits() {
+        assert_eq!(iter_set_bits(&[0b1001, 0, 1 << 63 | 1]).collect::<Vec<_>>(), [0, 3, 128, 191]);
+        assert_eq!(iter_set_bits(&[0, 0]).count(), 0);
+    }
+
+    #[test]
+    fn test_save_to_and_load_from() {
+        let coord_type = CoordinateType::CornerState;
+        let mut pruning_table = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_table.populate_coordinate(&MoveTable::new(coord_type), coord_type);
+        assert_eq!(pruning_table.populated_coordinate_types(), vec![coord_type]);
+
+        let path = std::env::temp_dir().join(format!("fto-pruningtables-{}.dat", std::process::id()));
+        pruning_table.save_to(&path).unwrap();
+        let loaded = SimplePruningTable::load_from(&path);
+        let size = std::fs::metadata(&path).unwrap().len() as usize;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), pruning_table);
+        assert_eq!(size, 4 + 8 * 4 + 4 + coord_type.get_size() + 4);
+    }
+
+    #[test]
+    fn test_to_bytes_and_from_bytes() {
+        let coord_type = CoordinateType::CornerState;
+        let mut pruning_table = SimplePruningTable::init(&[Face::U, Face::R]);
+        pruning_table.populate_coordinate(&MoveTable::new(coord_type), coord_type);
+
+        let bytes = pruning_table.to_bytes();
+        assert_eq!(bytes.len(), 4 + 2 * 4 + 4 + coord_type.get_size() + 4);
+        assert_eq!(SimplePruningTable::from_bytes(&bytes).unwrap(), pruning_table);
+    }
+
+    #[cfg(feature = "serde-tables")]
+    #[test]
+    fn test_serde_round_trip() {
+        let coord_type = CoordinateType::CornerState;
+        let mut pruning_table = SimplePruningTable::init(&[Face::U, Face::R]);
+        pruning_table.populate_coordinate(&MoveTable::new(coord_type), coord_type);
+
+        let bytes = pruning_table.to_serde_bytes();
+        // The header, two faces, one table with its coordinate type, then the distances with a two byte length
+        assert_eq!(&bytes[..8], b"FTOP\x01\x02UR");
+        assert_eq!(bytes.len(), 5 + 3 + 2 + 2 + coord_type.get_size());
+        assert_eq!(SimplePruningTable::from_bytes(&bytes).unwrap(), pruning_table);
+
+        let error = SimplePruningTable::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "serde-tables")]
+    #[test]
+    fn test_serde_rejects_wrong_table_length() {
+        let mut bytes = SERDE_PRUNING_TABLE_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 1, b'U', 1, b'C', 2, 0, 1]);
+        let error = SimplePruningTable::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(not(feature = "serde-tables"))]
+    #[test]
+    fn test_serde_format_needs_feature() {
+        let error = SimplePruningTable::from_bytes(b"FTOP\x01\x00\x00").unwrap_err();
+        assert!(error.to_string().contains("serde-tables feature"));
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_coordinate_type() {
+        let data: &[u8] = &[0,0,0,1, 0,0,0,b'U', 0,0,0,b'X'];
+        let error = SimplePruningTable::read_from(data).unwrap_err();
+        assert_eq!(error.to_string(), "unrecognised coordinate type at byte offset 12");
+    }
 }
@@ -1,61 +1,577 @@
 use crate::coordinates::CoordinateType;
+use crate::ergonomics::{score_sequence, Scoring};
 use crate::movetables::MoveTables;
-use crate::pruningtables::{SimplePruningTable, PruningTable};
+use crate::pruningtables::{PruningTable, SimplePruningTable};
 use crate::state::{CoordState, do_triple_centres_match_corners};
-use crate::movedefs::Turn;
+use crate::movedefs::{Turn, is_redundant_turn};
+use log::debug;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 
+/// Counts of what the search did, for seeing whether time goes on exploring the tree or on the pruning lookups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// States reached by a turn, including ones that were then pruned.
+    pub nodes_explored: u64,
+    /// States not searched any further because the pruning tables showed they were too far from the goal.
+    pub nodes_pruned: u64,
+    /// States whose whole subtree was searched without finding a solution.
+    pub backtracks: u64,
+    /// The most turns from the starting state of any state reached.
+    pub max_depth_reached: u8,
+}
+
+impl SearchStats {
+    /// Adds the counts from another search, such as the next depth of an iterative deepening search.
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes_explored += other.nodes_explored;
+        self.nodes_pruned += other.nodes_pruned;
+        self.backtracks += other.backtracks;
+        self.max_depth_reached = self.max_depth_reached.max(other.max_depth_reached);
+    }
+}
+
+/// A phase 1 solution, or `None` if there wasn't one within the limit, along with how much searching it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolveResult {
+    pub solution: Option<Vec<Turn>>,
+    pub stats: SearchStats,
+}
+
+/// What a phase 1 search may do besides searching to a depth. The default tries every turn, excludes no end states
+/// and starts without a turn before it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchConfig<'a> {
+    /// Only these turns are tried, or every turn if it is `None`. Each turn is tried in both directions only if
+    /// both are listed.
+    ///
+    /// The pruning tables have to be populated with every face the allowed turns use, and no others, such as with
+    /// `SimplePruningTable::init(&faces)` for the faces of the allowed turns. Distances in tables built with more
+    /// faces are still lower bounds, so the search is still correct, but it prunes less. Tables built without some
+    /// of the faces can overestimate the distance, and the search can then miss solutions.
+    pub allowed_moves: Option<&'a [Turn]>,
+    /// States a solution must never end on, such as the states earlier solutions led to when looking for solutions
+    /// that reach a state not seen yet. The search carries on past an excluded state, so a solution may pass
+    /// through one on its way to another.
+    pub exclusion_list: Option<&'a HashSet<CoordState>>,
+    /// The turn made just before the search starts, so that the first turn doesn't repeat it.
+    pub prev_turn: Option<&'a Turn>,
+}
+
 pub fn search_phase_1(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, prev_turn: Option<&Turn>) -> Vec<Turn> {
-    if limit > 0 {
-        for turn in Turn::get_all_turns().iter().rev() {
-            if is_redundant_turn(prev_turn, turn) {
-                continue;
-            }
+    let config = SearchConfig { prev_turn, ..Default::default() };
+    search_phase_1_with_config(state, move_tables, pruning_tables, limit, &config, &mut SearchStats::default())
+}
+
+/// The same as `search_phase_1`, with the options in `config`, adding what the search did to `stats`.
+pub fn search_phase_1_with_config(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, config: &SearchConfig, stats: &mut SearchStats) -> Vec<Turn> {
+    match config.allowed_moves {
+        Some(turns) => {
+            debug_assert!(turns.iter().all(|turn| pruning_tables.get_faces().contains(&turn.get_face())),
+                "the pruning tables are missing faces of the allowed moves");
+            Search { move_tables, pruning_tables, turns: turns.iter().copied(), exclusion_list: config.exclusion_list, stats }
+                .search(state, limit, config.prev_turn, 0)
+        }
+        None => Search { move_tables, pruning_tables, turns: Turn::iter_all(), exclusion_list: config.exclusion_list, stats }
+            .search(state, limit, config.prev_turn, 0),
+    }
+}
+
+/// What stays the same all the way down one search.
+struct Search<'a, I> {
+    move_tables: &'a MoveTables,
+    pruning_tables: &'a SimplePruningTable,
+    turns: I,
+    exclusion_list: Option<&'a HashSet<CoordState>>,
+    stats: &'a mut SearchStats,
+}
 
-            // println!("  - solving {:?} limited to {:?}", turn, limit);
-            let mut next_state = *state;
-            next_state.apply(move_tables, turn);
+impl<I> Search<'_, I>
+where I: DoubleEndedIterator<Item = Turn> + Clone
+{
+    fn search(&mut self, state: &CoordState, limit: u8, prev_turn: Option<&Turn>, depth: u8) -> Vec<Turn> {
+        if limit > 0 {
+            for turn in self.turns.clone().rev() {
+                if is_redundant_turn(prev_turn, &turn) {
+                    continue;
+                }
 
-            if is_phase_1_solved(&next_state) {
-                return vec![*turn];
+                let mut next_state = *state;
+                next_state.apply(self.move_tables, &turn);
+                self.stats.nodes_explored += 1;
+                self.stats.max_depth_reached = self.stats.max_depth_reached.max(depth + 1);
+
+                if is_phase_1_solved(&next_state) && !self.exclusion_list.is_some_and(|excluded| excluded.contains(&next_state)) {
+                    return vec![turn];
+                }
+                else if next_state.phase1_heuristic(self.pruning_tables) <= limit {
+                    let mut solution = self.search(&next_state, limit - 1, Some(&turn), depth + 1);
+                    if !solution.is_empty() {
+                        solution.insert(0, turn);
+                        return solution
+                    }
+                    self.stats.backtracks += 1;
+                }
+                else {
+                    self.stats.nodes_pruned += 1;
+                }
             }
-            else if !should_prune_phase_1(&next_state, pruning_tables, limit) {
-                let mut solution = search_phase_1(&next_state, move_tables, pruning_tables, limit - 1, Some(turn));
-                if !solution.is_empty() {
-                    solution.insert(0, *turn);
-                    return solution
+        }
+        vec![]
+    }
+}
+
+/// Finds a phase 1 solution of at most `limit` turns, or `None` if there isn't one. With `optimal`, each depth is
+/// searched in turn, so the solution found is as short as possible.
+pub fn solve_phase_1(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, optimal: bool) -> Option<Vec<Turn>> {
+    solve_phase_1_with_stats(state, move_tables, pruning_tables, limit, optimal).solution
+}
+
+/// The same as `solve_phase_1`, also counting what the search did. With `optimal`, the counts cover every depth.
+pub fn solve_phase_1_with_stats(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, optimal: bool) -> SolveResult {
+    let mut stats = SearchStats::default();
+    let depths = if optimal { 1..=limit } else { limit..=limit };
+    let solution = if is_phase_1_solved(state) {
+        Some(vec![])
+    } else {
+        depths
+            .map(|depth| {
+                let solution = search_phase_1_with_config(state, move_tables, pruning_tables, depth, &SearchConfig::default(),
+                    &mut stats);
+                debug!("Searched to depth {}: {} nodes explored and {} pruned so far",
+                    depth, stats.nodes_explored, stats.nodes_pruned);
+                solution
+            })
+            .find(|solution| !solution.is_empty())
+    };
+    match &solution {
+        Some(solution) => debug!("Found a phase 1 solution of {} moves after exploring {} nodes",
+            solution.len(), stats.nodes_explored),
+        None => debug!("No phase 1 solution within {} moves", limit),
+    }
+    SolveResult { solution, stats }
+}
+
+/// Solves every state as `solve_phase_1_with_stats` does, spreading the work over all available cores. The
+/// results are in the same order as the states.
+pub fn solve_phase_1_batch(states: &[CoordState], move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, optimal: bool) -> Vec<SolveResult> {
+    let num_threads = thread::available_parallelism().map_or(1, |n| n.get()).min(states.len());
+    // Threads take the next unsolved state when they finish one, as some states take much longer than others
+    let next_index = AtomicUsize::new(0);
+
+    let mut results: Vec<Option<SolveResult>> = vec![None; states.len()];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..num_threads)
+            .map(|_| scope.spawn(|| {
+                let mut solved = vec![];
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    let Some(state) = states.get(index) else {
+                        return solved;
+                    };
+                    solved.push((index, solve_phase_1_with_stats(state, move_tables, pruning_tables, limit, optimal)));
                 }
+            }))
+            .collect();
+        for worker in workers {
+            for (index, result) in worker.join().expect("a solver thread panicked") {
+                results[index] = Some(result);
+            }
+        }
+    });
+    results.into_iter()
+        .map(|result| result.expect("every state was taken by a thread"))
+        .collect()
+}
+
+/// How `solve_multiple` orders the solutions it finds.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Ranking {
+    /// Shortest first.
+    #[default]
+    Length,
+    /// Easiest to turn first, by `score_sequence` with these weights.
+    Ergonomic(Scoring),
+}
+
+/// Finds up to `count` phase 1 solutions of at most `limit` turns, each ending on a different phase 1 solved state,
+/// and sorts them by `ranking`. The shortest solutions are found first, so when there are more than `count` of
+/// them, the longer ones are never looked at, whatever the ranking.
+pub fn solve_multiple(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, count: usize, ranking: Ranking) -> Vec<Vec<Turn>> {
+    solve_multiple_with_stats(state, move_tables, pruning_tables, limit, count, ranking, &mut SearchStats::default())
+}
+
+/// The same as `solve_multiple`, adding what the search did to `stats`.
+pub fn solve_multiple_with_stats(state: &CoordState, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, limit: u8, count: usize, ranking: Ranking, stats: &mut SearchStats) -> Vec<Vec<Turn>> {
+    let mut solutions = Vec::new();
+    let mut excluded = HashSet::new();
+    if is_phase_1_solved(state) && count > 0 {
+        solutions.push(vec![]);
+        excluded.insert(*state);
+    }
+    // Each depth is searched until every end state within it has been reached, so the solutions found at one depth
+    // are never shorter than the ones found before it
+    for depth in 1..=limit {
+        while solutions.len() < count {
+            let config = SearchConfig { exclusion_list: Some(&excluded), ..Default::default() };
+            let solution = search_phase_1_with_config(state, move_tables, pruning_tables, depth, &config, stats);
+            if solution.is_empty() {
+                break;
             }
+            let mut end = *state;
+            end.apply_sequence(move_tables, &solution.iter().collect::<Vec<_>>());
+            excluded.insert(end);
+            solutions.push(solution);
         }
     }
-    vec![]
+    if let Ranking::Ergonomic(scoring) = ranking {
+        solutions.sort_by(|a, b| score_sequence(b, &scoring).total_cmp(&score_sequence(a, &scoring)));
+    }
+    debug!("Found {} phase 1 solutions after exploring {} nodes", solutions.len(), stats.nodes_explored);
+    solutions
+}
+
+/// A choice of kinds of piece, for saying which pieces a search has to reach a goal for or leave alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PieceSet {
+    pub corners: bool,
+    pub edges: bool,
+    pub up_centres: bool,
+    pub down_centres: bool,
+}
+
+impl PieceSet {
+    pub const CORNERS_AND_EDGES: Self = Self { corners: true, edges: true, up_centres: false, down_centres: false };
+    pub const CENTRES: Self = Self { corners: false, edges: false, up_centres: true, down_centres: true };
+
+    /// The coordinates that between them say where every piece in the set is.
+    pub fn get_coord_types(&self) -> Vec<CoordinateType> {
+        let mut coord_types = Vec::new();
+        if self.corners {
+            coord_types.push(CoordinateType::CornerState);
+        }
+        if self.edges {
+            coord_types.extend([CoordinateType::EdgeInFace, CoordinateType::EdgeAcrossFaces]);
+        }
+        if self.up_centres {
+            coord_types.push(CoordinateType::UpCentre);
+        }
+        if self.down_centres {
+            coord_types.push(CoordinateType::DownCentre);
+        }
+        coord_types
+    }
+}
+
+/// A state to reach for some of the pieces, with the others free to end up anywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoalSpec {
+    pub state: CoordState,
+    pub pieces: PieceSet,
+}
+
+impl GoalSpec {
+    pub fn is_reached(&self, state: &CoordState) -> bool {
+        self.pieces.get_coord_types().into_iter()
+            .all(|coord_type| get_coord(state, coord_type) == get_coord(&self.state, coord_type))
+    }
 }
 
-fn is_redundant_turn(prev_turn: Option<&Turn>, curr_turn: &Turn) -> bool {
-    match prev_turn {
-        Some(prev_turn) => {
-            let prev_axis = prev_turn.face.get_primary_face();
-            let curr_axis = curr_turn.face.get_primary_face();
+fn get_coord(state: &CoordState, coord_type: CoordinateType) -> u32 {
+    state.get_coords().into_iter()
+        .find_map(|(state_coord_type, coord)| (state_coord_type == coord_type).then_some(coord))
+        .expect("the state has every type of coordinate that a piece set uses")
+}
 
-            // Don't turn the same face twice
-            prev_turn.face == curr_turn.face ||
-            // Skip if it is the same axis, and the current face is primary (favour the secondary face in phase 1)
-            (prev_axis == curr_axis && curr_turn.face == curr_axis)
-        },
-        None => false,
+/// Finds the shortest sequence of at most `max_len` turns that takes `start` to `goal` while leaving every piece in
+/// `preserve` where it was, or `None` if there isn't one. The preserved pieces have to be solved in `start`, since
+/// the pruning tables give the distance to solved. A branch is cut off as soon as the preserved pieces are further
+/// from solved than the turns left can bring them back. Goal coordinates that are solved are used for cutting off
+/// branches in the same way, so a search towards solved pieces is much faster than one away from them. The pruning
+/// tables need to be populated for every coordinate of the preserved pieces, and of the goal pieces that are solved.
+pub fn search_preserving(start: &CoordState, goal: &GoalSpec, preserve: PieceSet, max_len: u8, move_tables: &MoveTables, pruning_tables: &SimplePruningTable) -> Option<Vec<Turn>> {
+    let mut pruned = preserve.get_coord_types();
+    if pruned.iter().any(|coord_type| get_coord(start, *coord_type) != 0) {
+        return None;
     }
+    let solved_goal_coord_types = goal.pieces.get_coord_types().into_iter()
+        .filter(|coord_type| get_coord(&goal.state, *coord_type) == 0 && !pruned.contains(coord_type));
+    pruned.extend(solved_goal_coord_types.collect::<Vec<_>>());
+
+    let search = |limit| search_preserving_to_depth(start, goal, &pruned, limit, None, move_tables, pruning_tables);
+    (0..=max_len).find_map(search)
 }
 
-fn should_prune_phase_1(state: &CoordState, pruning_tables: &SimplePruningTable, limit: u8) -> bool {
-    let coords = [state.edges_within_faces, state.down_centres];
-    let coord_types = [CoordinateType::EdgeInFace, CoordinateType::DownCentre];
-    let distance = pruning_tables.get_distance_lower_bound(&coords, &coord_types);
+/// Searches for a sequence of exactly `limit` turns, where every coordinate in `pruned` has to end up solved.
+fn search_preserving_to_depth(state: &CoordState, goal: &GoalSpec, pruned: &[CoordinateType], limit: u8, prev_turn: Option<&Turn>, move_tables: &MoveTables, pruning_tables: &SimplePruningTable) -> Option<Vec<Turn>> {
+    if limit == 0 {
+        let is_solved = pruned.iter().all(|coord_type| get_coord(state, *coord_type) == 0);
+        return (is_solved && goal.is_reached(state)).then(Vec::new);
+    }
+    for turn in Turn::iter_all() {
+        if is_redundant_turn(prev_turn, &turn) {
+            continue;
+        }
+        let mut next_state = *state;
+        next_state.apply(move_tables, &turn);
 
-    distance > limit
+        let is_too_far = pruned.iter().any(|coord_type|
+            pruning_tables.get_distance_lower_bound(&[get_coord(&next_state, *coord_type)], &[*coord_type]) > limit - 1);
+        if is_too_far {
+            continue;
+        }
+        if let Some(mut sequence) = search_preserving_to_depth(&next_state, goal, pruned, limit - 1, Some(&turn), move_tables, pruning_tables) {
+            sequence.insert(0, turn);
+            return Some(sequence);
+        }
+    }
+    None
 }
 
-fn is_phase_1_solved(state: &CoordState) -> bool {
+/// Whether a state has reached the phase 1 goal, which is that three things are in place:
+///
+/// 1. `edges_within_faces == 0`: every edge is on the up face (U, F, BL or BR) it belongs to. Each edge touches one
+///    up face, so this groups the edges by up face, but the three edges of a group can still be in any order.
+/// 2. `up_centres == 0`: every up centre is on its own face. The three centres of a colour are identical, so this
+///    says all there is to say about them.
+/// 3. The triple centres match the corners: each corner has the two down centres that sit next to it in a solved
+///    puzzle on either side of it, so every corner and its two down centres form a block that moves together.
+///
+/// Once all three hold, the edges only need ordering within their up face, the up centres are done, and the down
+/// centres are fixed by the corners, so what is left is the edge order and the corners, with the down centres
+/// following them as triples. That is what phase 2 would solve, keeping to turns that leave the three conditions
+/// alone. Phase 2 doesn't exist yet, so for now a solve ends here.
+pub fn is_phase_1_solved(state: &CoordState) -> bool {
     state.edges_within_faces == 0 &&
     state.up_centres == 0 &&
     do_triple_centres_match_corners(state.corners, state.down_centres)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movedefs::{Face, get_random_turns};
+    use crate::state::SOLVED_COORD_STATE;
+
+    #[test]
+    fn test_solve_phase_1_batch_matches_solve_phase_1() {
+        use crate::coordinates::CoordinateType;
+        use crate::movetables::get_test_move_tables;
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::EdgeInFace);
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::UpCentre);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let states: Vec<CoordState> = [0, 1, 3, 4, 20]
+            .iter()
+            .map(|&num_moves| {
+                let mut state = SOLVED_COORD_STATE;
+                state.apply_sequence(move_tables, &get_random_turns(num_moves, &mut rng).iter().collect::<Vec<_>>());
+                state
+            })
+            .collect();
+
+        let results = solve_phase_1_batch(&states, move_tables, &pruning_tables, 4, true);
+        assert_eq!(results.len(), states.len());
+        assert_eq!(results[0].solution, Some(vec![]));
+        for (state, result) in states.iter().zip(results.iter()) {
+            assert_eq!(*result, solve_phase_1_with_stats(state, move_tables, &pruning_tables, 4, true));
+        }
+        assert!(solve_phase_1_batch(&[], move_tables, &pruning_tables, 4, true).is_empty());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        use crate::coordinates::CoordinateType;
+        use crate::movedefs::Face;
+        use crate::movetables::get_test_move_tables;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::EdgeInFace);
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::UpCentre);
+
+        let result = solve_phase_1_with_stats(&SOLVED_COORD_STATE, move_tables, &pruning_tables, 5, false);
+        assert_eq!(result.stats, SearchStats::default());
+
+        let mut state = SOLVED_COORD_STATE;
+        state.apply_clockwise_sequence(move_tables, &[Face::R, Face::U, Face::BL]);
+        let result = solve_phase_1_with_stats(&state, move_tables, &pruning_tables, 5, true);
+        let solution = result.solution.unwrap();
+        let stats = result.stats;
+        assert_eq!(stats.max_depth_reached as usize, solution.len());
+        assert!(stats.nodes_pruned > 0);
+        // Every state explored is either on the way to the solution, pruned, or searched under and given up on
+        assert_eq!(stats.nodes_explored, stats.nodes_pruned + stats.backtracks + solution.len() as u64);
+
+        let mut direct_stats = SearchStats::default();
+        let direct = search_phase_1_with_config(&state, move_tables, &pruning_tables, solution.len() as u8,
+            &SearchConfig::default(), &mut direct_stats);
+        assert_eq!(direct, solution);
+        assert!(direct_stats.nodes_explored < stats.nodes_explored);
+    }
+
+    #[test]
+    fn test_search_with_allowed_moves() {
+        use crate::movetables::get_test_move_tables;
+
+        let move_tables = get_test_move_tables();
+        let faces = [Face::R, Face::U, Face::L];
+        let allowed_moves = Turn::get_allowed_turns_for_faces(&faces);
+        let mut pruning_tables = SimplePruningTable::init(&faces);
+        pruning_tables.populate(move_tables);
+
+        let mut state = SOLVED_COORD_STATE;
+        state.apply_clockwise_sequence(move_tables, &[Face::R, Face::U, Face::L]);
+        let config = SearchConfig { allowed_moves: Some(&allowed_moves), ..Default::default() };
+        let solution = search_phase_1_with_config(&state, move_tables, &pruning_tables, 5, &config,
+            &mut SearchStats::default());
+        assert!(!solution.is_empty());
+        assert!(solution.iter().all(|turn| faces.contains(&turn.get_face())), "{:?}", solution);
+        state.apply_sequence(move_tables, &solution.iter().collect::<Vec<_>>());
+        assert!(is_phase_1_solved(&state));
+
+        // Only clockwise turns of one face can't undo a single anticlockwise turn of another
+        let mut state = SOLVED_COORD_STATE;
+        state.apply(move_tables, &Turn::new(Face::R, true));
+        let config = SearchConfig { allowed_moves: Some(&[Turn::new(Face::U, false)]), ..Default::default() };
+        let solution = search_phase_1_with_config(&state, move_tables, &pruning_tables, 3, &config,
+            &mut SearchStats::default());
+        assert!(solution.is_empty());
+    }
+
+    #[test]
+    fn test_search_with_exclusions() {
+        use crate::movetables::get_test_move_tables;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate(move_tables);
+        let mut state = SOLVED_COORD_STATE;
+        state.apply_clockwise_sequence(move_tables, &[Face::R, Face::U]);
+
+        // Each search ends on a phase 1 solved state that none of the earlier ones reached
+        let mut excluded = HashSet::new();
+        for _ in 0..3 {
+            let config = SearchConfig { exclusion_list: Some(&excluded), ..Default::default() };
+            let solution = search_phase_1_with_config(&state, move_tables, &pruning_tables, 4, &config,
+                &mut SearchStats::default());
+            assert!(!solution.is_empty());
+            let mut end = state;
+            end.apply_sequence(move_tables, &solution.iter().collect::<Vec<_>>());
+            assert!(is_phase_1_solved(&end));
+            assert!(excluded.insert(end), "{:?} reached an excluded state", solution);
+        }
+
+        let unrestricted = search_phase_1(&state, move_tables, &pruning_tables, 4, None);
+        let empty = HashSet::new();
+        let config = SearchConfig { exclusion_list: Some(&empty), ..Default::default() };
+        let no_exclusions = search_phase_1_with_config(&state, move_tables, &pruning_tables, 4, &config,
+            &mut SearchStats::default());
+        assert_eq!(no_exclusions, unrestricted);
+    }
+
+    #[test]
+    fn test_solve_multiple() {
+        use crate::movetables::get_test_move_tables;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate(move_tables);
+        let mut state = SOLVED_COORD_STATE;
+        state.apply_clockwise_sequence(move_tables, &[Face::R, Face::U]);
+
+        let by_length = solve_multiple(&state, move_tables, &pruning_tables, 4, 6, Ranking::Length);
+        assert_eq!(by_length.len(), 6);
+        assert!(by_length.windows(2).all(|pair| pair[0].len() <= pair[1].len()));
+        let ends: HashSet<CoordState> = by_length.iter()
+            .map(|solution| {
+                let mut end = state;
+                end.apply_sequence(move_tables, &solution.iter().collect::<Vec<_>>());
+                assert!(is_phase_1_solved(&end));
+                end
+            })
+            .collect();
+        assert_eq!(ends.len(), by_length.len());
+
+        let scoring = Scoring::default();
+        let ranked = solve_multiple(&state, move_tables, &pruning_tables, 4, 6, Ranking::Ergonomic(scoring));
+        assert!(ranked.windows(2).all(|pair| score_sequence(&pair[0], &scoring) >= score_sequence(&pair[1], &scoring)));
+        // The same solutions, only in a different order
+        let get_sorted = |solutions: &[Vec<Turn>]| {
+            let mut sorted: Vec<String> = solutions.iter().map(|solution| format!("{:?}", solution)).collect();
+            sorted.sort();
+            sorted
+        };
+        assert_eq!(get_sorted(&ranked), get_sorted(&by_length));
+
+        assert_eq!(solve_multiple(&SOLVED_COORD_STATE, move_tables, &pruning_tables, 4, 1, Ranking::Length), [vec![]]);
+        assert!(solve_multiple(&state, move_tables, &pruning_tables, 4, 0, Ranking::Length).is_empty());
+    }
+
+    #[test]
+    fn test_search_stats_add() {
+        let mut stats = SearchStats { nodes_explored: 10, nodes_pruned: 4, backtracks: 3, max_depth_reached: 2 };
+        stats.add(&SearchStats { nodes_explored: 5, nodes_pruned: 1, backtracks: 1, max_depth_reached: 6 });
+        assert_eq!(stats, SearchStats { nodes_explored: 15, nodes_pruned: 5, backtracks: 4, max_depth_reached: 6 });
+    }
+
+    #[test]
+    fn test_piece_set_coord_types() {
+        assert_eq!(PieceSet::default().get_coord_types(), []);
+        assert_eq!(PieceSet::CORNERS_AND_EDGES.get_coord_types(),
+            [CoordinateType::CornerState, CoordinateType::EdgeInFace, CoordinateType::EdgeAcrossFaces]);
+        assert_eq!(PieceSet::CENTRES.get_coord_types(), [CoordinateType::UpCentre, CoordinateType::DownCentre]);
+    }
+
+    #[test]
+    fn test_search_preserving_finds_centre_cycle() {
+        use crate::cycles::get_cycle_state;
+        use crate::movedefs::PieceType;
+        use crate::movetables::get_test_move_tables;
+        use crate::state::RawState;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        for coord_type in [PieceSet::CORNERS_AND_EDGES.get_coord_types(), vec![CoordinateType::UpCentre]].concat() {
+            pruning_tables.populate_coordinate_type(move_tables, coord_type);
+        }
+        let cycled = get_cycle_state(PieceType::UpCentre, [0, 3, 9], [false; 3]).unwrap();
+        let goal = GoalSpec { state: SOLVED_COORD_STATE, pieces: PieceSet { up_centres: true, ..Default::default() } };
+
+        let sequence = search_preserving(&cycled.to_coords(), &goal, PieceSet::CORNERS_AND_EDGES, 10, move_tables,
+            &pruning_tables).unwrap();
+        assert_eq!(search_preserving(&cycled.to_coords(), &goal, PieceSet::CORNERS_AND_EDGES, 4, move_tables,
+            &pruning_tables), None);
+
+        // From solved, the sequence cycles the same centres the other way, and leaves every corner and edge alone
+        let mut state = RawState::solved();
+        for turn in sequence.iter() {
+            state.apply(turn);
+        }
+        let solved = RawState::solved();
+        assert_eq!((&state.corners, state.corner_orientation, &state.edges),
+            (&solved.corners, solved.corner_orientation, &solved.edges));
+        assert_eq!(state.up_centres, get_cycle_state(PieceType::UpCentre, [9, 3, 0], [false; 3]).unwrap().up_centres);
+    }
+
+    #[test]
+    fn test_search_preserving_needs_preserved_pieces_solved() {
+        let move_tables = crate::movetables::get_test_move_tables();
+        let pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        let start = SOLVED_COORD_STATE.after_clockwise_sequence(move_tables, &[Face::R]);
+        let goal = GoalSpec { state: start, pieces: PieceSet::CENTRES };
+
+        assert!(goal.is_reached(&start));
+        assert!(!goal.is_reached(&SOLVED_COORD_STATE));
+        assert_eq!(search_preserving(&start, &goal, PieceSet::CORNERS_AND_EDGES, 3, move_tables, &pruning_tables), None);
+        assert_eq!(search_preserving(&start, &goal, PieceSet::default(), 3, move_tables, &pruning_tables), Some(vec![]));
+    }
+}
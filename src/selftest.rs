@@ -0,0 +1,240 @@
+/*
+Checks that find stale or corrupted move tables and mistakes in the coordinate conversions. Move tables are checked
+against themselves, since every clockwise turn has to be undone by the anticlockwise turn and by two more clockwise
+turns, and against the raw states they are built from. Checking every entry against a raw state would take as long
+as generating the tables, so that is only done for a sample of entries, and for any entry that fails the cheaper
+checks, so that the failure can name what the entry should have been.
+*/
+
+use std::fmt;
+
+use rand::Rng;
+
+use crate::coordinates::CoordinateType;
+use crate::movedefs::{Face, RawTurn, Turn};
+use crate::movetables::{apply_turn_to_state, ApplyMove, MoveTable, MoveTables};
+use crate::state::{CoordState, RawState};
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelfTestFailure {
+    MissingTable(CoordinateType),
+    /// A move table entry that is not what turning the state for `coord` gives.
+    WrongEntry { coord_type: CoordinateType, face: Face, coord: u32, value: u32, expected: u32 },
+    /// Converting a coordinate to a state and back gave a different coordinate.
+    RoundTrip { coord_type: CoordinateType, coord: u32, result: u32 },
+    /// Applying a turn to a coordinate state gave a different coordinate than applying it to the raw state.
+    ApplyMismatch(MismatchReport),
+}
+
+/// The first coordinate that `verify_tables_against_raw` found to differ, with the state that was turned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MismatchReport {
+    pub state: RawState,
+    pub turn: Turn,
+    pub coord_type: CoordinateType,
+    /// The coordinate before the turn.
+    pub coord: u32,
+    /// The coordinate the raw state gave after the turn.
+    pub expected: u32,
+    /// The coordinate the move table gave after the turn.
+    pub got: u32,
+}
+
+impl fmt::Display for MismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "turning {:?} coordinate {} with {:?} gave {}, but the raw state gave {} (state {:?})",
+            self.coord_type, self.coord, self.turn, self.got, self.expected, self.state)
+    }
+}
+
+impl std::error::Error for MismatchReport {}
+
+impl fmt::Display for SelfTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTable(coord_type) => write!(f, "there is no {:?} move table", coord_type),
+            Self::WrongEntry { coord_type, face, coord, value, expected } => write!(f,
+                "the {:?} move table turns {} to {} for face {:?}, expected {}", coord_type, coord, value, face, expected),
+            Self::RoundTrip { coord_type, coord, result } => write!(f,
+                "{:?} coordinate {} came back as {} after converting it to a state", coord_type, coord, result),
+            Self::ApplyMismatch(report) => write!(f, "{}", report),
+        }
+    }
+}
+
+impl std::error::Error for SelfTestFailure {}
+
+/// Checks every move table with `check_move_table`.
+pub fn check_move_tables(move_tables: &MoveTables, samples: usize, rng: &mut impl Rng) -> Result<(), SelfTestFailure> {
+    for coord_type in CoordinateType::iter() {
+        let move_table = move_tables.tables.get(&coord_type).ok_or(SelfTestFailure::MissingTable(coord_type))?;
+        check_move_table(move_table, samples, rng)?;
+    }
+    Ok(())
+}
+
+/// Checks that for every face and coordinate, the anticlockwise turn and three clockwise turns both return to
+/// the coordinate, and that `samples` random entries for each face match turning the state directly.
+pub fn check_move_table(move_table: &MoveTable, samples: usize, rng: &mut impl Rng) -> Result<(), SelfTestFailure> {
+    let coord_type = move_table.coord_type;
+    let size = coord_type.get_size() as u32;
+    for face in Face::get_all_faces() {
+        let turn = Turn::new(face, false);
+        let inverse = Turn::new(face, true);
+        let check_entry = |coord: u32| {
+            let value = move_table.apply_move_to_coord(coord, coord_type, &turn);
+            let expected = get_turned_coord(coord_type, face, coord);
+            match value == expected {
+                true => Ok(()),
+                false => Err(SelfTestFailure::WrongEntry { coord_type, face, coord, value, expected }),
+            }
+        };
+
+        for coord in 0..size {
+            let next = move_table.apply_move_to_coord(coord, coord_type, &turn);
+            let undone = move_table.apply_move_to_coord(next, coord_type, &inverse);
+            let third = move_table.apply_move_to_coord(
+                move_table.apply_move_to_coord(next, coord_type, &turn), coord_type, &turn);
+            // A bad entry also breaks the checks for the coordinate that should have turned to it, so only the
+            // entry that doesn't match the raw state is reported
+            if undone != coord || third != coord {
+                check_entry(coord)?;
+            }
+        }
+        for _ in 0..samples {
+            check_entry(rng.gen_range(0..size))?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the first and last coordinate of each type, and `samples` random ones, are the same after
+/// converting them to a state and back.
+pub fn check_coordinate_round_trips(samples: usize, rng: &mut impl Rng) -> Result<(), SelfTestFailure> {
+    for coord_type in CoordinateType::iter() {
+        let size = coord_type.get_size() as u32;
+        let coords = [0, size - 1].into_iter().chain((0..samples).map(|_| rng.gen_range(0..size)));
+        for coord in coords {
+            let result = coord_type.state_to_coord(&coord_type.coord_to_state(coord));
+            if result != coord {
+                return Err(SelfTestFailure::RoundTrip { coord_type, coord, result });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that every turn applied to `samples` random states with the move tables gives the same coordinates as
+/// applying it to the raw state, after checking that none of the tables are missing.
+pub fn check_move_tables_match_raw_state(move_tables: &MoveTables, samples: usize, rng: &mut impl Rng) -> Result<(), SelfTestFailure> {
+    if let Some(coord_type) = move_tables.missing_tables().first() {
+        return Err(SelfTestFailure::MissingTable(*coord_type));
+    }
+    verify_tables_against_raw(move_tables, samples, rng).map_err(SelfTestFailure::ApplyMismatch)
+}
+
+/// Turns `samples` random raw states by every turn in both directions, once with `RawState::apply` and then
+/// converting to coordinates, and once by converting to coordinates and then looking the turn up in the move
+/// tables, and reports the first coordinate where the two differ. The move tables and the raw turns are built
+/// separately, so this finds a mistake in either. Every table has to be present.
+pub fn verify_tables_against_raw(move_tables: &MoveTables, samples: usize, rng: &mut impl Rng) -> Result<(), MismatchReport> {
+    for _ in 0..samples {
+        let state = CoordState::get_random_with_rng(rng).to_raw();
+        let before = state.to_coords();
+        for turn in Turn::iter_all() {
+            let mut raw = state.clone();
+            raw.apply(&turn);
+            let expected = raw.to_coords().get_coords();
+            let mut turned = before;
+            turned.apply(move_tables, &turn);
+
+            for (i, (coord_type, got)) in turned.get_coords().into_iter().enumerate() {
+                if got != expected[i].1 {
+                    return Err(MismatchReport {
+                        state, turn, coord_type, coord: before.get_coords()[i].1, expected: expected[i].1, got });
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The coordinate a clockwise turn of `face` gives, worked out from the state rather than the move table.
+fn get_turned_coord(coord_type: CoordinateType, face: Face, coord: u32) -> u32 {
+    let mut state = coord_type.coord_to_state(coord);
+    apply_turn_to_state(&mut state, RawTurn::get(face), coord_type.get_turn_effect_type());
+    coord_type.state_to_coord(&state)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::movetables::get_test_move_tables;
+
+    #[test]
+    fn test_checks_pass_for_generated_tables() {
+        let move_tables = get_test_move_tables();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(check_move_tables(move_tables, 20, &mut rng), Ok(()));
+        assert_eq!(check_coordinate_round_trips(200, &mut rng), Ok(()));
+        assert_eq!(check_move_tables_match_raw_state(move_tables, 20, &mut rng), Ok(()));
+        assert_eq!(verify_tables_against_raw(move_tables, 5, &mut rng), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_tables_against_raw_reports_mismatch() {
+        let coord_type = CoordinateType::CornerState;
+        let mut data = vec![];
+        get_test_move_tables().tables[&coord_type].write_to(&mut data).unwrap();
+        data.extend([0, 0, 0, 0]);
+
+        // Swapping the face numbers of the U and D tables makes U turn the corners the way D does
+        let table_size = 4 * (coord_type.get_size() + 1);
+        data[table_size * Face::U.to_index() + 3] = Face::D.to_byte();
+        data[table_size * Face::D.to_index() + 3] = Face::U.to_byte();
+        let reader = &mut crate::movetables::TableReader::new(&data[..]);
+        let corrupted = MoveTable::try_read_from_buffer(reader, coord_type).unwrap();
+        let mut move_tables = MoveTables::from_bytes(&get_test_move_tables().to_bytes()).unwrap();
+        move_tables.tables.insert(coord_type, corrupted);
+
+        let report = verify_tables_against_raw(&move_tables, 1, &mut StdRng::seed_from_u64(1)).unwrap_err();
+        assert_eq!(report.turn, Turn::new(Face::U, false));
+        assert_eq!(report.coord_type, coord_type);
+        assert_eq!(report.coord, report.state.to_coords().corners);
+        assert_ne!(report.got, report.expected);
+        assert!(report.to_string().starts_with("turning CornerState coordinate"));
+    }
+
+    #[test]
+    fn test_check_move_table_finds_swapped_entry() {
+        let coord_type = CoordinateType::CornerState;
+        let mut data = vec![];
+        MoveTable::new(coord_type).write_to(&mut data).unwrap();
+        data.extend([0, 0, 0, 0]);
+
+        // The tables are in face index order, each a face number and then one number per coordinate
+        let coord = 100;
+        let offset = 4 * (Face::R.to_index() * (coord_type.get_size() + 1) + 1 + coord);
+        let expected = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap());
+        let value = (expected + 1) % coord_type.get_size() as u32;
+        data[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+
+        let reader = &mut crate::movetables::TableReader::new(&data[..]);
+        let corrupted = MoveTable::try_read_from_buffer(reader, coord_type).unwrap();
+        let failure = check_move_table(&corrupted, 0, &mut StdRng::seed_from_u64(1)).unwrap_err();
+        assert_eq!(failure, SelfTestFailure::WrongEntry { coord_type, face: Face::R, coord: coord as u32, value, expected });
+    }
+
+    #[test]
+    fn test_missing_table() {
+        let move_tables = MoveTables { tables: Default::default() };
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(check_move_tables(&move_tables, 1, &mut rng), Err(SelfTestFailure::MissingTable(CoordinateType::CornerState)));
+        assert_eq!(check_move_tables_match_raw_state(&move_tables, 1, &mut rng),
+            Err(SelfTestFailure::MissingTable(CoordinateType::CornerState)));
+    }
+}
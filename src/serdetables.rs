@@ -0,0 +1,130 @@
+/*
+A second way of saving the tables, using serde and the postcard format, for when the original format's framing gets
+in the way. The original format marks the end of each table and of the file with a zero word, so no face or
+coordinate type can ever be written as zero, and adding anything to it means more markers like these. Postcard
+gives every list its length instead.
+
+Each file starts with four bytes saying what it holds and a byte for the version of this layout, followed by the
+postcard data. The original files always start with a zero byte, so the loaders can tell the formats apart from
+the first few bytes. Faces and coordinate types are written as the same bytes the original format uses.
+*/
+
+use std::fmt;
+use std::io::{self, BufRead};
+
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::coordinates::CoordinateType;
+use crate::movedefs::Face;
+
+
+/// The version of the layout after the header, which is increased whenever the layout changes.
+pub const SERDE_FORMAT_VERSION: u8 = 1;
+
+
+/// Writes `value` after the header for `magic`.
+pub(crate) fn to_bytes<T: Serialize>(magic: &[u8], value: &T) -> Vec<u8> {
+    let mut bytes = magic.to_vec();
+    bytes.push(SERDE_FORMAT_VERSION);
+    postcard::to_extend(value, bytes).expect("the tables should always be serialisable")
+}
+
+/// Reads a value written by `to_bytes`, checking that the header matches `magic` and the current version.
+pub(crate) fn read_from<T: DeserializeOwned>(magic: &[u8], mut reader: impl BufRead) -> io::Result<T> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let data = bytes.strip_prefix(magic).ok_or_else(|| get_invalid_data_error("not a serde table file"))?;
+    match data.split_first() {
+        Some((&SERDE_FORMAT_VERSION, data)) => postcard::from_bytes(data)
+            .map_err(|error| get_invalid_data_error(&error.to_string())),
+        Some((version, _)) => Err(get_invalid_data_error(&format!("unsupported serde table version {}", version))),
+        None => Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+    }
+}
+
+pub(crate) fn get_invalid_data_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+impl Serialize for CoordinateType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.to_byte())
+    }
+}
+
+impl<'de> Deserialize<'de> for CoordinateType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_u8(ByteVisitor::new("a coordinate type byte", |byte| {
+            CoordinateType::iter().chain([CoordinateType::TripleCentre]).find(|coord_type| coord_type.to_byte() == byte)
+        }))
+    }
+}
+
+impl Serialize for Face {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.to_byte())
+    }
+}
+
+impl<'de> Deserialize<'de> for Face {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_u8(ByteVisitor::new("a face byte", |byte| {
+            Face::get_all_faces().into_iter().find(|face| face.to_byte() == byte)
+        }))
+    }
+}
+
+/// Reads a byte and looks up what it stands for, as `from_byte` would but without panicking on an unknown byte.
+struct ByteVisitor<F> {
+    expected: &'static str,
+    lookup: F,
+}
+
+impl<F> ByteVisitor<F> {
+    fn new(expected: &'static str, lookup: F) -> Self {
+        Self { expected, lookup }
+    }
+}
+
+impl<'de, T, F: FnOnce(u8) -> Option<T>> Visitor<'de> for ByteVisitor<F> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expected)
+    }
+
+    fn visit_u8<E: de::Error>(self, byte: u8) -> Result<T, E> {
+        (self.lookup)(byte).ok_or_else(|| E::invalid_value(de::Unexpected::Unsigned(byte as u64), &self.expected))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: &[u8] = b"TEST";
+
+    #[test]
+    fn test_round_trip() {
+        let value = (vec![Face::U, Face::BL, Face::D], CoordinateType::EdgeAcrossFaces);
+        let bytes = to_bytes(MAGIC, &value);
+        assert_eq!(&bytes[..5], b"TEST\x01");
+        assert_eq!(&bytes[5..], &[3, b'U', b'P', b'D', b'A']);
+        assert_eq!(read_from::<(Vec<Face>, CoordinateType)>(MAGIC, &bytes[..]).unwrap(), value);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let error = read_from::<Face>(MAGIC, &b"TEST\x02U"[..]).unwrap_err();
+        assert_eq!(error.to_string(), "unsupported serde table version 2");
+        let error = read_from::<Face>(MAGIC, &b"TSET\x01U"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read_from::<Face>(MAGIC, &b"TEST"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = read_from::<Face>(MAGIC, &b"TEST\x01X"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
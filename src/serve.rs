@@ -0,0 +1,321 @@
+/*
+The serve command, which loads the tables once and then answers requests from other programs over a socket, so
+that they don't pay for loading the tables each time they would otherwise run the binary. Each request is a line of
+JSON naming the command, and each response is a single line with the same JSON the command prints with --json, or
+an object with an "error" when the request couldn't be answered.
+
+    {"cmd":"solve","scramble":"R U BL'","depth":9,"optimal":false}
+    {"cmd":"scramble","count":5,"seed":1}
+    {"cmd":"draw","scramble":"R U","colours":"default","layout":"net"}
+    {"cmd":"draw","query":"fd=…&layout=net"}
+
+Draw responds with the SVG itself, as {"svg":"…"}, rather than saving a file. It takes either a scramble or a query
+string in the form the draw command's --query reads, which gives the colours and layout in place of the options.
+Clients are served by a fixed number of threads, each answering one client at a time.
+*/
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+
+#[cfg(feature = "svg")]
+use fto_scramble::drawquery::RenderRequest;
+#[cfg(feature = "svg")]
+use fto_scramble::drawstate::RenderOptions;
+use fto_scramble::movedefs::Turn;
+use fto_scramble::movetables::MoveTables;
+use fto_scramble::pruningtables::SimplePruningTable;
+#[cfg(feature = "svg")]
+use fto_scramble::state::RawState;
+
+use crate::cli::{scramble, solve_with_tables, CliError, LayoutArg, MAX_SCRAMBLE_COUNT};
+#[cfg(feature = "svg")]
+use crate::cli::get_colour_scheme;
+
+
+/// The deepest a client may ask a solve to search. Each extra turn multiplies the time a search can take, so a
+/// client asking for much more would hold up a thread for good.
+const MAX_REQUEST_DEPTH: u8 = 12;
+
+/// Where the server listens for clients.
+pub enum Address {
+    /// A Unix domain socket, created at the path and removed again when the server stops.
+    Socket(PathBuf),
+    /// A TCP port on 127.0.0.1, for platforms without Unix domain sockets. Port 0 picks a free port.
+    Port(u16),
+}
+
+pub struct ServeOptions {
+    /// The most turns a solution may have, when a solve request doesn't say.
+    pub depth: u8,
+    /// How many clients can be served at once.
+    pub threads: usize,
+    /// Stop once the first client has disconnected.
+    pub once: bool,
+}
+
+pub enum Listener {
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    pub fn bind(address: &Address) -> Result<Self, CliError> {
+        match address {
+            #[cfg(unix)]
+            Address::Socket(path) => {
+                // A socket file left behind by a server that was killed can be replaced, but not one still in use
+                if path.exists() && UnixStream::connect(path).is_err() {
+                    std::fs::remove_file(path).map_err(|source| CliError::Write { path: path.clone(), source })?;
+                }
+                let listener = UnixListener::bind(path).map_err(|source| CliError::Write { path: path.clone(), source })?;
+                Ok(Self::Unix(listener, path.clone()))
+            }
+            #[cfg(not(unix))]
+            Address::Socket(_) => Err(CliError::Usage(clap::Error::raw(clap::error::ErrorKind::InvalidValue,
+                "Unix domain sockets aren't available on this platform, so use --port instead\n"))),
+            Address::Port(port) => Ok(Self::Tcp(TcpListener::bind((Ipv4Addr::LOCALHOST, *port))?)),
+        }
+    }
+
+    /// The socket path or the address and port, which gives the port that was picked if the port was 0.
+    pub fn get_address(&self) -> String {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(_, path) => path.display().to_string(),
+            Self::Tcp(listener) => listener.local_addr().map_or_else(|error| error.to_string(), |address| address.to_string()),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Connection>> {
+        match self {
+            #[cfg(unix)]
+            Self::Unix(listener, _) => Ok(Box::new(listener.accept()?.0)),
+            Self::Tcp(listener) => Ok(Box::new(listener.accept()?.0)),
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Self::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+trait Connection: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Connection for T {}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+enum Request {
+    Solve {
+        scramble: String,
+        depth: Option<u8>,
+        #[serde(default)]
+        optimal: bool,
+    },
+    Scramble {
+        #[serde(default = "get_default_count")]
+        count: usize,
+        seed: Option<u64>,
+    },
+    Draw {
+        scramble: Option<String>,
+        query: Option<String>,
+        colours: Option<String>,
+        layout: Option<LayoutArg>,
+        width: Option<u32>,
+        height: Option<u32>,
+        #[serde(default)]
+        margin: u32,
+        background: Option<String>,
+    },
+}
+
+fn get_default_count() -> usize {
+    1
+}
+
+impl Request {
+    /// Checks the numbers the client gave, which are otherwise only limited by their types.
+    fn check_limits(&self) -> Result<(), String> {
+        match self {
+            Self::Solve { depth: Some(depth), .. } if *depth > MAX_REQUEST_DEPTH =>
+                Err(format!("the depth can be at most {}, but got {}", MAX_REQUEST_DEPTH, depth)),
+            Self::Scramble { count, .. } if *count > MAX_SCRAMBLE_COUNT =>
+                Err(format!("the count can be at most {}, but got {}", MAX_SCRAMBLE_COUNT, count)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Answers clients on `options.threads` threads until the process is killed, or until the first client is done
+/// with `options.once`.
+pub fn serve(
+    listener: Listener,
+    move_tables: Arc<MoveTables>,
+    pruning_tables: Arc<SimplePruningTable>,
+    options: &ServeOptions,
+) -> Result<(), CliError> {
+    let (sender, receiver) = mpsc::channel::<Box<dyn Connection>>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let workers: Vec<_> = (0..options.threads.max(1))
+        .map(|_| {
+            let receiver = Arc::clone(&receiver);
+            let move_tables = Arc::clone(&move_tables);
+            let pruning_tables = Arc::clone(&pruning_tables);
+            let depth = options.depth;
+            thread::spawn(move || loop {
+                // The lock is only held while waiting, so the other workers can take the next client meanwhile
+                let connection = receiver.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).recv();
+                let connection = match connection {
+                    Ok(connection) => connection,
+                    Err(_) => break,
+                };
+                // A panic while answering loses that client, but the thread carries on to serve the next one
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    answer_client(connection, &move_tables, &pruning_tables, depth)
+                }));
+                match result {
+                    Ok(Ok(())) => (),
+                    Ok(Err(error)) => warn!("Lost a client: {}", error),
+                    Err(_) => warn!("Lost a client, as answering it panicked"),
+                }
+            })
+        })
+        .collect();
+
+    info!("Listening on {}", listener.get_address());
+    loop {
+        match listener.accept() {
+            Ok(connection) => {
+                debug!("A client connected");
+                sender.send(connection).expect("the workers only stop once the sender is dropped");
+                if options.once {
+                    break;
+                }
+            }
+            Err(error) => warn!("Could not accept a client: {}", error),
+        }
+    }
+
+    drop(sender);
+    for worker in workers {
+        worker.join().expect("a serve thread panicked");
+    }
+    Ok(())
+}
+
+/// Answers each line the client sends until it disconnects.
+fn answer_client(
+    connection: Box<dyn Connection>,
+    move_tables: &MoveTables,
+    pruning_tables: &SimplePruningTable,
+    depth: u8,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(connection);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        if !line.trim().is_empty() {
+            let response = get_response(line.trim(), move_tables, pruning_tables, depth);
+            let connection = reader.get_mut();
+            connection.write_all(response.as_bytes())?;
+            connection.write_all(b"\n")?;
+            connection.flush()?;
+        }
+        line.clear();
+    }
+    debug!("A client disconnected");
+    Ok(())
+}
+
+fn get_response(line: &str, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, depth: u8) -> String {
+    let result = serde_json::from_str::<Request>(line)
+        .map_err(|error| format!("could not read the request: {}", error))
+        .and_then(|request| request.check_limits().map(|()| request))
+        .and_then(|request| answer(request, move_tables, pruning_tables, depth).map_err(|error| error.to_string()));
+    match result {
+        Ok(response) => response,
+        Err(error) => json!({ "error": error }).to_string(),
+    }
+}
+
+fn answer(request: Request, move_tables: &MoveTables, pruning_tables: &SimplePruningTable, depth: u8) -> Result<String, CliError> {
+    let mut out = Vec::new();
+    match request {
+        Request::Solve { scramble, depth: request_depth, optimal } => {
+            let scramble = Turn::parse_sequence(&scramble).map_err(CliError::Notation)?;
+            solve_with_tables(move_tables, pruning_tables, &scramble, request_depth.unwrap_or(depth), optimal)?
+                .write(&mut out, true)?;
+        }
+        Request::Scramble { count, seed } => scramble(&mut out, count, seed, true)?,
+        #[cfg(feature = "svg")]
+        Request::Draw { scramble, query, colours, layout, width, height, margin, background } => {
+            let mut request = match (scramble, query) {
+                (None, Some(query)) => RenderRequest::parse(&query).map_err(CliError::Query)?,
+                (Some(scramble), None) => {
+                    let mut state = RawState::solved();
+                    for turn in Turn::parse_sequence(&scramble).map_err(CliError::Notation)?.iter() {
+                        state.apply(turn);
+                    }
+                    RenderRequest::from_state(&state, RenderOptions {
+                        colour_scheme: get_colour_scheme(colours.as_deref().unwrap_or("default"))?,
+                        layout: layout.unwrap_or(LayoutArg::Default).into(),
+                        ..Default::default()
+                    })
+                }
+                _ => return Err(CliError::InvalidState(String::from("a draw request needs either a scramble or a query"))),
+            };
+            request.options = RenderOptions { width, height, margin, background, ..request.options };
+            writeln!(out, "{}", json!({ "svg": request.render() }))?;
+        }
+        #[cfg(not(feature = "svg"))]
+        Request::Draw { .. } => return Err(CliError::FeatureDisabled { command: "draw", feature: "svg" }),
+    }
+    Ok(String::from_utf8(out).expect("the responses are written as text").trim_end().to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_requests() {
+        let request: Request = serde_json::from_str(r#"{"cmd":"solve","scramble":"R U"}"#).unwrap();
+        assert_eq!(request, Request::Solve { scramble: "R U".to_string(), depth: None, optimal: false });
+        let request: Request = serde_json::from_str(r#"{"cmd":"scramble","seed":3}"#).unwrap();
+        assert_eq!(request, Request::Scramble { count: 1, seed: Some(3) });
+        let request: Request = serde_json::from_str(r#"{"cmd":"draw","scramble":"R","layout":"top-view"}"#).unwrap();
+        assert!(matches!(request, Request::Draw { layout: Some(LayoutArg::TopView), margin: 0, .. }));
+        let request: Request = serde_json::from_str(r#"{"cmd":"draw","query":"layout=net"}"#).unwrap();
+        assert!(matches!(request, Request::Draw { scramble: None, query: Some(_), .. }));
+
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"gen-tables"}"#).is_err());
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"solve"}"#).is_err());
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let check = |line| serde_json::from_str::<Request>(line).unwrap().check_limits();
+        assert_eq!(check(r#"{"cmd":"solve","scramble":"R","depth":12}"#), Ok(()));
+        assert_eq!(check(r#"{"cmd":"solve","scramble":"R","depth":13}"#),
+            Err(String::from("the depth can be at most 12, but got 13")));
+        assert_eq!(check(r#"{"cmd":"scramble","count":10000}"#), Ok(()));
+        assert!(check(r#"{"cmd":"scramble","count":18446744073709551615}"#).is_err());
+    }
+}
@@ -1,3 +1,43 @@
+//! Two representations of a puzzle state.
+//!
+//! `RawState` is the physical description: which piece is in each position and how each corner is flipped, using
+//! the orderings defined below. Turns are applied to it directly from their definitions in `movedefs.rs`, so it
+//! needs no tables, and it is what the renderers in `drawstate` work from. Applying a turn means permuting
+//! several vectors, which is too slow to do millions of times.
+//!
+//! `CoordState` packs the same information into five numbers (see `coordinates.rs`). Applying a turn is a single
+//! move table lookup per coordinate, and the coordinates can be used directly as indices into pruning tables, so
+//! this is the representation the search works with. It is `Copy`, which makes it cheap to branch on.
+//!
+//! Converting between the two with `RawState::to_coords` and `CoordState::to_raw` is straightforward but not free,
+//! since every coordinate has to be encoded or decoded, so it belongs at the edges of a program rather than in
+//! inner loops. `CoordState::try_to_raw` also checks that the coordinates describe a reachable state.
+//!
+//! A typical flow is to pick a random `CoordState`, search for a solution, and then replay the solution on a
+//! `RawState` to draw it. Only phase 1 of the search exists so far, so the drawn state is phase 1 solved:
+//!
+//! ```no_run
+//! # #[cfg(feature = "svg")] {
+//! use fto_scramble::drawstate;
+//! use fto_scramble::movedefs::Face;
+//! use fto_scramble::movetables::MoveTables;
+//! use fto_scramble::pruningtables::SimplePruningTable;
+//! use fto_scramble::search::search_phase_1;
+//! use fto_scramble::state::CoordState;
+//!
+//! let move_tables = MoveTables::try_load_or_generate();
+//! let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+//! pruning_tables.populate(&move_tables);
+//!
+//! let scrambled = CoordState::get_random();
+//! let solution = search_phase_1(&scrambled, &move_tables, &pruning_tables, 9, None);
+//!
+//! let mut raw = scrambled.to_raw();
+//! raw.apply_sequence(&solution.iter().collect::<Vec<_>>());
+//! let svg = drawstate::get_svg_for_state(&raw);
+//! # }
+//! ```
+
 /*
     PIECE DEFINITIONS:
     The orientation and face moves match those used in Ben Streeter's document:
@@ -88,19 +128,23 @@
     physically moved by the turn, as the corner that those centres were attached to moves.
 */
 
+use std::fmt;
+
 use lazy_static::lazy_static;
 use rand::{thread_rng, Rng};
+use rand::seq::SliceRandom;
 
-use crate::coordinates::{CoordinateType, NUM_CORNER_STATES, get_down_centre_coord_for_matched_triples, NUM_FACE_PIECE_PERMS, NUM_ACROSS_FACE_PERMS};
-use crate::movedefs::{RawTurn, NUM_CORNERS, Turn};
+use crate::coordinates::{CoordinateType, NUM_CORNER_STATES, corner_state_to_coord_separate, get_down_centre_coord_for_matched_triples, NUM_FACE_PIECE_PERMS, NUM_ACROSS_FACE_PERMS, is_even_parity};
+use crate::movedefs::{NUM_CORNERS, NUM_EDGES, NUM_CENTRES, Face, Turn, get_random_turns};
 use crate::movetables::{MoveTables, ApplyMove};
+use crate::pruningtables::{PruningTable, SimplePruningTable};
 
 lazy_static! {
     static ref SOLVED_CENTRES: [u32; NUM_CORNER_STATES] = precompute_solved_triple_centre_coords();
 }
 
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct RawState {
     pub corners: Vec<u8>,
     pub corner_orientation: u8,
@@ -109,7 +153,19 @@ pub struct RawState {
     pub down_centres: Vec<u8>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    CoordinateOutOfRange { coord_type: CoordinateType, value: u32 },
+    InvalidCornerPermutation,
+    InvalidCornerOrientation,
+    InvalidEdgePermutation,
+    InvalidUpCentres,
+    InvalidDownCentres,
+    /// Bytes given to `CoordState::from_bytes` that aren't the length of a state, holding how many there were.
+    WrongByteCount(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct CoordState {
     pub corners: u32,
     pub edges_within_faces: u32,
@@ -121,11 +177,11 @@ pub struct CoordState {
 impl RawState {
     pub fn new(corners: &[u8], corner_orientation: u8, edges: &[u8], up_centres: &[u8], down_centres: &[u8]) -> Self {
         Self {
-            corners: corners.try_into().unwrap(),
+            corners: corners.to_vec(),
             corner_orientation,
-            edges: edges.try_into().unwrap(),
-            up_centres: up_centres.try_into().unwrap(),
-            down_centres: down_centres.try_into().unwrap(),
+            edges: edges.to_vec(),
+            up_centres: up_centres.to_vec(),
+            down_centres: down_centres.to_vec(),
         }
     }
 
@@ -139,24 +195,104 @@ impl RawState {
         Self::new(&corners, corner_orientation, &edges, &up_centres, &down_centres)
     }
 
+    /// Picks a state uniformly from every state that can be reached, building the pieces directly rather than
+    /// going through coordinates.
+    ///
+    /// Every turn is a 3-cycle of corners, a 3-cycle of edges and one or two 3-cycles of each kind of centre, so
+    /// each of those permutations is always even, and the number of flipped corners is always even. Nothing else
+    /// holds: the group the eight turns generate, with every centre told apart (as in `export_gap`), has order
+    /// `(6!/2 * 2^5) * (12!/2)^3`, which is every combination of those even permutations and flips, with no
+    /// constraint between the kinds of pieces. In particular, the centres have no parity that depends on the
+    /// corners. Since each centre colour has three identical pieces, an odd arrangement of the centres can be
+    /// made even by swapping two of the same colour, so every arrangement of each set of centres can be reached,
+    /// whatever the corners and edges are doing. That gives `6!/2 * 2^5 * 12!/2 * (12!/3!^4)^2` states, 12 times
+    /// the usual count of 31,408,133,379,194,880,000,000, which treats the 12 rotations of the puzzle as the same.
+    ///
+    /// The permutations are shuffled and then, if odd, have their first two pieces swapped. That swap pairs every
+    /// odd permutation with exactly one even one, so the even permutations stay equally likely.
+    pub fn random_solvable(rng: &mut impl Rng) -> Self {
+        let mut corners: Vec<u8> = (0..NUM_CORNERS as u8).collect();
+        let mut edges: Vec<u8> = (0..NUM_EDGES as u8).collect();
+        for pieces in [&mut corners, &mut edges] {
+            pieces.shuffle(rng);
+            if !is_even_parity(pieces) {
+                pieces.swap(0, 1);
+            }
+        }
+
+        // The last five corners are flipped at random, and the first corner evens out the number of flips
+        let mut corner_orientation: u8 = rng.gen_range(0..1 << (NUM_CORNERS - 1));
+        corner_orientation |= (corner_orientation.count_ones() as u8 % 2) << (NUM_CORNERS - 1);
+
+        let solved = Self::solved();
+        let mut up_centres = solved.up_centres;
+        let mut down_centres = solved.down_centres;
+        up_centres.shuffle(rng);
+        down_centres.shuffle(rng);
+
+        Self::new(&corners, corner_orientation, &edges, &up_centres, &down_centres)
+    }
+
     pub fn apply_sequence(&mut self, sequence: &[&Turn]) {
         for turn in sequence {
             self.apply(turn);
         }
     }
 
+    /// Applies the turn with `RawTurn::apply_to_raw_state_in_place`, twice for an anticlockwise turn.
     pub fn apply(&mut self, turn: &Turn) {
-        let m: &RawTurn = turn.face.get_raw_turn();
-
-        apply_raw_permutation(&mut self.corners, &m.corner_permutation);
-        apply_orientation(&mut self.corner_orientation, &m.corner_permutation, &m.corner_orientation[0]);
-        apply_raw_permutation(&mut self.edges, &m.edges);
-        apply_raw_permutation(&mut self.up_centres, &m.up_centres);
-        apply_raw_permutation(&mut self.down_centres, &m.down_centres);
+        let raw_turn = turn.get_face().get_raw_turn();
+        for _ in 0..if turn.is_inverse() { 2 } else { 1 } {
+            raw_turn.apply_to_raw_state_in_place(self);
+        }
+    }
 
-        if turn.invert {
-            self.apply(&Turn::new(turn.face, false));
+    /// Checks that the state could be reached from solved: corners and edges must be even permutations,
+    /// an even number of corners must be flipped, and each set of centres must contain three of each colour.
+    pub fn validate(&self) -> Result<(), StateError> {
+        if !is_even_permutation_of_size(&self.corners, NUM_CORNERS) {
+            return Err(StateError::InvalidCornerPermutation);
         }
+        if self.corner_orientation >= 1 << NUM_CORNERS || !self.corner_orientation.count_ones().is_multiple_of(2) {
+            return Err(StateError::InvalidCornerOrientation);
+        }
+        if !is_even_permutation_of_size(&self.edges, NUM_EDGES) {
+            return Err(StateError::InvalidEdgePermutation);
+        }
+        if !are_valid_centres(&self.up_centres) {
+            return Err(StateError::InvalidUpCentres);
+        }
+        if !are_valid_centres(&self.down_centres) {
+            return Err(StateError::InvalidDownCentres);
+        }
+        Ok(())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Counts the corners that are out of place or flipped, the edges that are out of place, and the centres of
+    /// either kind that aren't on their solved face, compared with `RawState::solved`. This gives a rough idea of
+    /// how scrambled a state is without solving it.
+    pub fn get_num_unsolved_pieces(&self) -> (usize, usize, usize) {
+        let solved = Self::solved();
+        let flips = flip_num_to_bool_array(&self.corner_orientation);
+
+        let corners = self.corners.iter().zip(solved.corners.iter()).zip(flips.iter())
+            .filter(|((corner, solved_corner), flipped)| corner != solved_corner || **flipped)
+            .count();
+        let edges = count_differences(&self.edges, &solved.edges);
+        let centres = count_differences(&self.up_centres, &solved.up_centres)
+            + count_differences(&self.down_centres, &solved.down_centres);
+        (corners, edges, centres)
+    }
+
+    /// Converts to coordinates, first checking with `validate` that the pieces make up a state that can exist.
+    /// `to_coords` can panic on a state that isn't valid, so use this for states that come from outside.
+    pub fn try_to_coords(&self) -> Result<CoordState, StateError> {
+        self.validate()?;
+        Ok(self.to_coords())
     }
 
     pub fn to_coords(&self) -> CoordState {
@@ -170,18 +306,7 @@ impl RawState {
     }
 
     fn get_corner_coord(&self) -> u32 {
-        let mut state = self.corners.clone();
-        let mut orientation = self.corner_orientation;
-        let mut first_flip: u8 = 0;
-        for i in (1..NUM_CORNERS).rev() {
-            let flip = orientation % 2;
-            orientation /= 2;
-
-            first_flip ^= flip;
-            state[i] = state[i]*2 + flip;
-        }
-        state[0] = state[0]*2 + first_flip;
-        CoordinateType::CornerState.state_to_coord(&state)
+        corner_state_to_coord_separate(&self.corners, self.corner_orientation)
     }
 
     fn get_edge_within_face(&self) -> u32 {
@@ -201,25 +326,51 @@ impl RawState {
     }
 }
 
+fn count_differences(pieces: &[u8], solved_pieces: &[u8]) -> usize {
+    pieces.iter().zip(solved_pieces.iter()).filter(|(piece, solved_piece)| piece != solved_piece).count()
+}
+
+/// The solved state, where every coordinate is 0. Searches and random walks start from here.
+pub const SOLVED_COORD_STATE: CoordState = CoordState {
+    corners: 0,
+    edges_within_faces: 0,
+    edges_across_faces: 0,
+    up_centres: 0,
+    down_centres: 0,
+};
+
+/// The length of `CoordState::to_bytes`: four bytes for each of the five coordinates.
+pub const COORD_STATE_BYTES: usize = 20;
+
+impl Default for CoordState {
+    fn default() -> Self {
+        SOLVED_COORD_STATE
+    }
+}
+
 impl CoordState {
-    pub fn solved() -> Self {
-        Self {
-            corners: 0,
-            edges_within_faces: 0,
-            edges_across_faces: 0,
-            up_centres: 0,
-            down_centres: 0,
-        }
+    pub const fn solved() -> Self {
+        SOLVED_COORD_STATE
     }
 
     pub fn get_random() -> Self {
-        let mut rng = thread_rng();
-        Self {
-            corners: rng.gen_range(0..NUM_CORNER_STATES) as u32,
-            edges_within_faces: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
-            edges_across_faces: rng.gen_range(0..NUM_ACROSS_FACE_PERMS) as u32,
-            up_centres: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
-            down_centres: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
+        Self::get_random_with_rng(&mut thread_rng())
+    }
+
+    /// Picks a state uniformly from every state that can be reached. Half of the edge coordinates combine into an
+    /// odd edge permutation, which no sequence of turns can make, so those are drawn again.
+    pub fn get_random_with_rng(rng: &mut impl Rng) -> Self {
+        loop {
+            let state = Self {
+                corners: rng.gen_range(0..NUM_CORNER_STATES) as u32,
+                edges_within_faces: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
+                edges_across_faces: rng.gen_range(0..NUM_ACROSS_FACE_PERMS) as u32,
+                up_centres: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
+                down_centres: rng.gen_range(0..NUM_FACE_PIECE_PERMS) as u32,
+            };
+            if state.try_to_raw().is_ok() {
+                return state;
+            }
         }
     }
 
@@ -229,6 +380,19 @@ impl CoordState {
         }
     }
 
+    /// Applies a clockwise turn of each face in order, as sequences are often written in algorithm descriptions.
+    pub fn apply_clockwise_sequence(&mut self, move_tables: &MoveTables, faces: &[Face]) {
+        for face in faces {
+            self.apply(move_tables, &Turn::new(*face, false));
+        }
+    }
+
+    pub fn after_clockwise_sequence(&self, move_tables: &MoveTables, faces: &[Face]) -> Self {
+        let mut state = *self;
+        state.apply_clockwise_sequence(move_tables, faces);
+        state
+    }
+
     pub fn apply(&mut self, move_tables: &MoveTables, turn: &Turn) {
         self.corners = move_tables.apply_move_to_coord(self.corners, CoordinateType::CornerState, turn);
         self.edges_within_faces = move_tables.apply_move_to_coord(self.edges_within_faces, CoordinateType::EdgeInFace, turn);
@@ -237,6 +401,129 @@ impl CoordState {
         self.down_centres = move_tables.apply_move_to_coord(self.down_centres, CoordinateType::DownCentre, turn);
     }
 
+    /// The state the turn leads to, or `None` if any of its coordinates is out of range, in the same way as
+    /// `checked_add` for integers. Turning a valid state can only give an invalid one if a move table is wrong, so
+    /// tests use this to catch a bad table at the turn where it goes wrong.
+    pub fn apply_checked(&self, move_tables: &MoveTables, turn: &Turn) -> Option<CoordState> {
+        let mut state = *self;
+        state.apply(move_tables, turn);
+        state.is_valid().then_some(state)
+    }
+
+    /// Every turn, paired with the state it leads to, such as for expanding a state in a breadth first or beam
+    /// search. The states are worked out as the iterator is used rather than collected up front.
+    pub fn all_successors<'a>(&self, move_tables: &'a MoveTables) -> impl Iterator<Item = (Turn, CoordState)> + 'a {
+        let state = *self;
+        Turn::iter_all().map(move |turn| {
+            let mut successor = state;
+            successor.apply(move_tables, &turn);
+            (turn, successor)
+        })
+    }
+
+    /// Whether every edge is in its own position, which takes both edge coordinates: either one alone leaves the
+    /// edges partly unknown.
+    pub fn edges_fully_solved(&self) -> bool {
+        self.edges_within_faces == 0 && self.edges_across_faces == 0
+    }
+
+    /// Whether every corner is in its own position and unflipped.
+    pub fn corners_fully_solved(&self) -> bool {
+        self.corners == 0
+    }
+
+    /// Whether every up and down centre is on its own face.
+    pub fn centres_fully_solved(&self) -> bool {
+        self.up_centres == 0 && self.down_centres == 0
+    }
+
+    /// A lower bound on the number of turns left to solve phase 1: the furthest from solved of the coordinates that
+    /// phase 1 has to solve. The down centres only have to match the corners in phase 1, not be solved, so their
+    /// distance isn't a lower bound and they are left out.
+    pub fn phase1_heuristic(&self, pruning_tables: &SimplePruningTable) -> u8 {
+        pruning_tables.get_distance_lower_bound(
+            &[self.edges_within_faces, self.up_centres],
+            &[CoordinateType::EdgeInFace, CoordinateType::UpCentre],
+        )
+    }
+
+    /// Applies `num_moves` random turns to a copy of this state. Turns that would be redundant after the previous
+    /// turn, such as turning the same face again, are never chosen.
+    pub fn perturb(&self, move_tables: &MoveTables, num_moves: u8, rng: &mut impl Rng) -> Self {
+        let mut state = *self;
+        for turn in get_random_turns(num_moves as usize, rng) {
+            state.apply(move_tables, &turn);
+        }
+        state
+    }
+
+    /// Each coordinate along with its type, in the order `CoordinateType::iter` gives the types.
+    pub fn get_coords(&self) -> [(CoordinateType, u32); 5] {
+        [
+            (CoordinateType::CornerState, self.corners),
+            (CoordinateType::EdgeInFace, self.edges_within_faces),
+            (CoordinateType::EdgeAcrossFaces, self.edges_across_faces),
+            (CoordinateType::UpCentre, self.up_centres),
+            (CoordinateType::DownCentre, self.down_centres),
+        ]
+    }
+
+    /// Checks that every coordinate is in range, which is all it takes for the move tables to be able to apply
+    /// turns to the state. Use `try_to_raw` to also check that the coordinates describe a state that can exist.
+    pub fn validate(&self) -> Result<(), StateError> {
+        for (coord_type, value) in self.get_coords() {
+            if value as usize >= coord_type.get_size() {
+                return Err(StateError::CoordinateOutOfRange { coord_type, value });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Converts to a raw state, first checking that every coordinate is in range and that the coordinates
+    /// combine into a state that can actually exist.
+    pub fn try_to_raw(&self) -> Result<RawState, StateError> {
+        self.validate()?;
+
+        let raw = self.to_raw();
+        raw.validate()?;
+        Ok(raw)
+    }
+
+    /// The five coordinates as little endian `u32`s, in the order `get_coords` gives them, for storing or sending a
+    /// state in a fixed size.
+    pub fn to_bytes(&self) -> [u8; COORD_STATE_BYTES] {
+        let mut bytes = [0; COORD_STATE_BYTES];
+        for (chunk, (_, coord)) in bytes.chunks_exact_mut(4).zip(self.get_coords()) {
+            chunk.copy_from_slice(&coord.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a state written by `to_bytes`. The bytes may come from anywhere, so anything other than exactly
+    /// `COORD_STATE_BYTES` of them, or a coordinate out of range, is an error rather than a state that could make
+    /// the move tables panic.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StateError> {
+        if bytes.len() != COORD_STATE_BYTES {
+            return Err(StateError::WrongByteCount(bytes.len()));
+        }
+        let mut coords = bytes.chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().expect("the chunks are 4 bytes")));
+        let mut next = || coords.next().expect("there are five coordinates");
+        let state = Self {
+            corners: next(),
+            edges_within_faces: next(),
+            edges_across_faces: next(),
+            up_centres: next(),
+            down_centres: next(),
+        };
+        state.validate()?;
+        Ok(state)
+    }
+
     pub fn to_raw(&self) -> RawState {
         RawState {
             corners: self.get_corner_permutation(),
@@ -249,8 +536,8 @@ impl CoordState {
 
     fn get_corner_permutation(&self) -> Vec<u8> {
         let mut corner_permutation = CoordinateType::CornerState.coord_to_state(self.corners);
-        for i in 0..corner_permutation.len() {
-            corner_permutation[i] /= 2;
+        for corner in corner_permutation.iter_mut() {
+            *corner /= 2;
         }
         corner_permutation
     }
@@ -288,6 +575,42 @@ impl CoordState {
 }
 
 
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CoordinateOutOfRange { coord_type, value } =>
+                write!(f, "{:?} coordinate {} is out of range (expected less than {})", coord_type, value, coord_type.get_size()),
+            Self::InvalidCornerPermutation => write!(f, "corners are not an even permutation"),
+            Self::InvalidCornerOrientation => write!(f, "corners do not have an even number of flips"),
+            Self::InvalidEdgePermutation => write!(f, "edges are not an even permutation"),
+            Self::InvalidUpCentres => write!(f, "up centres do not contain three of each colour"),
+            Self::InvalidDownCentres => write!(f, "down centres do not contain three of each colour"),
+            Self::WrongByteCount(count) => write!(f, "expected {} bytes but got {}", COORD_STATE_BYTES, count),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+
+/// Builds the lookup behind `do_triple_centres_match_corners` now, rather than on its first call. Building it takes
+/// a few milliseconds, so calling this when the program starts keeps that out of the first search.
+pub fn warm_up_solved_centres_cache() {
+    lazy_static::initialize(&SOLVED_CENTRES);
+}
+
+/// Whether every corner has the two down centres that sit next to it in a solved puzzle on either side of it, as
+/// phase 1 needs.
+///
+/// Which down centre coordinate that is depends on the whole corner state. Each corner takes its two triple centres
+/// with it, so wherever a corner is, the centres beside it have to be the ones from beside its solved position, and
+/// when the corner is flipped they are on the opposite sides of it. Rather than moving the centres around to match
+/// on every call, `SOLVED_CENTRES[c]` holds the down centre coordinate that matches corner state `c`, worked out
+/// once for all 11,520 corner states with `get_down_centre_coord_for_matched_triples`. The check is then a single
+/// lookup, which matters because the search makes it for every state it reaches.
+///
+/// Only one down centre coordinate matches each corner state because the three centres of a colour are identical,
+/// so swapping centres of the same colour doesn't change the coordinate.
 pub fn do_triple_centres_match_corners(corners: u32, down_centres: u32) -> bool {
     SOLVED_CENTRES[corners as usize] == down_centres
 }
@@ -302,6 +625,22 @@ where T: Copy + Clone
     }
 }
 
+/// The same as `apply_raw_permutation`, but for fixed size arrays, so the copy of the original state can be kept
+/// on the stack instead of being allocated.
+pub fn apply_raw_permutation_buffered<T, const N: usize>(state: &mut [T; N], effect: &[u8; N])
+where T: Copy
+{
+    let orig_state: [T; N] = *state;
+
+    for i in 0..N {
+        state[i] = orig_state[effect[i] as usize];
+    }
+}
+
+pub(crate) fn as_fixed_size<const N: usize>(pieces: &mut [u8]) -> &mut [u8; N] {
+    pieces.try_into().expect("the state should have as many pieces as the turn moves")
+}
+
 pub fn apply_orientation(state: &mut u8, perm_effect: &[u8], orient_effect: &u8) {
     let mut flip_state = flip_num_to_bool_array(state);
     apply_raw_permutation::<bool>(&mut flip_state, perm_effect);
@@ -342,6 +681,87 @@ fn flip_bool_array_to_num(state: &[bool]) -> u8 {
     num
 }
 
+fn is_even_permutation_of_size(perm: &[u8], size: usize) -> bool {
+    if perm.len() != size {
+        return false;
+    }
+    let mut seen = vec![false; size];
+    for piece in perm {
+        match seen.get_mut(*piece as usize) {
+            Some(seen_piece) if !*seen_piece => *seen_piece = true,
+            _ => return false,
+        }
+    }
+    is_even_parity(perm)
+}
+
+fn are_valid_centres(centres: &[u8]) -> bool {
+    if centres.len() != NUM_CENTRES {
+        return false;
+    }
+    let mut counts = [0; NUM_CENTRES];
+    for centre in centres {
+        if *centre as usize >= NUM_CENTRES || centre % 3 != 0 {
+            return false;
+        }
+        counts[*centre as usize] += 1;
+    }
+    counts.iter().step_by(3).all(|count| *count == 3)
+}
+
+/// Mostly states that can be reached, made by turning from solved, since those are what most code expects. The
+/// rest are reachable states with two pieces swapped or a corner flipped, which breaks them in the ways
+/// `validate` checks for, and arrays of any length and content.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RawState {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.ratio(1, 4)? {
+            return Ok(Self {
+                corners: u.arbitrary()?,
+                corner_orientation: u.arbitrary()?,
+                edges: u.arbitrary()?,
+                up_centres: u.arbitrary()?,
+                down_centres: u.arbitrary()?,
+            });
+        }
+        let mut state = Self::solved();
+        let turns: Vec<Turn> = u.arbitrary()?;
+        for turn in turns.iter() {
+            state.apply(turn);
+        }
+        if u.ratio(1, 3)? {
+            let corner = u.choose_index(NUM_CORNERS)?;
+            state.corner_orientation ^= 1 << corner;
+        }
+        if u.ratio(1, 3)? {
+            let pieces = match u.choose_index(4)? {
+                0 => &mut state.corners,
+                1 => &mut state.edges,
+                2 => &mut state.up_centres,
+                _ => &mut state.down_centres,
+            };
+            let (first, second) = (u.choose_index(pieces.len())?, u.choose_index(pieces.len())?);
+            pieces.swap(first, second);
+        }
+        Ok(state)
+    }
+}
+
+/// Coordinates anywhere in their range or a little past the end of it, where off by one mistakes would show up.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CoordState {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut coord = |coord_type: CoordinateType| u.int_in_range(0..=coord_type.get_size() as u32 + 2);
+        Ok(Self {
+            corners: coord(CoordinateType::CornerState)?,
+            edges_within_faces: coord(CoordinateType::EdgeInFace)?,
+            edges_across_faces: coord(CoordinateType::EdgeAcrossFaces)?,
+            up_centres: coord(CoordinateType::UpCentre)?,
+            down_centres: coord(CoordinateType::DownCentre)?,
+        })
+    }
+}
+
 fn precompute_solved_triple_centre_coords() -> [u32; NUM_CORNER_STATES] {
     let mut coord_map = [0; NUM_CORNER_STATES];
     // for coord in 0..NUM_CORNER_STATES {
@@ -355,11 +775,42 @@ fn precompute_solved_triple_centre_coords() -> [u32; NUM_CORNER_STATES] {
 
 #[cfg(test)]
 mod tests {
-    use crate::movedefs::Face;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use crate::movetables::get_test_move_tables;
 
     use super::*;
     use test_case::test_case;
 
+    #[test]
+    fn test_solved_coord_state() {
+        assert_eq!(CoordState::default(), SOLVED_COORD_STATE);
+        assert_eq!(CoordState::solved(), SOLVED_COORD_STATE);
+        assert_eq!(RawState::solved().to_coords(), SOLVED_COORD_STATE);
+    }
+
+    #[test]
+    fn test_warm_up_solved_centres_cache() {
+        warm_up_solved_centres_cache();
+        for corners in [0, 1, 3327, NUM_CORNER_STATES as u32 - 1] {
+            let down_centres = get_down_centre_coord_for_matched_triples(corners);
+            assert!(do_triple_centres_match_corners(corners, down_centres));
+            assert!(!do_triple_centres_match_corners(corners, (down_centres + 1) % NUM_FACE_PIECE_PERMS as u32));
+        }
+    }
+
+    #[test_case(&[], (0, 0, 0) ; "solved")]
+    #[test_case(&[Turn::new(Face::U, false)], (3, 3, 6) ; "one turn")]
+    #[test_case(&[Turn::new(Face::U, false), Turn::new(Face::U, true)], (0, 0, 0) ; "undone turn")]
+    #[test_case(&[Turn::new(Face::F, false), Turn::new(Face::R, false)], (4, 5, 12) ; "two turns")]
+    fn test_get_num_unsolved_pieces(turns: &[Turn], expected: (usize, usize, usize)) {
+        let mut state = RawState::solved();
+        for turn in turns {
+            state.apply(turn);
+        }
+        assert_eq!(state.get_num_unsolved_pieces(), expected);
+    }
+
     #[test_case(&[false, false, false, false, false, false], 0)]
     #[test_case(&[false, false, false, false, false, true], 1)]
     #[test_case(&[true, true, false, false, false, false], 0b110000)]
@@ -386,6 +837,17 @@ mod tests {
         assert_eq!(state, expected);
     }
 
+    #[test_case([0,1,2,3,4,5], [1,2,3,4,5,0])]
+    #[test_case([3,2,1,4,5,0], [5,3,4,2,0,1])]
+    #[test_case([1,1,0,0,2,2], [2,0,1,5,3,4])]
+    fn test_apply_permutation_buffered_matches_unbuffered(start_state: [u8; 6], permutation: [u8; 6]) {
+        let mut buffered = start_state;
+        apply_raw_permutation_buffered(&mut buffered, &permutation);
+        let mut unbuffered = start_state;
+        apply_raw_permutation(&mut unbuffered, &permutation);
+        assert_eq!(buffered, unbuffered);
+    }
+
     #[test_case(0, &[0,1,2,3,4,5], &0, 0)]
     #[test_case(0, &[0,1,2,3,4,5], &0b000101, 0b000101)]
     #[test_case(0, &[0,1,2,3,4,5], &0b111001, 0b111001)]
@@ -412,7 +874,7 @@ mod tests {
 
     #[test]
     fn test_to_raw() {
-        let mut coord_state = CoordState::solved();
+        let mut coord_state = SOLVED_COORD_STATE;
         let mut raw_state = coord_state.to_raw();
         let mut expected = RawState::solved();
         assert_eq!(raw_state, expected);
@@ -428,7 +890,7 @@ mod tests {
     fn test_to_coord() {
         let mut raw_state = RawState::solved();
         let mut coord_state = raw_state.to_coords();
-        let mut expected = CoordState::solved();
+        let mut expected = SOLVED_COORD_STATE;
         assert_eq!(coord_state, expected);
 
         raw_state.corner_orientation = 33;
@@ -474,4 +936,336 @@ mod tests {
 
         assert_eq!(state, expected);
     }
+
+    #[test]
+    fn test_validate_solved() {
+        assert_eq!(RawState::solved().validate(), Ok(()));
+        assert!(RawState::solved().is_valid());
+    }
+
+    #[test]
+    fn test_validate_scrambled() {
+        let mut state = RawState::solved();
+        state.apply_sequence(&[
+            &Turn::new(Face::R, false),
+            &Turn::new(Face::BL, true),
+            &Turn::new(Face::D, false),
+        ]);
+        assert_eq!(state.validate(), Ok(()));
+    }
+
+    #[test_case(&[1,0,2,3,4,5], 0, &[0,1,2,3,4,5,6,7,8,9,10,11], &[0,0,0,3,3,3,6,6,6,9,9,9], StateError::InvalidCornerPermutation)]
+    #[test_case(&[0,0,2,3,4,5], 0, &[0,1,2,3,4,5,6,7,8,9,10,11], &[0,0,0,3,3,3,6,6,6,9,9,9], StateError::InvalidCornerPermutation)]
+    #[test_case(&[0,1,2,3,4,5], 1, &[0,1,2,3,4,5,6,7,8,9,10,11], &[0,0,0,3,3,3,6,6,6,9,9,9], StateError::InvalidCornerOrientation)]
+    #[test_case(&[0,1,2,3,4,5], 0, &[1,0,2,3,4,5,6,7,8,9,10,11], &[0,0,0,3,3,3,6,6,6,9,9,9], StateError::InvalidEdgePermutation)]
+    #[test_case(&[0,1,2,3,4,5], 0, &[0,1,2,3,4,5,6,7,8,9,10,12], &[0,0,0,3,3,3,6,6,6,9,9,9], StateError::InvalidEdgePermutation)]
+    #[test_case(&[0,1,2,3,4,5], 0, &[0,1,2,3,4,5,6,7,8,9,10,11], &[0,0,0,0,3,3,6,6,6,9,9,9], StateError::InvalidUpCentres)]
+    fn test_validate_invalid(corners: &[u8], corner_orientation: u8, edges: &[u8], up_centres: &[u8], expected: StateError) {
+        let state = RawState::new(corners, corner_orientation, edges, up_centres, &[0,0,0,3,3,3,6,6,6,9,9,9]);
+        assert_eq!(state.validate(), Err(expected));
+    }
+
+    #[test]
+    fn test_try_to_raw() {
+        let mut raw_state = RawState::solved();
+        raw_state.apply(&Turn::new(Face::F, false));
+        let coord_state = raw_state.to_coords();
+        assert_eq!(coord_state.try_to_raw(), Ok(raw_state));
+    }
+
+    #[test]
+    fn test_try_to_raw_out_of_range() {
+        let mut coord_state = SOLVED_COORD_STATE;
+        coord_state.up_centres = NUM_FACE_PIECE_PERMS as u32;
+        assert_eq!(coord_state.try_to_raw(), Err(StateError::CoordinateOutOfRange {
+            coord_type: CoordinateType::UpCentre,
+            value: NUM_FACE_PIECE_PERMS as u32,
+        }));
+    }
+
+    #[test_case(CoordinateType::CornerState ; "corners")]
+    #[test_case(CoordinateType::EdgeInFace ; "edges within faces")]
+    #[test_case(CoordinateType::EdgeAcrossFaces ; "edges across faces")]
+    #[test_case(CoordinateType::UpCentre ; "up centres")]
+    #[test_case(CoordinateType::DownCentre ; "down centres")]
+    fn test_coord_state_validate(coord_type: CoordinateType) {
+        let set_coord = |value: u32| {
+            let mut state = SOLVED_COORD_STATE;
+            match coord_type {
+                CoordinateType::CornerState => state.corners = value,
+                CoordinateType::EdgeInFace => state.edges_within_faces = value,
+                CoordinateType::EdgeAcrossFaces => state.edges_across_faces = value,
+                CoordinateType::UpCentre => state.up_centres = value,
+                CoordinateType::DownCentre => state.down_centres = value,
+                CoordinateType::TripleCentre => unreachable!(),
+            }
+            state
+        };
+        let largest = coord_type.get_size() as u32 - 1;
+        assert!(set_coord(largest).is_valid());
+        assert!(!set_coord(largest + 1).is_valid());
+        assert_eq!(set_coord(u32::MAX).validate(), Err(StateError::CoordinateOutOfRange { coord_type, value: u32::MAX }));
+    }
+
+    #[test]
+    fn test_try_to_raw_inconsistent_edges() {
+        // The edge coordinates are each valid on their own, but do not combine into a permutation
+        let mut coord_state = SOLVED_COORD_STATE;
+        coord_state.edges_within_faces = 1;
+        assert_eq!(coord_state.try_to_raw(), Err(StateError::InvalidEdgePermutation));
+    }
+
+    #[test]
+    fn test_phase1_heuristic() {
+        use crate::search::solve_phase_1;
+
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::EdgeInFace);
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::UpCentre);
+        assert_eq!(SOLVED_COORD_STATE.phase1_heuristic(&pruning_tables), 0);
+
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..20 {
+            let state = SOLVED_COORD_STATE.perturb(move_tables, 5, &mut rng);
+            let expected = pruning_tables.get_all_distances(CoordinateType::EdgeInFace)[state.edges_within_faces as usize]
+                .max(pruning_tables.get_all_distances(CoordinateType::UpCentre)[state.up_centres as usize]);
+            assert_eq!(state.phase1_heuristic(&pruning_tables), expected);
+
+            let solution = solve_phase_1(&state, move_tables, &pruning_tables, 5, true).unwrap();
+            assert!(state.phase1_heuristic(&pruning_tables) as usize <= solution.len());
+        }
+    }
+
+    #[test]
+    fn test_perturb_with_no_moves_is_unchanged() {
+        let move_tables = get_test_move_tables();
+        let mut rng = StdRng::seed_from_u64(1);
+        let state = SOLVED_COORD_STATE.perturb(move_tables, 5, &mut rng);
+        assert_eq!(state.perturb(move_tables, 0, &mut rng), state);
+    }
+
+    #[test]
+    fn test_perturb_by_one_move() {
+        let move_tables = get_test_move_tables();
+        let mut rng = StdRng::seed_from_u64(2);
+        let perturbed = SOLVED_COORD_STATE.perturb(move_tables, 1, &mut rng);
+
+        let matches_a_turn = Turn::get_all_turns().iter().any(|turn| {
+            let mut state = SOLVED_COORD_STATE;
+            state.apply(move_tables, turn);
+            state == perturbed
+        });
+        assert!(matches_a_turn);
+    }
+
+    #[test]
+    fn test_all_successors() {
+        let move_tables = get_test_move_tables();
+        let state = SOLVED_COORD_STATE.perturb(move_tables, 6, &mut StdRng::seed_from_u64(5));
+        let successors: Vec<(Turn, CoordState)> = state.all_successors(move_tables).collect();
+
+        assert_eq!(successors.iter().map(|(turn, _)| *turn).collect::<Vec<Turn>>(), Turn::get_all_turns());
+        for (turn, successor) in successors {
+            assert_eq!(Some(successor), state.apply_checked(move_tables, &turn));
+        }
+    }
+
+    #[test]
+    fn test_try_to_coords() {
+        let state = SOLVED_COORD_STATE.perturb(get_test_move_tables(), 8, &mut StdRng::seed_from_u64(4));
+        assert_eq!(state.to_raw().try_to_coords(), Ok(state));
+        let mut swapped = state.to_raw();
+        swapped.edges.swap(0, 1);
+        assert_eq!(swapped.try_to_coords(), Err(StateError::InvalidEdgePermutation));
+        assert_eq!(RawState::new(&[0, 1], 0, &[], &[], &[]).try_to_coords(), Err(StateError::InvalidCornerPermutation));
+    }
+
+    // The checked conversions have to cope with anything the fuzz targets can generate
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_checked_conversions_on_arbitrary_states() {
+        use arbitrary::{Arbitrary, Unstructured};
+        use rand::RngCore;
+
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut valid = 0;
+        for _ in 0..2000 {
+            let mut bytes = vec![0; 256];
+            rng.fill_bytes(&mut bytes);
+            let mut u = Unstructured::new(&bytes);
+            let (raw, coords) = <(RawState, CoordState)>::arbitrary(&mut u).unwrap();
+            if let Ok(raw_coords) = raw.try_to_coords() {
+                assert_eq!(raw_coords.to_raw(), raw);
+                valid += 1;
+            }
+            if let Ok(coords_raw) = coords.try_to_raw() {
+                assert_eq!(coords_raw.to_coords(), coords);
+            }
+        }
+        assert!(valid > 500, "{} valid states", valid);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let state = SOLVED_COORD_STATE.perturb(get_test_move_tables(), 8, &mut StdRng::seed_from_u64(3));
+        assert_eq!(CoordState::from_bytes(&state.to_bytes()), Ok(state));
+        assert_eq!(SOLVED_COORD_STATE.to_bytes(), [0; COORD_STATE_BYTES]);
+
+        assert_eq!(CoordState::from_bytes(&[0; 19]), Err(StateError::WrongByteCount(19)));
+        let mut out_of_range = state.to_bytes();
+        out_of_range[16..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(CoordState::from_bytes(&out_of_range),
+            Err(StateError::CoordinateOutOfRange { coord_type: CoordinateType::DownCentre, value: u32::MAX }));
+    }
+
+    #[test]
+    fn test_fully_solved_predicates() {
+        let move_tables = get_test_move_tables();
+        assert!(SOLVED_COORD_STATE.edges_fully_solved());
+        assert!(SOLVED_COORD_STATE.corners_fully_solved());
+        assert!(SOLVED_COORD_STATE.centres_fully_solved());
+
+        // A U turn moves every kind of piece
+        let turned = SOLVED_COORD_STATE.after_clockwise_sequence(move_tables, &[Face::U]);
+        assert!(!turned.edges_fully_solved());
+        assert!(!turned.corners_fully_solved());
+        assert!(!turned.centres_fully_solved());
+
+        let edges_across_only = CoordState { edges_across_faces: 1, ..SOLVED_COORD_STATE };
+        assert!(!edges_across_only.edges_fully_solved());
+        assert!(edges_across_only.corners_fully_solved() && edges_across_only.centres_fully_solved());
+    }
+
+    #[test]
+    fn test_apply_checked_stays_in_range() {
+        let move_tables = get_test_move_tables();
+        let mut rng = StdRng::seed_from_u64(8);
+        for _ in 0..20 {
+            let state = SOLVED_COORD_STATE.perturb(move_tables, 10, &mut rng);
+            for turn in Turn::iter_all() {
+                assert!(state.apply_checked(move_tables, &turn).is_some(), "{:?} after {:?}", turn, state);
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_checked_catches_wrong_table() {
+        // The up centre table has far more coordinates than the corner one, so using it for the corners gives
+        // corner coordinates that are out of range
+        let mut move_tables = MoveTables::from_bytes(&get_test_move_tables().to_bytes()).unwrap();
+        let wrong_table = MoveTables::from_bytes(&get_test_move_tables().to_bytes()).unwrap()
+            .tables.remove(&CoordinateType::UpCentre).unwrap();
+        move_tables.tables.insert(CoordinateType::CornerState, wrong_table);
+
+        assert!(Turn::iter_all().any(|turn| SOLVED_COORD_STATE.apply_checked(&move_tables, &turn).is_none()));
+    }
+
+    #[test]
+    fn test_perturb_is_repeatable_and_valid() {
+        let move_tables = get_test_move_tables();
+        let start = SOLVED_COORD_STATE.perturb(move_tables, 20, &mut StdRng::seed_from_u64(3));
+
+        let first = start.perturb(move_tables, 2, &mut StdRng::seed_from_u64(4));
+        let second = start.perturb(move_tables, 2, &mut StdRng::seed_from_u64(4));
+        assert_eq!(first, second);
+        // Two non-redundant turns can never cancel out
+        assert_ne!(first, start);
+        assert!(first.try_to_raw().is_ok());
+    }
+
+    /// Pearson's chi-squared statistic for counts that should all be the same.
+    fn get_chi_squared(counts: &[usize]) -> f64 {
+        let expected = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+        counts.iter().map(|count| (*count as f64 - expected).powi(2) / expected).sum()
+    }
+
+    #[test]
+    fn test_random_solvable_is_valid() {
+        let move_tables = get_test_move_tables();
+        let mut pruning_tables = SimplePruningTable::init(&Face::get_all_faces());
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::EdgeInFace);
+        pruning_tables.populate_coordinate_type(move_tables, CoordinateType::UpCentre);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let state = RawState::random_solvable(&mut rng);
+            assert_eq!(state.validate(), Ok(()), "{:?}", state);
+            let coords = state.to_coords();
+            assert_eq!(coords.try_to_raw(), Ok(state));
+            // Solving phase 1 for a random state takes too long for a test, but the search never rules it out
+            assert!(coords.phase1_heuristic(&pruning_tables) < u8::MAX);
+        }
+    }
+
+    // The critical values are for a 0.1% chance of failing with a uniform distribution
+    #[test]
+    fn test_random_solvable_is_uniform() {
+        const SAMPLES: usize = 6000;
+        let mut corners = [[0; NUM_CORNERS]; NUM_CORNERS];
+        let mut flips = [[0; 2]; NUM_CORNERS];
+        let mut edges = [[0; NUM_EDGES]; NUM_EDGES];
+        let mut up_centres = [[0; 4]; NUM_CENTRES];
+        let mut down_centres = [[0; 4]; NUM_CENTRES];
+
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..SAMPLES {
+            let state = RawState::random_solvable(&mut rng);
+            let flipped = flip_num_to_bool_array(&state.corner_orientation);
+            for position in 0..NUM_CORNERS {
+                corners[position][state.corners[position] as usize] += 1;
+                flips[position][flipped[position] as usize] += 1;
+            }
+            for position in 0..NUM_EDGES {
+                edges[position][state.edges[position] as usize] += 1;
+            }
+            for position in 0..NUM_CENTRES {
+                up_centres[position][state.up_centres[position] as usize / 3] += 1;
+                down_centres[position][state.down_centres[position] as usize / 3] += 1;
+            }
+        }
+
+        assert!(corners.iter().all(|counts| get_chi_squared(counts) < 20.52), "{:?}", corners);
+        assert!(flips.iter().all(|counts| get_chi_squared(counts) < 10.83), "{:?}", flips);
+        assert!(edges.iter().all(|counts| get_chi_squared(counts) < 31.26), "{:?}", edges);
+        assert!(up_centres.iter().all(|counts| get_chi_squared(counts) < 16.27), "{:?}", up_centres);
+        assert!(down_centres.iter().all(|counts| get_chi_squared(counts) < 16.27), "{:?}", down_centres);
+    }
+
+    #[test]
+    fn test_get_random_with_rng_is_repeatable_and_valid() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let states: Vec<CoordState> = (0..200).map(|_| CoordState::get_random_with_rng(&mut rng)).collect();
+        assert!(states.iter().all(|state| state.try_to_raw().is_ok()));
+        assert_ne!(states[0], states[1]);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(CoordState::get_random_with_rng(&mut rng), states[0]);
+    }
+
+    #[test]
+    fn test_apply_clockwise_sequence() {
+        let move_tables = get_test_move_tables();
+        let faces = [Face::R, Face::U, Face::BL, Face::D];
+
+        let mut expected = SOLVED_COORD_STATE;
+        for face in faces {
+            expected.apply(move_tables, &Turn::new(face, false));
+        }
+
+        let mut state = SOLVED_COORD_STATE;
+        state.apply_clockwise_sequence(move_tables, &faces);
+        assert_eq!(state, expected);
+        assert_eq!(SOLVED_COORD_STATE.after_clockwise_sequence(move_tables, &faces), expected);
+    }
+
+    #[test]
+    fn test_two_clockwise_turns_are_one_anticlockwise() {
+        let move_tables = get_test_move_tables();
+        let state = SOLVED_COORD_STATE.after_clockwise_sequence(move_tables, &[Face::F, Face::F]);
+
+        let mut expected = SOLVED_COORD_STATE;
+        expected.apply(move_tables, &Turn::new(Face::F, true));
+        assert_eq!(state, expected);
+    }
 }
@@ -0,0 +1,111 @@
+/*
+Totals of the time spent in each phase of the work, so that a caller can report where the time went. Each run keeps
+its own `Timings` and passes it to whatever it wants timed, so runs on different threads don't add to each other's
+totals. Browsers have no clock that `Instant` can use, so nothing is recorded when built for WebAssembly.
+*/
+
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Loading or generating the move tables.
+    TableLoad,
+    /// Loading or populating the pruning tables.
+    Pruning,
+    Search,
+    /// Drawing states as SVG or PNG images.
+    Render,
+}
+
+impl Phase {
+    pub fn iter() -> impl Iterator<Item = Phase> {
+        [Phase::TableLoad, Phase::Pruning, Phase::Search, Phase::Render].into_iter()
+    }
+
+    /// The name used for the phase in timing reports, which gives the time in milliseconds.
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Phase::TableLoad => "table_load_ms",
+            Phase::Pruning => "pruning_ms",
+            Phase::Search => "search_ms",
+            Phase::Render => "render_ms",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timings {
+    pub table_load: Duration,
+    pub pruning: Duration,
+    pub search: Duration,
+    pub render: Duration,
+}
+
+impl Timings {
+    pub fn get(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::TableLoad => self.table_load,
+            Phase::Pruning => self.pruning,
+            Phase::Search => self.search,
+            Phase::Render => self.render,
+        }
+    }
+
+    fn get_mut(&mut self, phase: Phase) -> &mut Duration {
+        match phase {
+            Phase::TableLoad => &mut self.table_load,
+            Phase::Pruning => &mut self.pruning,
+            Phase::Search => &mut self.search,
+            Phase::Render => &mut self.render,
+        }
+    }
+
+    pub fn add(&mut self, phase: Phase, duration: Duration) {
+        *self.get_mut(phase) += duration;
+    }
+
+    /// Runs `f`, adding the time it took to the total for `phase`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn time<T>(&mut self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(phase, start.elapsed());
+        result
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn time<T>(&mut self, _phase: Phase, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_adds_to_the_phase() {
+        let mut timings = Timings::default();
+        let result = timings.time(Phase::Render, || {
+            std::thread::sleep(Duration::from_millis(5));
+            42
+        });
+        timings.add(Phase::Pruning, Duration::from_millis(3));
+        timings.add(Phase::Pruning, Duration::from_millis(4));
+
+        assert_eq!(result, 42);
+        assert!(timings.render >= Duration::from_millis(5));
+        assert_eq!(timings.pruning, Duration::from_millis(7));
+        assert_eq!(timings.table_load, Duration::ZERO);
+        assert_eq!(timings.search, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_phase_names() {
+        let names: Vec<&str> = Phase::iter().map(Phase::get_name).collect();
+        assert_eq!(names, ["table_load_ms", "pruning_ms", "search_ms", "render_ms"]);
+    }
+}
@@ -0,0 +1,176 @@
+/*
+Checks that a solution really solves a scramble, by replaying both on a raw state. Raw states need no tables, so
+this is independent of the move tables the solver uses, which is what makes it useful for testing solver changes.
+*/
+
+use std::fmt;
+
+use crate::coordinates::{CoordinateType, get_down_centre_coord_for_matched_triples};
+use crate::movedefs::{Face, ParseTurnError, Turn, get_state_sticker_faces, get_sticker_position_face};
+use crate::state::{CoordState, RawState};
+
+
+/// What a solution has to reach for `verify_solution_for_goal` to accept it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerifyGoal {
+    /// Every piece back where it started.
+    #[default]
+    Solved,
+    /// Every face a single colour, even if the puzzle as a whole is turned from where it started.
+    SolvedUpToRotation,
+    /// The goal of phase 1 of the search: edges within faces and up centres solved, and every corner with the
+    /// down centres that match it.
+    Phase1,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyFailure {
+    Scramble(ParseTurnError),
+    Solution(ParseTurnError),
+    /// After `moves` turns of the solution, the first coordinate found not to be at its goal.
+    WrongCoordinate { moves: usize, coord_type: CoordinateType, value: u32, expected: u32 },
+    /// After `moves` turns of the solution, the first face found with more than one colour on it.
+    MixedFace { moves: usize, face: Face },
+}
+
+impl fmt::Display for VerifyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scramble(error) => write!(f, "could not read the scramble: {}", error),
+            Self::Solution(error) => write!(f, "could not read the solution: {}", error),
+            Self::WrongCoordinate { moves, coord_type, value, expected } => write!(f,
+                "after move {} the {:?} coordinate is {}, expected {}", moves, coord_type, value, expected),
+            Self::MixedFace { moves, face } => write!(f, "after move {} the {:?} face has more than one colour", moves, face),
+        }
+    }
+}
+
+impl std::error::Error for VerifyFailure {}
+
+
+/// Checks that the solution, applied after the scramble, solves the puzzle.
+pub fn verify_solution(scramble: &str, solution: &str) -> Result<(), VerifyFailure> {
+    verify_solution_for_goal(scramble, solution, VerifyGoal::Solved)
+}
+
+pub fn verify_solution_for_goal(scramble: &str, solution: &str, goal: VerifyGoal) -> Result<(), VerifyFailure> {
+    let scramble = Turn::parse_sequence(scramble).map_err(VerifyFailure::Scramble)?;
+    let solution = Turn::parse_sequence(solution).map_err(VerifyFailure::Solution)?;
+
+    let mut state = RawState::solved();
+    for turn in scramble.iter().chain(solution.iter()) {
+        state.apply(turn);
+    }
+    check_goal(&state, goal, solution.len())
+}
+
+fn check_goal(state: &RawState, goal: VerifyGoal, moves: usize) -> Result<(), VerifyFailure> {
+    match goal {
+        VerifyGoal::Solved => check_coords(&state.to_coords(), &CoordinateType::iter().collect::<Vec<_>>(), moves),
+        VerifyGoal::SolvedUpToRotation => check_faces(state, moves),
+        VerifyGoal::Phase1 => check_coords(&state.to_coords(),
+            &[CoordinateType::EdgeInFace, CoordinateType::UpCentre, CoordinateType::DownCentre], moves),
+    }
+}
+
+fn check_coords(coords: &CoordState, coord_types: &[CoordinateType], moves: usize) -> Result<(), VerifyFailure> {
+    for coord_type in coord_types.iter().copied() {
+        let (value, expected) = match coord_type {
+            CoordinateType::CornerState => (coords.corners, 0),
+            CoordinateType::EdgeInFace => (coords.edges_within_faces, 0),
+            CoordinateType::EdgeAcrossFaces => (coords.edges_across_faces, 0),
+            CoordinateType::UpCentre => (coords.up_centres, 0),
+            // Only phase 1 checks the down centres without the corners, and then they only have to match them
+            CoordinateType::DownCentre if !coord_types.contains(&CoordinateType::CornerState) =>
+                (coords.down_centres, get_down_centre_coord_for_matched_triples(coords.corners)),
+            CoordinateType::DownCentre => (coords.down_centres, 0),
+            CoordinateType::TripleCentre => unreachable!("coordinate states have no triple centre coordinate"),
+        };
+        if value != expected {
+            return Err(VerifyFailure::WrongCoordinate { moves, coord_type, value, expected });
+        }
+    }
+    Ok(())
+}
+
+fn check_faces(state: &RawState, moves: usize) -> Result<(), VerifyFailure> {
+    check_sticker_faces(&get_state_sticker_faces(state), moves)
+}
+
+fn check_sticker_faces(sticker_faces: &[(&str, u8)], moves: usize) -> Result<(), VerifyFailure> {
+    let mut face_colours: [Option<u8>; 8] = [None; 8];
+    for &(name, colour) in sticker_faces.iter() {
+        let position = get_sticker_position_face(name) as usize;
+        if *face_colours[position].get_or_insert(colour) != colour {
+            return Err(VerifyFailure::MixedFace { moves, face: get_face(position as u8) });
+        }
+    }
+    Ok(())
+}
+
+/// Converts one of the drawing face constants, which are in a different order to `Face::to_index`.
+fn get_face(position: u8) -> Face {
+    [Face::U, Face::F, Face::BL, Face::BR, Face::D, Face::B, Face::L, Face::R][position as usize]
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("R U BL'", "BL U' R'" ; "inverse")]
+    #[test_case("", "" ; "nothing to solve")]
+    #[test_case("R", "R R" ; "three turns of one face")]
+    fn test_verify_solution_correct(scramble: &str, solution: &str) {
+        assert_eq!(verify_solution(scramble, solution), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_solution_one_move_short() {
+        let failure = verify_solution("R U BL'", "BL U'").unwrap_err();
+        assert!(matches!(failure, VerifyFailure::WrongCoordinate { moves: 2, expected: 0, .. }));
+        assert!(failure.to_string().starts_with("after move 2 the "));
+    }
+
+    #[test]
+    fn test_verify_solution_notation_errors() {
+        let expected = VerifyFailure::Scramble(ParseTurnError { token: String::from("R2") });
+        assert_eq!(verify_solution("U R2", "R"), Err(expected));
+        let failure = verify_solution("U", "U' x").unwrap_err();
+        assert_eq!(failure, VerifyFailure::Solution(ParseTurnError { token: String::from("x") }));
+        assert_eq!(failure.to_string(), "could not read the solution: unrecognised turn 'x'");
+    }
+
+    #[test]
+    fn test_verify_solution_up_to_rotation() {
+        assert_eq!(verify_solution_for_goal("R U", "U' R'", VerifyGoal::SolvedUpToRotation), Ok(()));
+        assert_eq!(verify_solution_for_goal("R", "", VerifyGoal::SolvedUpToRotation),
+            Err(VerifyFailure::MixedFace { moves: 0, face: Face::U }));
+    }
+
+    // The half turn is found from the positions of the stickers in the drawing template
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_check_sticker_faces_accepts_rotated_puzzle() {
+        use crate::drawstate::get_half_turn_sticker_faces;
+
+        // The second view of the default layout is the puzzle turned over, so every face has a new colour
+        let solved = get_state_sticker_faces(&RawState::solved());
+        let rotated = get_half_turn_sticker_faces(&solved);
+        assert_ne!(rotated.iter().find(|(name, _)| *name == "cent-UBL"), solved.iter().find(|(name, _)| *name == "cent-UBL"));
+        assert_eq!(check_sticker_faces(&rotated, 0), Ok(()));
+
+        let mut turned = RawState::solved();
+        turned.apply(&Turn::new(Face::F, false));
+        let rotated = get_half_turn_sticker_faces(&get_state_sticker_faces(&turned));
+        assert!(matches!(check_sticker_faces(&rotated, 1), Err(VerifyFailure::MixedFace { moves: 1, .. })));
+    }
+
+    #[test]
+    fn test_verify_solution_phase1() {
+        assert!(verify_solution("R U", "U' R'").is_ok());
+        assert_eq!(verify_solution_for_goal("U R' BL", "BL' R", VerifyGoal::Phase1), Ok(()));
+        assert!(verify_solution_for_goal("U R' BL", "BL'", VerifyGoal::Phase1).is_err());
+    }
+}
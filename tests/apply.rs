@@ -0,0 +1,64 @@
+/*
+Runs the built binary's apply command, to check that states written with --out-json can be read back with
+--state-json, and that the text drawing shows the turns applied. None of these need any tables.
+*/
+
+use std::path::PathBuf;
+use std::process::Command;
+
+
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_fto-scramble"))
+        .args(args)
+        .output()
+        .expect("the binary should run");
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn make_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fto-scramble-apply-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn get_coordinates(json: &str) -> serde_json::Value {
+    let entry: serde_json::Value = serde_json::from_str(json).unwrap();
+    entry["coordinates"].clone()
+}
+
+#[test]
+fn test_json_in_json_out() {
+    let dir = make_temp_dir("json");
+    let scrambled = dir.join("scrambled.json").to_string_lossy().into_owned();
+    let undone = dir.join("undone.json").to_string_lossy().into_owned();
+
+    let output = run(&["apply", "--scramble", "R U BL'", "--out-json", &scrambled]);
+    assert_eq!(output, format!("Saved {}\n", scrambled));
+    let scrambled_json = std::fs::read_to_string(&scrambled).unwrap();
+    assert_eq!(get_coordinates(&scrambled_json), get_coordinates(&run(&["apply", "--scramble", "R U BL'"])));
+
+    run(&["apply", "--state-json", &scrambled, "--scramble", "BL U' R'", "--out-json", &undone]);
+    let undone_json = std::fs::read_to_string(&undone).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(get_coordinates(&undone_json), get_coordinates(&run(&["apply", "--scramble", ""])));
+    assert_ne!(get_coordinates(&undone_json), get_coordinates(&scrambled_json));
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn test_ascii_after_u() {
+    let expected = concat!(
+        "                 F\n",
+        "                FFF\n",
+        "               FFFFS\n",
+        "  URRLLLPDDDDDSRRRBB\n",
+        " UUURRLPPPDDDSSSRBB\n",
+        "UUUUURFPPPPDSSSSPB\n",
+        "LLLLL\n",
+        " BBB\n",
+        "  B\n",
+    );
+    assert_eq!(run(&["apply", "--scramble", "U", "--ascii"]), expected);
+}
@@ -0,0 +1,26 @@
+/*
+Checks that the library and the web example still build for WebAssembly, so that filesystem code that isn't gated
+off for it is caught without a CI job for that target. The check needs the wasm32-unknown-unknown target, so it is
+ignored by default. Install the target with `rustup target add wasm32-unknown-unknown` and run the check with
+`cargo test --test build -- --ignored`.
+*/
+
+use std::path::Path;
+use std::process::Command;
+
+
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+#[test]
+#[ignore = "needs the wasm32-unknown-unknown target"]
+fn test_wasm_check() {
+    // A target directory of its own, so the check doesn't wait on the lock held by the build running the tests
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("wasm-check");
+    let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| String::from("cargo")))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["check", "--lib", "--example", "wasm_solver", "--all-features", "--target", WASM_TARGET, "--target-dir"])
+        .arg(&target_dir)
+        .output()
+        .expect("cargo should run");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
@@ -0,0 +1,56 @@
+/*
+Runs the built binary's draw command with a query string, as a web service wrapping it would, and checks the image
+against drawing the same state from its scramble.
+*/
+#![cfg(feature = "svg")]
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use fto_scramble::drawquery::RenderRequest;
+use fto_scramble::drawstate::{Layout, RenderOptions};
+use fto_scramble::movedefs::Turn;
+use fto_scramble::state::RawState;
+
+
+fn run(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_fto-scramble"))
+        .args(args)
+        .output()
+        .expect("the binary should run")
+}
+
+fn make_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fto-scramble-query-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_render_from_query() {
+    let dir = make_temp_dir("render");
+    let from_query = dir.join("query.svg").to_string_lossy().into_owned();
+    let from_scramble = dir.join("scramble.svg").to_string_lossy().into_owned();
+
+    let mut state = RawState::solved();
+    for turn in Turn::parse_sequence("R U BL'").unwrap().iter() {
+        state.apply(turn);
+    }
+    let query = RenderRequest::from_state(&state, RenderOptions { layout: Layout::Net, ..Default::default() })
+        .to_query_string();
+
+    assert!(run(&["draw", "--query", &query, "--out", &from_query]).status.success());
+    assert!(run(&["draw", "--scramble", "R U BL'", "--layout", "net", "--out", &from_scramble]).status.success());
+    let query_svg = std::fs::read_to_string(&from_query).unwrap();
+    let scramble_svg = std::fs::read_to_string(&from_scramble).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(query_svg, scramble_svg);
+}
+
+#[test]
+fn test_bad_facelet_reports_position() {
+    let query = format!("fd={}X", "U".repeat(71));
+    let output = run(&["draw", "--query", &query, "--out", "unused.svg"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unrecognised facelet 'X' at position 71"));
+}
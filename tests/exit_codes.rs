@@ -0,0 +1,83 @@
+/*
+Runs the built binary to check the exit code and error output of each kind of failure, as scripts see them. Only
+failures that are found before any tables are needed are covered here, so none of these have to generate tables.
+*/
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use test_case::test_case;
+
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fto-scramble"))
+        .args(args)
+        .output()
+        .expect("the binary should run")
+}
+
+fn make_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fto-scramble-exit-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Checks that the run failed with `code` and a single `error: …` line on stderr.
+fn assert_error(output: &Output, code: i32) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(code), "stderr: {}", stderr);
+    assert_eq!(stderr.lines().count(), 1, "stderr: {}", stderr);
+    assert!(stderr.starts_with("error: "), "stderr: {}", stderr);
+}
+
+#[test_case(&["--help"]; "help")]
+#[test_case(&["verify", "--scramble", "R U", "--solution", "U' R'"]; "correct solution")]
+#[test_case(&["scramble", "--seed", "1"]; "scramble")]
+fn test_success(args: &[&str]) {
+    assert_eq!(run(args).status.code(), Some(0));
+}
+
+#[test_case(&["solve", "--no-such-flag"]; "unknown argument")]
+#[test_case(&["scramble", "--count", "18446744073709551615"]; "count out of range")]
+fn test_usage_error(args: &[&str]) {
+    let output = run(args);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+}
+
+#[test_case(&["solve", "--scramble", "R X"]; "bad scramble")]
+#[test_case(&["verify", "--scramble", "R", "--solution", "U2"]; "bad solution")]
+#[test_case(&["draw", "--scramble", "R", "--colours", "nonsense", "--out", "unused.svg"]; "unknown colours")]
+fn test_parse_error(args: &[&str]) {
+    assert_error(&run(args), 2);
+}
+
+#[test]
+fn test_wrong_solution() {
+    assert_error(&run(&["verify", "--scramble", "R", "--solution", "U"]), 3);
+}
+
+#[test]
+fn test_corrupt_tables() {
+    let dir = make_temp_dir("corrupt");
+    std::fs::write(dir.join("movetables.dat"), [0, 0, 0, 9, 1, 2, 3]).unwrap();
+    let output = run(&["solve", "--scramble", "R U", "--tables-dir", dir.to_str().unwrap()]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_error(&output, 4);
+}
+
+#[test]
+fn test_missing_input() {
+    assert_error(&run(&["solve", "--input", "/no/such/dir/scrambles.txt"]), 5);
+}
+
+// Drawing is the quickest way to write a file, so this needs the svg feature
+#[cfg(feature = "svg")]
+#[test]
+fn test_unwritable_output() {
+    let dir = make_temp_dir("unwritable");
+    let out = dir.join("missing").join("state.svg");
+    let output = run(&["draw", "--scramble", "R", "--out", out.to_str().unwrap()]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_error(&output, 5);
+}